@@ -0,0 +1,231 @@
+mod results;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::{
+    build::compiler::{execute_compiler, CompilerOptions},
+    compiler::version::check_compiler_version,
+    config::{BenchConfig, Runtime, SpagoConfig},
+    install::{install_with_extra_dependencies, InstallManager},
+    registry::{PackageName, PackageQuery, PackageSet},
+    run::{
+        entry_program,
+        runner::{self, Program},
+        RunArgs,
+    },
+    sources::generate_dependency_glob,
+};
+
+use results::{compare, parse_results, regressions, render_comparison, Baseline};
+
+pub const BENCH_SOURCES: &str = "./bench/**/*.purs";
+
+/// Bench options from the command line. The bench module comes from
+/// `package.bench.main`.
+#[derive(Debug, Default)]
+pub struct BenchArgs {
+    pub function: Option<String>,
+    pub runtime: Option<Runtime>,
+    pub node_args: Vec<String>,
+    pub program_args: Vec<String>,
+    /// Compare results against this saved baseline
+    pub baseline: Option<PathBuf>,
+    /// Save results as a baseline here
+    pub save_baseline: Option<PathBuf>,
+    /// Fail when a benchmark is this many percent slower than the baseline
+    pub max_regression: Option<f64>,
+}
+
+pub async fn execute(args: BenchArgs, skip_compilation: bool, verbose: bool) -> Result<()> {
+    let config = crate::config::load_config_cwd()?;
+
+    let message = if skip_compilation {
+        "Quick bench".dimmed()
+    } else {
+        "Bench".dimmed()
+    };
+    println!("{}", message);
+    println!();
+
+    let bench_config = config
+        .package
+        .bench
+        .as_ref()
+        .context("No benchmarks. Add a package.bench.")?;
+
+    // Read the baseline up front so a bad path fails before the slow part
+    let baseline = args.baseline.as_deref().map(Baseline::load).transpose()?;
+
+    if !skip_compilation {
+        compile(&config, bench_config, verbose).await?;
+    }
+
+    let program = bench_program(&config, bench_config, &args);
+    let captured = runner::run_captured(&config.run_output_dir(), &program, None, true).await?;
+    if captured.code != 0 {
+        println!();
+        eprintln!("❌ Benchmarks failed");
+        std::process::exit(captured.code);
+    }
+
+    let results = parse_results(&captured.stdout);
+    if verbose {
+        println!("{} Read {} benchmark results", "→".cyan(), results.len());
+    }
+
+    if let Some(path) = &args.save_baseline {
+        Baseline {
+            benchmarks: results.clone(),
+        }
+        .save(path)?;
+        println!();
+        println!("{} Saved baseline to {}", "✓".green(), path.display());
+    }
+
+    if let Some(baseline) = baseline {
+        let comparisons = compare(&baseline, &results);
+        println!();
+        print!("{}", render_comparison(&comparisons));
+
+        if let Some(max_regression) = args.max_regression {
+            let slower = regressions(&comparisons, max_regression);
+            if !slower.is_empty() {
+                println!();
+                eprintln!(
+                    "❌ {} benchmarks are more than {}% slower than the baseline",
+                    slower.len(),
+                    max_regression
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    println!();
+    println!("{}", "✓ Benchmarks finished".green());
+    Ok(())
+}
+
+/// Compile sources, benchmarks and the benchmark-only dependencies
+async fn compile(config: &SpagoConfig, bench_config: &BenchConfig, verbose: bool) -> Result<()> {
+    check_compiler_version(config, verbose).await?;
+    let package_set = config.package_set().await?;
+    install_with_extra_dependencies(config, &package_set, &bench_config.dependencies).await?;
+
+    let sources =
+        crate::sources::generate_sources(config, Some(package_set.clone()), false, false, verbose)
+            .await?;
+    let mut all_sources = sources
+        .dependency_globs
+        .iter()
+        .map(|g| g.glob_pattern.clone())
+        .collect::<Vec<String>>();
+
+    let already_included: HashSet<&str> = sources
+        .dependency_globs
+        .iter()
+        .map(|g| g.package_name.as_str())
+        .collect();
+    for package_name in bench_dependency_closure(config, &package_set, bench_config)? {
+        if package_name == config.package.name || already_included.contains(package_name.0.as_str())
+        {
+            continue;
+        }
+        if let Some(glob) =
+            generate_dependency_glob(&package_name, &config.spago_dir(), &package_set, verbose)?
+        {
+            all_sources.push(glob.glob_pattern);
+        }
+    }
+
+    if let Some(main) = &sources.main_sources {
+        all_sources.push(main.clone());
+    }
+    all_sources.push(BENCH_SOURCES.to_string());
+
+    execute_compiler(
+        &all_sources,
+        &CompilerOptions::from_config(config, verbose)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Benchmark dependencies and everything they depend on
+fn bench_dependency_closure(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+    bench_config: &BenchConfig,
+) -> Result<HashSet<PackageName>> {
+    let manager = InstallManager::new(&config.spago_dir())?;
+    let query = PackageQuery::new(package_set);
+    let mut all_dependencies = HashSet::new();
+    let mut processed = HashSet::new();
+    for dependency in &bench_config.dependencies {
+        manager.collect_dependencies_recursive(
+            dependency,
+            &query,
+            &mut all_dependencies,
+            &mut processed,
+        )?;
+    }
+    Ok(all_dependencies)
+}
+
+/// What to run for the benchmarks. The runtime falls back from the command
+/// line to `package.bench.runtime` then `package.run.runtime`.
+fn bench_program(config: &SpagoConfig, bench_config: &BenchConfig, args: &BenchArgs) -> Program {
+    let args = RunArgs {
+        module: None,
+        function: args.function.clone(),
+        runtime: args.runtime.clone(),
+        node_args: args.node_args.clone(),
+        program_args: args.program_args.clone(),
+    };
+    entry_program(
+        config,
+        &bench_config.main,
+        bench_config.runtime.as_ref(),
+        args,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spago.yaml");
+        std::fs::write(
+            &path,
+            "workspace: {}\npackage:\n  name: app\n  run:\n    runtime: bun\n  bench:\n    main: Bench.Main\n    dependencies: [minibench]\n",
+        )
+        .unwrap();
+        let config = crate::config::load_config(&path, false).unwrap();
+        let bench_config = config.package.bench.clone().unwrap();
+        assert_eq!(
+            config.bench_dependencies(),
+            vec![&PackageName::new("minibench")]
+        );
+
+        let program = bench_program(&config, &bench_config, &BenchArgs::default());
+        assert_eq!(program.module, "Bench.Main");
+        assert_eq!(program.function, "main");
+        assert_eq!(program.runtime, Runtime::Bun);
+
+        let args = BenchArgs {
+            function: Some("quick".to_string()),
+            runtime: Some(Runtime::Deno),
+            ..Default::default()
+        };
+        let program = bench_program(&config, &bench_config, &args);
+        assert_eq!(program.function, "quick");
+        assert_eq!(program.runtime, Runtime::Deno);
+    }
+}
@@ -0,0 +1,353 @@
+//! Benchmark timings read from purescript-minibench style output, and how
+//! they compare with a saved baseline.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::run::runner::strip_ansi;
+
+static STAT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(mean|stddev|min|max)\s*=\s*([0-9.eE+-]+)\s*(ns|μs|µs|us|ms|s)\s*$").unwrap()
+});
+static SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[-=*#\s]*$").unwrap());
+
+/// Changes smaller than this are reported as noise
+const NOISE_PERCENT: f64 = 5.0;
+
+/// One benchmark's timings, in nanoseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub mean: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stddev: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// Results saved with `--save-baseline`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub benchmarks: Vec<BenchResult>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+}
+
+/// Read the timings a benchmark printed. Each benchmark is a `mean = ...`
+/// line, optionally followed by `stddev`, `min` and `max`, and is named by
+/// the last other line printed before it.
+pub fn parse_results(output: &str) -> Vec<BenchResult> {
+    let output = strip_ansi(output);
+    let mut results: Vec<BenchResult> = Vec::new();
+    let mut label: Option<String> = None;
+    // Whether stats still belong to the last result
+    let mut in_result = false;
+
+    for line in output.lines() {
+        let Some(captures) = STAT_LINE.captures(line) else {
+            if !SEPARATOR.is_match(line) {
+                label = Some(line.trim().to_string());
+                in_result = false;
+            }
+            continue;
+        };
+
+        let Ok(value) = captures[2].parse::<f64>() else {
+            continue;
+        };
+        let nanoseconds = value * unit_factor(&captures[3]);
+
+        match &captures[1] {
+            "mean" => {
+                let name = unique_name(
+                    label
+                        .take()
+                        .unwrap_or_else(|| format!("benchmark {}", results.len() + 1)),
+                    &results,
+                );
+                results.push(BenchResult {
+                    name,
+                    mean: nanoseconds,
+                    stddev: None,
+                    min: None,
+                    max: None,
+                });
+                in_result = true;
+            }
+            stat if in_result => {
+                let result = results.last_mut().expect("in a result");
+                match stat {
+                    "stddev" => result.stddev = Some(nanoseconds),
+                    "min" => result.min = Some(nanoseconds),
+                    _ => result.max = Some(nanoseconds),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    results
+}
+
+fn unit_factor(unit: &str) -> f64 {
+    match unit {
+        "ns" => 1.0,
+        "μs" | "µs" | "us" => 1e3,
+        "ms" => 1e6,
+        _ => 1e9,
+    }
+}
+
+/// Repeated names get a counter so each can be compared on its own
+fn unique_name(name: String, results: &[BenchResult]) -> String {
+    let taken = |candidate: &str| results.iter().any(|r| r.name == candidate);
+    if !taken(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{} #{}", name, n))
+        .find(|candidate| !taken(candidate))
+        .expect("an unused name")
+}
+
+/// A benchmark's mean in the baseline and in this run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub name: String,
+    pub baseline: Option<f64>,
+    pub current: Option<f64>,
+}
+
+impl Comparison {
+    /// How much slower this run was, as a percentage of the baseline
+    pub fn change(&self) -> Option<f64> {
+        match (self.baseline, self.current) {
+            (Some(baseline), Some(current)) if baseline > 0.0 => {
+                Some((current - baseline) / baseline * 100.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Pair up results by name, in the order of this run with benchmarks only
+/// in the baseline last
+pub fn compare(baseline: &Baseline, current: &[BenchResult]) -> Vec<Comparison> {
+    let baseline_means: HashMap<&str, f64> = baseline
+        .benchmarks
+        .iter()
+        .map(|r| (r.name.as_str(), r.mean))
+        .collect();
+
+    let mut comparisons: Vec<Comparison> = current
+        .iter()
+        .map(|r| Comparison {
+            name: r.name.clone(),
+            baseline: baseline_means.get(r.name.as_str()).copied(),
+            current: Some(r.mean),
+        })
+        .collect();
+    comparisons.extend(
+        baseline
+            .benchmarks
+            .iter()
+            .filter(|b| !current.iter().any(|r| r.name == b.name))
+            .map(|b| Comparison {
+                name: b.name.clone(),
+                baseline: Some(b.mean),
+                current: None,
+            }),
+    );
+    comparisons
+}
+
+/// Benchmarks that got slower by more than `max_percent`
+pub fn regressions(comparisons: &[Comparison], max_percent: f64) -> Vec<&Comparison> {
+    comparisons
+        .iter()
+        .filter(|c| c.change().is_some_and(|change| change > max_percent))
+        .collect()
+}
+
+pub fn render_comparison(comparisons: &[Comparison]) -> String {
+    let mut out = String::from("Compared with baseline\n");
+    let width = comparisons.iter().map(|c| c.name.len()).max().unwrap_or(0);
+
+    for comparison in comparisons {
+        let detail = match (comparison.baseline, comparison.current) {
+            (Some(baseline), Some(current)) => {
+                let change = comparison.change().unwrap_or(0.0);
+                let percent = format!("{:+.1}%", change);
+                let percent = if change > NOISE_PERCENT {
+                    percent.red().to_string()
+                } else if change < -NOISE_PERCENT {
+                    percent.green().to_string()
+                } else {
+                    percent.dimmed().to_string()
+                };
+                format!(
+                    "{} → {} ({})",
+                    format_duration(baseline),
+                    format_duration(current),
+                    percent
+                )
+            }
+            (None, Some(current)) => format!("{} (new)", format_duration(current)),
+            (Some(baseline), None) => format!("{} (missing)", format_duration(baseline)),
+            (None, None) => continue,
+        };
+        let _ = writeln!(out, "  {:width$}  {}", comparison.name, detail);
+    }
+    out
+}
+
+/// Nanoseconds in the most readable unit
+pub fn format_duration(nanoseconds: f64) -> String {
+    if nanoseconds < 1e3 {
+        format!("{:.2} ns", nanoseconds)
+    } else if nanoseconds < 1e6 {
+        format!("{:.2} μs", nanoseconds / 1e3)
+    } else if nanoseconds < 1e9 {
+        format!("{:.2} ms", nanoseconds / 1e6)
+    } else {
+        format!("{:.2} s", nanoseconds / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, mean: f64) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            mean,
+            stddev: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_results() {
+        let output = "\
+---
+Array.sort
+---
+mean   = 12.5 μs
+stddev = 1.25 μs
+min    = 10.0 μs
+max    = 20.0 μs
+\x1b[32mList.sort\x1b[0m
+mean   = 3.5 ms
+stddev = 0.5 ms
+mean   = 900.0 ns
+List.sort
+mean   = 4.0 ms
+";
+        let results = parse_results(output);
+
+        assert_eq!(
+            results[0],
+            BenchResult {
+                name: "Array.sort".to_string(),
+                mean: 12500.0,
+                stddev: Some(1250.0),
+                min: Some(10000.0),
+                max: Some(20000.0),
+            }
+        );
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Array.sort", "List.sort", "benchmark 3", "List.sort #2"]
+        );
+        assert_eq!(results[1].mean, 3_500_000.0);
+        assert_eq!(results[2].mean, 900.0);
+    }
+
+    #[test]
+    fn test_compare_with_baseline() {
+        let baseline = Baseline {
+            benchmarks: vec![
+                result("fast", 1000.0),
+                result("slow", 1000.0),
+                result("gone", 1000.0),
+            ],
+        };
+        let current = vec![
+            result("fast", 800.0),
+            result("slow", 1200.0),
+            result("added", 50.0),
+        ];
+
+        let comparisons = compare(&baseline, &current);
+        let changes: Vec<(&str, Option<f64>)> = comparisons
+            .iter()
+            .map(|c| (c.name.as_str(), c.change()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("fast", Some(-20.0)),
+                ("slow", Some(20.0)),
+                ("added", None),
+                ("gone", None),
+            ]
+        );
+
+        let slower: Vec<&str> = regressions(&comparisons, 10.0)
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(slower, vec!["slow"]);
+        assert!(regressions(&comparisons, 25.0).is_empty());
+
+        colored::control::set_override(false);
+        let rendered = render_comparison(&comparisons);
+        assert!(rendered.contains("slow   1.00 μs → 1.20 μs (+20.0%)"));
+        assert!(rendered.contains("added  50.00 ns (new)"));
+        assert!(rendered.contains("gone   1.00 μs (missing)"));
+    }
+
+    #[test]
+    fn test_baseline_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bench/baseline.json");
+        let baseline = Baseline {
+            benchmarks: vec![BenchResult {
+                stddev: Some(2.0),
+                ..result("sort", 10.0)
+            }],
+        };
+
+        baseline.save(&path).unwrap();
+        assert_eq!(Baseline::load(&path).unwrap(), baseline);
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use tokio::process::Command;
+
+use crate::build::run_from_root::make_path_relative_to_workspace;
+use crate::config::BackendConfig;
+
+/// The compiler output directory the backend reads corefn from, relative to the workspace root
+const ENV_OUTPUT_DIR: &str = "RAGU_OUTPUT_DIR";
+/// Comma separated names of the modules purs recompiled in this build
+const ENV_CHANGED_MODULES: &str = "RAGU_CHANGED_MODULES";
+
+/// Run the configured backend over the compiler output.
+///
+/// The backend runs from the workspace root with its output inherited.
+/// With `json_errors` stdout only carries the diagnostics, so the
+/// backend's stdout goes to stderr. Returns whether the backend succeeded.
+pub async fn run_backend(
+    backend: &BackendConfig,
+    workspace_root: &Path,
+    output_dir: &Path,
+    changed_modules: &HashSet<String>,
+    json_errors: bool,
+    verbose: bool,
+) -> Result<bool> {
+    let relative_output = make_path_relative_to_workspace(output_dir, workspace_root)?;
+
+    let mut changed: Vec<&str> = changed_modules.iter().map(|m| m.as_str()).collect();
+    changed.sort_unstable();
+
+    if verbose {
+        println!(
+            "{} Running backend: {} {}",
+            "→".cyan(),
+            backend.cmd,
+            backend.args.join(" ")
+        );
+        println!("  Changed modules: {}", changed.len());
+    }
+
+    let status = Command::new(&backend.cmd)
+        .args(&backend.args)
+        .current_dir(workspace_root)
+        .env(ENV_OUTPUT_DIR, relative_output)
+        .env(ENV_CHANGED_MODULES, changed.join(","))
+        .stdout(if json_errors {
+            Stdio::from(std::io::stderr())
+        } else {
+            Stdio::inherit()
+        })
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .with_context(|| format!("Failed to start backend '{}'", backend.cmd))?;
+
+    Ok(status.success())
+}
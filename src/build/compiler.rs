@@ -1,25 +1,40 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use sysinfo::System;
+use sysinfo::{MemoryRefreshKind, RefreshKind, System};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
+use crate::build::backend;
+use crate::build::diagnostics::{CompilerOutput, DiagnosticSummary};
+use crate::build::fingerprint;
+use crate::build::psa;
 use crate::build::run_from_root::{
-    make_path_relative_to_workspace, map_diagnostic_paths_from_output_to_cwd,
+    make_path_relative_to_workspace, map_diagnostic_paths_from_output_to_cwd, map_source_map_paths,
     map_sources_to_output_dir,
 };
-use crate::config::PsaOptionsConfig;
+use crate::build::timings::RtsStats;
+use crate::compiler::toolchain::resolve_toolchain;
+use crate::config::{BackendConfig, PsaOptionsConfig, RtsConfig, SpagoConfig};
 
-const COMPILER_CMD_PSA: &str = "psa";
-const COMPILER_CMD_PURS: &str = "purs";
 const COMPILER_ARG_COMPILE: &str = "compile";
+const ARG_JSON_ERRORS: &str = "--json-errors";
+
+const ARG_CODEGEN: &str = "--codegen";
+const CODEGEN_JS: &str = "js";
+const CODEGEN_COREFN: &str = "corefn";
+const CODEGEN_SOURCEMAPS: &str = "sourcemaps";
 
 const RTS_FLAG_START: &str = "+RTS";
 const RTS_FLAG_END: &str = "-RTS";
 const RTS_ARG_STATS: &str = "-s";
 
+/// Replaces both the configured and the auto-tuned runtime settings,
+/// e.g. `RAGU_PURS_RTS="-A64m -N2 -M3g"`
+const ENV_PURS_RTS: &str = "RAGU_PURS_RTS";
+
 // High memory (>= 32GB) configuration
 // -A256m: Allocation area size (large young generation)
 // -n16m:  Nursery chunk size
@@ -32,28 +47,103 @@ const RTS_ARG_HIGH_MEM_NURSERY: &str = "-n16m";
 const RTS_ARG_MED_MEM_ALLOC: &str = "-A128m";
 const RTS_ARG_MED_MEM_NURSERY: &str = "-n8m";
 
-/// Build RTS (Runtime System) arguments for the compiler based on available memory.
-///
-/// The RTS arguments configure the Haskell runtime behavior for the PureScript compiler.
-/// We optimize these based on available system memory:
-///
-/// - **High Memory (>= 32GB)**: Uses 256MB allocation area (`-A256m`).
-/// - **Medium Memory (> 15GB)**: Uses 128MB allocation area (`-A128m`).
-/// - **Standard**: Uses default settings.
-///
-/// Also handles the `-s` flag for RTS statistics if requested.
-fn build_rts_args(total_memory_gb: u64, include_stats: bool) -> Vec<String> {
-    let mut rts_args = Vec::new();
+/// RTS (Runtime System) flags for the compiler, one per setting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RtsFlags {
+    allocation_area: Option<String>,
+    nursery: Option<String>,
+    threads: Option<String>,
+    max_heap: Option<String>,
+}
 
-    // Set memory parameters based on available RAM
-    if total_memory_gb > 31 {
-        rts_args.push(RTS_ARG_HIGH_MEM_ALLOC.to_string());
-        rts_args.push(RTS_ARG_HIGH_MEM_NURSERY.to_string());
-    } else if total_memory_gb > 15 {
-        rts_args.push(RTS_ARG_MED_MEM_ALLOC.to_string());
-        rts_args.push(RTS_ARG_MED_MEM_NURSERY.to_string());
+impl RtsFlags {
+    /// Defaults tuned for the memory and CPUs available to the compiler.
+    ///
+    /// - **High Memory (>= 32GB)**: Uses 256MB allocation area (`-A256m`).
+    /// - **Medium Memory (> 15GB)**: Uses 128MB allocation area (`-A128m`).
+    /// - **Standard**: Uses default settings.
+    ///
+    /// Parallelism is left to purs, `-N` is only passed when configured.
+    fn auto(total_memory_gb: u64) -> Self {
+        // Set memory parameters based on available RAM
+        let (allocation_area, nursery) = if total_memory_gb > 31 {
+            (Some(RTS_ARG_HIGH_MEM_ALLOC), Some(RTS_ARG_HIGH_MEM_NURSERY))
+        } else if total_memory_gb > 15 {
+            (Some(RTS_ARG_MED_MEM_ALLOC), Some(RTS_ARG_MED_MEM_NURSERY))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            allocation_area: allocation_area.map(String::from),
+            nursery: nursery.map(String::from),
+            threads: None,
+            max_heap: None,
+        }
     }
 
+    /// The settings from `workspace.buildOpts.rts`
+    fn from_config(rts: &RtsConfig) -> Self {
+        Self {
+            allocation_area: rts.allocation_area.as_ref().map(|a| format!("-A{}", a)),
+            nursery: rts.nursery.as_ref().map(|n| format!("-n{}", n)),
+            threads: rts.threads.map(|threads| match threads {
+                0 => "-N".to_string(),
+                threads => format!("-N{}", threads),
+            }),
+            max_heap: rts.max_heap.as_ref().map(|m| format!("-M{}", m)),
+        }
+    }
+
+    /// Fill in any unset settings from the defaults
+    fn or(self, defaults: Self) -> Self {
+        Self {
+            allocation_area: self.allocation_area.or(defaults.allocation_area),
+            nursery: self.nursery.or(defaults.nursery),
+            threads: self.threads.or(defaults.threads),
+            max_heap: self.max_heap.or(defaults.max_heap),
+        }
+    }
+
+    fn into_args(self) -> Vec<String> {
+        [
+            self.allocation_area,
+            self.nursery,
+            self.threads,
+            self.max_heap,
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+/// Pick the RTS flags for a compiler run.
+///
+/// `RAGU_PURS_RTS` wins outright. Otherwise `workspace.buildOpts.rts` is
+/// used, with anything it leaves unset auto-tuned.
+fn resolve_rts_flags(
+    configured: &RtsConfig,
+    env_override: Option<&str>,
+    total_memory_gb: u64,
+) -> Vec<String> {
+    if let Some(env_override) = env_override {
+        return env_override
+            .split_whitespace()
+            .filter(|flag| *flag != RTS_FLAG_START && *flag != RTS_FLAG_END)
+            .map(String::from)
+            .collect();
+    }
+
+    RtsFlags::from_config(configured)
+        .or(RtsFlags::auto(total_memory_gb))
+        .into_args()
+}
+
+/// Wrap RTS flags in `+RTS ... -RTS` for the compiler command line.
+///
+/// Also handles the `-s` flag for RTS statistics if requested.
+fn build_rts_args(mut rts_args: Vec<String>, include_stats: bool) -> Vec<String> {
     if include_stats {
         rts_args.push(RTS_ARG_STATS.to_string());
     }
@@ -67,97 +157,211 @@ fn build_rts_args(total_memory_gb: u64, include_stats: bool) -> Vec<String> {
     rts_args
 }
 
-/// Construct the compiler command, choosing between `psa` and `purs`.
+/// Construct the `purs compile` command.
 ///
-/// - If `psa` (PureScript Adapter) options are provided AND `psa` is available in the PATH,
-///   it constructs a `psa` command with the specified flags (censoring, verbose stats, etc.).
-/// - Otherwise, it falls back to the standard `purs compile` command.
-fn compiler_command(psa_options: &Option<PsaOptionsConfig>) -> Command {
-    let psa_available = which::which(COMPILER_CMD_PSA).is_ok();
-    match psa_options {
-        Some(options) if psa_available => {
-            let mut command = Command::new(COMPILER_CMD_PSA);
-            if options.verbose_stats {
-                command.arg("--verbose-stats");
-            }
-            if options.verbose_warnings {
-                command.arg("--verbose-warnings");
-            }
-            if options.censor_warnings {
-                command.arg("--censor-warnings");
-            }
-            if options.censor_lib {
-                command.arg("--censor-lib");
-            }
-            if options.censor_src {
-                command.arg("--censor-src");
-            }
-            if options.censor_codes.len() > 0 {
-                command.arg(format!("--censor-codes={}", options.censor_codes.join(",")));
-            }
-            if options.filter_codes.len() > 0 {
-                command.arg(format!("--filter-codes={}", options.filter_codes.join(",")));
-            }
-            if options.no_colors {
-                command.arg("--no-colors");
-            }
-            if options.no_source {
-                command.arg("--no-source");
-            }
-            if options.strict {
-                command.arg("--strict");
-            }
-            if options.stash {
-                command.arg("--stash");
-            }
-            if options.stash_file.is_some() {
-                command.arg("--stash-file");
-                command.arg(options.stash_file.clone().unwrap());
-            }
-            command
+/// `psaOptions` are applied natively to the JSON diagnostics, so the `psa`
+/// binary is never needed.
+fn compiler_command(purs: &Path) -> Command {
+    let mut command = Command::new(purs);
+    command.arg(COMPILER_ARG_COMPILE);
+    command
+}
+
+/// The `--codegen` argument for the configured targets.
+///
+/// `buildOpts.codegen` picks the targets, with source maps and a backend's
+/// corefn added when needed. An explicit `--codegen` in the compiler
+/// arguments is left alone.
+fn codegen_args(options: &CompilerOptions) -> Vec<String> {
+    if options
+        .compiler_args
+        .iter()
+        .any(|arg| arg == ARG_CODEGEN || arg.starts_with(&format!("{}=", ARG_CODEGEN)))
+    {
+        return vec![];
+    }
+
+    let mut targets = match (&options.codegen, &options.backend) {
+        (Some(targets), _) => targets.clone(),
+        // Backends consume corefn, so purs only needs to emit that
+        (None, Some(_)) => vec![CODEGEN_COREFN.to_string()],
+        (None, None) if options.source_maps => vec![CODEGEN_JS.to_string()],
+        (None, None) => return vec![],
+    };
+
+    let mut require = |target: &str| {
+        if !targets.iter().any(|t| t == target) {
+            targets.push(target.to_string());
         }
-        _ => {
-            let mut command = Command::new(COMPILER_CMD_PURS);
-            command.arg(COMPILER_ARG_COMPILE);
-            command
+    };
+    if options.backend.is_some() {
+        require(CODEGEN_COREFN);
+    }
+    if options.source_maps {
+        require(CODEGEN_SOURCEMAPS);
+    }
+
+    vec![ARG_CODEGEN.to_string(), targets.join(",")]
+}
+
+/// Settings for a single compiler invocation
+#[derive(Debug, Clone)]
+pub struct CompilerOptions {
+    /// The purs binary, resolved from the workspace toolchain
+    pub purs: PathBuf,
+    pub output_dir: PathBuf,
+    pub workspace_root: PathBuf,
+    pub compiler_args: Vec<String>,
+    /// `buildOpts.codegen` targets, purs decides when unset
+    pub codegen: Option<Vec<String>>,
+    /// Emit source maps, rewritten to resolve from the output directory
+    pub source_maps: bool,
+    pub psa_options: Option<PsaOptionsConfig>,
+    /// `workspace.buildOpts.rts`, unset values are auto-tuned
+    pub rts: RtsConfig,
+    pub include_rts_stats: bool,
+    /// Parse the `+RTS -s` statistics into the outcome instead of printing them
+    pub capture_rts_stats: bool,
+    /// Print diagnostics as normalized JSON rather than rendering them
+    pub json_errors: bool,
+    /// Run after a successful compile, purs then only emits corefn
+    pub backend: Option<BackendConfig>,
+    pub verbose: bool,
+}
+
+impl CompilerOptions {
+    /// Default compiler options for a project
+    pub fn from_config(config: &SpagoConfig, verbose: bool) -> Result<Self> {
+        let toolchain = resolve_toolchain(config)?;
+        if verbose {
+            println!(
+                "{} Using {} ({})",
+                "→".cyan(),
+                toolchain.purs.display(),
+                toolchain.source
+            );
         }
+
+        // purs runs from the workspace root, so relative paths won't resolve
+        let purs = toolchain.purs.canonicalize().unwrap_or(toolchain.purs);
+
+        Ok(Self {
+            purs,
+            output_dir: config.output_dir(),
+            workspace_root: config.workspace_root.clone(),
+            compiler_args: vec![],
+            codegen: config.codegen(),
+            source_maps: config.source_maps(),
+            psa_options: config.workspace.psa_options.clone(),
+            rts: config.rts_config(),
+            include_rts_stats: false,
+            capture_rts_stats: false,
+            json_errors: false,
+            backend: config.workspace.backend.clone(),
+            verbose,
+        })
     }
 }
 
-/// Execute the purs compiler with streaming output
+/// The result of a compiler run
+#[derive(Debug, Clone, Copy)]
+pub struct CompileOutcome {
+    pub success: bool,
+    /// Error and warning counts after `psaOptions` are applied,
+    /// unknown when purs exited without reporting diagnostics
+    pub summary: Option<DiagnosticSummary>,
+    /// The compile succeeded but the configured backend did not
+    pub backend_failed: bool,
+    /// Runtime statistics, when `capture_rts_stats` was set
+    pub rts_stats: Option<RtsStats>,
+}
+
+/// Execute the purs compiler with streaming output, exiting the process on failure
 pub async fn execute_compiler(
     sources: &[String],
-    output_dir: &PathBuf,
-    workspace_root: &PathBuf,
-    compiler_args: Vec<String>,
-    psa_options: &Option<PsaOptionsConfig>,
-    include_rts_stats: bool,
-    verbose: bool,
-) -> Result<()> {
-    if verbose {
+    options: &CompilerOptions,
+) -> Result<CompileOutcome> {
+    let outcome = run_compiler(sources, options).await?;
+
+    if !outcome.success {
+        report_failure(&outcome);
+        std::process::exit(1);
+    }
+
+    Ok(outcome)
+}
+
+/// Print the failure message for an unsuccessful compilation
+pub fn report_failure(outcome: &CompileOutcome) {
+    if outcome.backend_failed {
+        eprintln!("❌ Backend failed");
+        return;
+    }
+    match outcome.summary {
+        Some(summary) => eprintln!("❌ Compilation failed: {}", summary),
+        None => eprintln!("❌ Compilation failed"),
+    }
+}
+
+/// Run the purs compiler with streaming output.
+///
+/// Returns the outcome of the compilation, leaving it to the caller to decide
+/// what a failed build means (watch mode keeps going, a one-off build exits).
+/// Any compile changes the output, so the last build's fingerprint is
+/// cleared first.
+pub async fn run_compiler(sources: &[String], options: &CompilerOptions) -> Result<CompileOutcome> {
+    fingerprint::clear(&options.output_dir)?;
+    let workspace_root = &options.workspace_root;
+    if options.verbose {
         println!("{} Running purs compiler...", "→".cyan());
     }
 
-    let total_memory = get_total_memory();
+    if options
+        .psa_options
+        .as_ref()
+        .is_some_and(|psa| psa.no_colors)
+    {
+        colored::control::set_override(false);
+    }
 
     // Build the purs compiler command
-    let mut command: Command = compiler_command(psa_options);
+    let mut command: Command = compiler_command(&options.purs);
 
     // Use the config output directory to share workspace output.
     // The path must be relative to workspace_root since the compiler
     // runs with current_dir(workspace_root).
     command.arg("--output");
-    let relative_output = make_path_relative_to_workspace(output_dir, workspace_root)?;
-    command.arg(relative_output);
+    let relative_output = make_path_relative_to_workspace(&options.output_dir, workspace_root)?;
+    command.arg(&relative_output);
 
-    // Add RTS arguments when memory is available for it.
+    // Tune the compiler runtime for the machine unless configured otherwise.
     // Helps with compiler performance.
-    let rts_args = build_rts_args(total_memory, include_rts_stats);
+    let rts_flags = resolve_rts_flags(
+        &options.rts,
+        std::env::var(ENV_PURS_RTS).ok().as_deref(),
+        available_memory_gb(),
+    );
+    let rts_args = build_rts_args(
+        rts_flags,
+        options.include_rts_stats || options.capture_rts_stats,
+    );
     if !rts_args.is_empty() {
         command.args(rts_args);
     }
 
-    command.args(compiler_args);
+    // We parse, filter and render the diagnostics ourselves
+    if !options.compiler_args.iter().any(|a| a == ARG_JSON_ERRORS) {
+        command.arg(ARG_JSON_ERRORS);
+    }
+
+    let codegen_args = codegen_args(options);
+    let source_maps = codegen_args
+        .iter()
+        .chain(&options.compiler_args)
+        .any(|arg| arg.contains(CODEGEN_SOURCEMAPS));
+    command.args(&codegen_args);
+
+    command.args(&options.compiler_args);
 
     // Add all source globs as arguments
     command.arg("--");
@@ -166,9 +370,6 @@ pub async fn execute_compiler(
 
     command.args(relative_sources);
 
-    // Check if we're using psa (which flips stdout/stderr)
-    let using_psa = psa_options.is_some() && which::which(COMPILER_CMD_PSA).is_ok();
-
     // Run the compiler with streaming output
     let mut child = command
         .current_dir(workspace_root)
@@ -177,40 +378,24 @@ pub async fn execute_compiler(
         .spawn()
         .context("Failed to start purs compiler")?;
 
-    // Stream stdout and stderr concurrently using tokio tasks
-    // Note: psa flips stdout/stderr, so we need to swap them when using psa
-    let stdout_handle = if let Some(stdout) = child.stdout.take() {
-        // If using psa, stdout contains what should be on stderr
-        let print_to_stderr = using_psa;
-        Some(spawn_output_streamer(
-            stdout,
-            print_to_stderr,
-            workspace_root.clone(),
-        ))
-    } else {
-        None
-    };
-
-    let stderr_handle = if let Some(stderr) = child.stderr.take() {
-        // If using psa, stderr contains what should be on stdout.
-        // If NOT using psa, stderr goes to stderr.
-        let print_to_stderr = !using_psa;
-        Some(spawn_output_streamer(
-            stderr,
-            print_to_stderr,
-            workspace_root.clone(),
-        ))
-    } else {
-        None
-    };
+    // Collect diagnostics from stdout while streaming progress from stderr
+    let json_handle = child
+        .stdout
+        .take()
+        .map(|stdout| spawn_json_collector(stdout, workspace_root.clone()));
+    let stderr_handle = child.stderr.take().map(|stderr| {
+        spawn_output_streamer(stderr, workspace_root.clone(), options.capture_rts_stats)
+    });
 
     // Wait for output threads to finish
-    if let Some(handle) = stdout_handle {
-        handle.await.ok();
-    }
-    if let Some(handle) = stderr_handle {
-        handle.await.ok();
-    }
+    let diagnostics = match json_handle {
+        Some(handle) => handle.await.ok().flatten(),
+        None => None,
+    };
+    let (compiled_modules, rts_stats) = match stderr_handle {
+        Some(handle) => handle.await.unwrap_or_default(),
+        None => (HashSet::new(), None),
+    };
 
     // Wait for completion
     let status = child
@@ -218,18 +403,122 @@ pub async fn execute_compiler(
         .await
         .context("Failed to wait for purs compiler")?;
 
-    if !status.success() {
-        eprintln!("❌ Compilation failed");
-        std::process::exit(1);
-    }
-    if verbose {
+    let summary = match diagnostics {
+        Some(diagnostics) => Some(report_diagnostics(diagnostics, &compiled_modules, options)?),
+        None => None,
+    };
+
+    // Strict mode can turn a successful compile into a failed build
+    let success = status.success() && summary.map_or(true, |summary| summary.errors == 0);
+
+    if success && options.verbose {
         println!("  Compiled {} source files", sources.len());
     }
 
-    Ok(())
+    if success && source_maps {
+        for module in &compiled_modules {
+            let map_file = options.output_dir.join(module).join("index.js.map");
+            if map_file.exists() {
+                map_source_map_paths(&map_file, &relative_output, workspace_root)?;
+            }
+        }
+    }
+
+    let mut backend_failed = false;
+    if let (true, Some(backend)) = (success, &options.backend) {
+        backend_failed = !backend::run_backend(
+            backend,
+            workspace_root,
+            &options.output_dir,
+            &compiled_modules,
+            options.json_errors,
+            options.verbose,
+        )
+        .await?;
+    }
+
+    Ok(CompileOutcome {
+        success: success && !backend_failed,
+        summary,
+        backend_failed,
+        rts_stats,
+    })
+}
+
+/// Apply `psaOptions` to the collected diagnostics, then print them
+/// either as JSON or rendered for humans.
+fn report_diagnostics(
+    mut diagnostics: CompilerOutput,
+    compiled_modules: &HashSet<String>,
+    options: &CompilerOptions,
+) -> Result<DiagnosticSummary> {
+    let workspace_root = &options.workspace_root;
+    let mut show_source = true;
+    let mut stats = None;
+
+    if let Some(psa_options) = &options.psa_options {
+        if let Some(stash_file) = psa::stash_path(psa_options, workspace_root) {
+            psa::replay_stash(
+                &stash_file,
+                workspace_root,
+                &mut diagnostics,
+                compiled_modules,
+            )?;
+        }
+        let (filtered, warning_stats) = psa::apply(psa_options, diagnostics);
+        diagnostics = filtered;
+        show_source = !psa_options.no_source;
+        if psa_options.verbose_stats {
+            stats = Some(warning_stats);
+        }
+    }
+
+    diagnostics.map_paths_to_cwd(workspace_root);
+    if options.json_errors {
+        println!(
+            "{}",
+            serde_json::to_string(&diagnostics).unwrap_or_default()
+        );
+    } else {
+        diagnostics.render(show_source);
+    }
+
+    if let Some(stats) = stats {
+        eprintln!("{}", stats.render());
+    }
+
+    Ok(diagnostics.summary())
 }
 
-/// Spawn a background task to stream process output (stdout/stderr).
+/// Spawn a background task collecting `--json-errors` output from purs stdout.
+///
+/// Any lines that aren't JSON diagnostics are passed through to stdout as-is.
+fn spawn_json_collector(
+    stream: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    workspace_root: PathBuf,
+) -> tokio::task::JoinHandle<Option<CompilerOutput>> {
+    let reader = BufReader::new(stream);
+    tokio::spawn(async move {
+        let mut lines = reader.lines();
+        let mut collected: Option<CompilerOutput> = None;
+        while let Ok(Some(line)) = lines.next_line().await {
+            match CompilerOutput::parse_line(&line) {
+                Some(output) => collected
+                    .get_or_insert_with(Default::default)
+                    .extend(output),
+                None => {
+                    let mapped_line =
+                        map_diagnostic_paths_from_output_to_cwd(&line, &workspace_root)
+                            .unwrap_or_else(|_| line.clone());
+                    println!("{}", mapped_line);
+                }
+            }
+        }
+        collected
+    })
+}
+
+/// Spawn a background task to stream process output.
 ///
 /// This helper handles two main things:
 /// 1. **Line-by-line Streaming**: Reads the output stream line by line.
@@ -237,248 +526,86 @@ pub async fn execute_compiler(
 ///    to ensure file paths in error messages are relative to the user's CWD, not the
 ///    compiler's output directory.
 ///
-/// It allows redirecting `stdout` content to `stderr` (or vice-versa) via `print_to_stderr`,
-/// which is necessary because `psa` flips these streams.
+/// Returns the modules purs reported compiling, used to replay stashed warnings,
+/// and with `capture_rts_stats` the parsed `+RTS -s` statistics, which are
+/// not echoed.
 fn spawn_output_streamer(
     stream: impl tokio::io::AsyncRead + Unpin + Send + 'static,
-    print_to_stderr: bool,
     workspace_root: PathBuf,
-) -> tokio::task::JoinHandle<()> {
+    capture_rts_stats: bool,
+) -> tokio::task::JoinHandle<(HashSet<String>, Option<RtsStats>)> {
     let reader = BufReader::new(stream);
     tokio::spawn(async move {
         let mut lines = reader.lines();
+        let mut compiled_modules = HashSet::new();
+        let mut rts_stats: Option<RtsStats> = None;
         while let Ok(Some(line)) = lines.next_line().await {
-            let mapped_line = map_diagnostic_paths_from_output_to_cwd(&line, &workspace_root)
-                .unwrap_or_else(|_| line.clone());
+            // The statistics come last, once the compiler has finished
+            if capture_rts_stats && (rts_stats.is_some() || RtsStats::is_start(&line)) {
+                rts_stats
+                    .get_or_insert_with(Default::default)
+                    .parse_line(&line);
+                continue;
+            }
 
-            if print_to_stderr {
-                eprintln!("{}", mapped_line);
-            } else {
-                println!("{}", mapped_line);
+            if let Some(module) = psa::compiled_module(&line) {
+                compiled_modules.insert(module);
             }
+
+            let mapped_line = map_diagnostic_paths_from_output_to_cwd(&line, &workspace_root)
+                .unwrap_or_else(|_| line.clone());
+            eprintln!("{}", mapped_line);
         }
+        (compiled_modules, rts_stats)
     })
 }
 
-fn get_total_memory() -> u64 {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    sys.total_memory() / 1024 / 1024 / 1024
+/// Memory (in GB) available to the compiler.
+///
+/// Container limits are respected, as the machine's total would size the
+/// runtime for memory the compiler can't use.
+fn available_memory_gb() -> u64 {
+    let sys = System::new_with_specifics(
+        RefreshKind::nothing().with_memory(MemoryRefreshKind::everything()),
+    );
+
+    let memory = sys.cgroup_limits().map_or(sys.total_memory(), |limits| {
+        limits.total_memory.min(sys.total_memory())
+    });
+
+    memory / 1024 / 1024 / 1024
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // Helper to create a default PsaOptionsConfig for testing
-    fn default_psa_options() -> PsaOptionsConfig {
-        PsaOptionsConfig {
-            verbose_stats: false,
-            verbose_warnings: false,
-            censor_warnings: false,
-            censor_lib: false,
-            censor_src: false,
-            censor_codes: vec![],
-            filter_codes: vec![],
-            no_colors: false,
-            no_source: false,
-            strict: false,
-            stash: false,
-            stash_file: None,
-        }
+    /// RTS arguments from the memory heuristic alone
+    fn auto_rts_args(total_memory_gb: u64, include_stats: bool) -> Vec<String> {
+        build_rts_args(RtsFlags::auto(total_memory_gb).into_args(), include_stats)
     }
 
     #[test]
-    fn test_compiler_command_uses_purs_when_no_options() {
-        let command = compiler_command(&None);
+    fn test_compiler_command_uses_purs_compile() {
+        let command = compiler_command(Path::new("purs"));
         let debug = format!("{:?}", command);
         assert!(debug.contains("purs"));
         assert!(debug.contains("compile"));
+        assert!(!debug.contains("psa"));
     }
 
-    #[test]
-    fn test_compiler_command_uses_purs_when_psa_not_available() {
-        // Even with options, should fall back to purs if psa not available
-        // (This test might pass or fail depending on whether psa is installed)
-        let options = Some(default_psa_options());
-        let command = compiler_command(&options);
-        let debug = format!("{:?}", command);
-        // Will be either purs or psa depending on system
-        assert!(debug.contains("purs") || debug.contains("psa"));
-    }
-
-    #[test]
-    fn test_compiler_command_with_verbose_stats() {
-        let mut options = default_psa_options();
-        options.verbose_stats = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        // Only check for the flag if psa is available
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--verbose-stats"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_verbose_warnings() {
-        let mut options = default_psa_options();
-        options.verbose_warnings = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--verbose-warnings"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_censor_flags() {
-        let mut options = default_psa_options();
-        options.censor_warnings = true;
-        options.censor_lib = true;
-        options.censor_src = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--censor-warnings"));
-            assert!(debug.contains("--censor-lib"));
-            assert!(debug.contains("--censor-src"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_censor_codes() {
-        let mut options = default_psa_options();
-        options.censor_codes = vec!["Error1".to_string(), "Error2".to_string()];
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--censor-codes=Error1,Error2"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_filter_codes() {
-        let mut options = default_psa_options();
-        options.filter_codes = vec!["Warn1".to_string(), "Warn2".to_string()];
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--filter-codes=Warn1,Warn2"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_no_colors_and_no_source() {
-        let mut options = default_psa_options();
-        options.no_colors = true;
-        options.no_source = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--no-colors"));
-            assert!(debug.contains("--no-source"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_strict() {
-        let mut options = default_psa_options();
-        options.strict = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--strict"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_stash() {
-        let mut options = default_psa_options();
-        options.stash = true;
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--stash"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_stash_file() {
-        let mut options = default_psa_options();
-        options.stash_file = Some("my-stash.json".to_string());
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--stash-file"));
-            assert!(debug.contains("my-stash.json"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_with_multiple_options() {
-        let mut options = default_psa_options();
-        options.verbose_stats = true;
-        options.strict = true;
-        options.no_colors = true;
-        options.censor_lib = true;
-        options.censor_codes = vec!["E1".to_string(), "E2".to_string()];
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        if which::which("psa").is_ok() {
-            assert!(debug.contains("--verbose-stats"));
-            assert!(debug.contains("--strict"));
-            assert!(debug.contains("--no-colors"));
-            assert!(debug.contains("--censor-lib"));
-            assert!(debug.contains("--censor-codes=E1,E2"));
-        }
-    }
-
-    #[test]
-    fn test_compiler_command_empty_censor_codes() {
-        let mut options = default_psa_options();
-        options.censor_codes = vec![];
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        // Should not include --censor-codes when empty
-        assert!(!debug.contains("--censor-codes"));
-    }
-
-    #[test]
-    fn test_compiler_command_empty_filter_codes() {
-        let mut options = default_psa_options();
-        options.filter_codes = vec![];
-        let command = compiler_command(&Some(options));
-        let debug = format!("{:?}", command);
-
-        // Should not include --filter-codes when empty
-        assert!(!debug.contains("--filter-codes"));
-    }
-
-    // Tests for build_rts_args
-
     #[test]
     fn test_build_rts_args_low_memory_no_stats() {
-        let args = build_rts_args(8, false);
+        let args = auto_rts_args(8, false);
         assert!(args.is_empty());
 
-        let args = build_rts_args(15, false);
+        let args = auto_rts_args(15, false);
         assert!(args.is_empty());
     }
 
     #[test]
     fn test_build_rts_args_medium_memory() {
-        let args = build_rts_args(16, false);
+        let args = auto_rts_args(16, false);
         assert_eq!(
             args,
             vec![
@@ -489,7 +616,7 @@ mod tests {
             ]
         );
 
-        let args = build_rts_args(24, false);
+        let args = auto_rts_args(24, false);
         assert_eq!(
             args,
             vec![
@@ -500,7 +627,7 @@ mod tests {
             ]
         );
 
-        let args = build_rts_args(31, false);
+        let args = auto_rts_args(31, false);
         assert_eq!(
             args,
             vec![
@@ -514,7 +641,7 @@ mod tests {
 
     #[test]
     fn test_build_rts_args_high_memory() {
-        let args = build_rts_args(32, false);
+        let args = auto_rts_args(32, false);
         assert_eq!(
             args,
             vec![
@@ -525,7 +652,7 @@ mod tests {
             ]
         );
 
-        let args = build_rts_args(64, false);
+        let args = auto_rts_args(64, false);
         assert_eq!(
             args,
             vec![
@@ -539,7 +666,7 @@ mod tests {
 
     #[test]
     fn test_build_rts_args_with_stats_medium_memory() {
-        let args = build_rts_args(16, true);
+        let args = auto_rts_args(16, true);
         assert_eq!(
             args,
             vec![
@@ -551,7 +678,7 @@ mod tests {
             ]
         );
 
-        let args = build_rts_args(24, true);
+        let args = auto_rts_args(24, true);
         assert_eq!(
             args,
             vec![
@@ -566,7 +693,7 @@ mod tests {
 
     #[test]
     fn test_build_rts_args_with_stats_high_memory() {
-        let args = build_rts_args(32, true);
+        let args = auto_rts_args(32, true);
         assert_eq!(
             args,
             vec![
@@ -578,7 +705,7 @@ mod tests {
             ]
         );
 
-        let args = build_rts_args(64, true);
+        let args = auto_rts_args(64, true);
         assert_eq!(
             args,
             vec![
@@ -594,31 +721,140 @@ mod tests {
     #[test]
     fn test_build_rts_args_with_stats_low_memory() {
         // With stats but low memory, should return just stats
-        let args = build_rts_args(8, true);
+        let args = auto_rts_args(8, true);
         assert_eq!(args, vec![RTS_FLAG_START, RTS_ARG_STATS, RTS_FLAG_END]);
 
-        let args = build_rts_args(15, true);
+        let args = auto_rts_args(15, true);
         assert_eq!(args, vec![RTS_FLAG_START, RTS_ARG_STATS, RTS_FLAG_END]);
     }
 
     #[test]
     fn test_build_rts_args_boundary_conditions() {
         // Test exact boundary at 15GB without stats
-        assert!(build_rts_args(15, false).is_empty());
-        assert!(!build_rts_args(16, false).is_empty());
+        assert!(auto_rts_args(15, false).is_empty());
+        assert!(!auto_rts_args(16, false).is_empty());
 
         // Test exact boundary at 15GB with stats
-        let args_15_stats = build_rts_args(15, true);
+        let args_15_stats = auto_rts_args(15, true);
         assert_eq!(
             args_15_stats,
             vec![RTS_FLAG_START, RTS_ARG_STATS, RTS_FLAG_END]
         );
 
         // Test exact boundary at 31GB
-        let args_31 = build_rts_args(31, false);
+        let args_31 = auto_rts_args(31, false);
         assert!(args_31.contains(&RTS_ARG_MED_MEM_ALLOC.to_string()));
 
-        let args_32 = build_rts_args(32, false);
+        let args_32 = auto_rts_args(32, false);
         assert!(args_32.contains(&RTS_ARG_HIGH_MEM_ALLOC.to_string()));
     }
+
+    #[test]
+    fn test_rts_flags_auto_leaves_threads_alone() {
+        assert!(RtsFlags::auto(8).into_args().is_empty());
+        assert!(!RtsFlags::auto(64)
+            .into_args()
+            .iter()
+            .any(|flag| flag.starts_with("-N")));
+    }
+
+    #[test]
+    fn test_resolve_rts_flags_config_overrides_auto() {
+        let configured = RtsConfig {
+            allocation_area: Some("64m".to_string()),
+            threads: Some(0),
+            max_heap: Some("3g".to_string()),
+            ..Default::default()
+        };
+
+        let flags = resolve_rts_flags(&configured, None, 32);
+
+        // The nursery isn't configured, so it still comes from the memory heuristic
+        assert_eq!(flags, vec!["-A64m", RTS_ARG_HIGH_MEM_NURSERY, "-N", "-M3g"]);
+    }
+
+    #[test]
+    fn test_resolve_rts_flags_env_override_wins() {
+        let configured = RtsConfig {
+            allocation_area: Some("64m".to_string()),
+            ..Default::default()
+        };
+
+        let flags = resolve_rts_flags(&configured, Some("+RTS -A32m -N2 -RTS"), 64);
+        assert_eq!(flags, vec!["-A32m", "-N2"]);
+
+        let args = build_rts_args(resolve_rts_flags(&configured, Some(""), 64), false);
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_rts_config_from_yaml() {
+        let rts: RtsConfig =
+            serde_yaml::from_str("allocationArea: 128m\nnursery: 8m\nthreads: 4\nmaxHeap: 6g\n")
+                .unwrap();
+
+        assert_eq!(
+            RtsFlags::from_config(&rts).into_args(),
+            vec!["-A128m", "-n8m", "-N4", "-M6g"]
+        );
+    }
+
+    fn test_options() -> CompilerOptions {
+        CompilerOptions {
+            purs: PathBuf::from("purs"),
+            output_dir: PathBuf::from("output"),
+            workspace_root: PathBuf::from("."),
+            compiler_args: vec![],
+            codegen: None,
+            source_maps: false,
+            psa_options: None,
+            rts: RtsConfig::default(),
+            include_rts_stats: false,
+            capture_rts_stats: false,
+            json_errors: false,
+            backend: None,
+            verbose: false,
+        }
+    }
+
+    #[test]
+    fn test_codegen_args_from_config() {
+        let mut options = test_options();
+        assert!(codegen_args(&options).is_empty());
+
+        options.source_maps = true;
+        assert_eq!(codegen_args(&options), vec!["--codegen", "js,sourcemaps"]);
+
+        options.codegen = Some(vec!["js".to_string(), "docs".to_string()]);
+        assert_eq!(
+            codegen_args(&options),
+            vec!["--codegen", "js,docs,sourcemaps"]
+        );
+    }
+
+    #[test]
+    fn test_codegen_args_backend_needs_corefn() {
+        let mut options = test_options();
+        options.backend = Some(BackendConfig {
+            cmd: "purs-backend-es".to_string(),
+            args: vec![],
+            output: None,
+        });
+        assert_eq!(codegen_args(&options), vec!["--codegen", "corefn"]);
+
+        options.codegen = Some(vec!["js".to_string()]);
+        assert_eq!(codegen_args(&options), vec!["--codegen", "js,corefn"]);
+    }
+
+    #[test]
+    fn test_codegen_args_respects_user_codegen() {
+        let mut options = test_options();
+        options.source_maps = true;
+
+        options.compiler_args = vec!["--codegen".to_string(), "corefn".to_string()];
+        assert!(codegen_args(&options).is_empty());
+
+        options.compiler_args = vec!["--codegen=corefn,js".to_string()];
+        assert!(codegen_args(&options).is_empty());
+    }
 }
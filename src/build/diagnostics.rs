@@ -0,0 +1,316 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::build::run_from_root::map_path_from_workspace_to_cwd;
+
+/// The output of `purs compile --json-errors`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompilerOutput {
+    #[serde(default)]
+    pub warnings: Vec<Diagnostic>,
+    #[serde(default)]
+    pub errors: Vec<Diagnostic>,
+}
+
+/// A single compiler error or warning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    #[serde(default)]
+    pub module_name: Option<String>,
+    pub error_code: String,
+    #[serde(default)]
+    pub error_link: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub position: Option<Position>,
+    #[serde(default)]
+    pub suggestion: Option<Suggestion>,
+}
+
+/// A source span, lines and columns are 1-indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// A suggested fix for a diagnostic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub replacement: String,
+    #[serde(default)]
+    pub replace_range: Option<Position>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Error and warning counts for a compilation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiagnosticSummary {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl CompilerOutput {
+    /// Try to parse a line of compiler stdout as JSON diagnostics
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let trimmed = line.trim();
+        if !trimmed.starts_with('{') {
+            return None;
+        }
+        serde_json::from_str(trimmed).ok()
+    }
+
+    /// Combine the diagnostics of several outputs into one
+    pub fn extend(&mut self, other: CompilerOutput) {
+        self.warnings.extend(other.warnings);
+        self.errors.extend(other.errors);
+    }
+
+    /// Rewrite all file paths from workspace-root relative to CWD relative
+    pub fn map_paths_to_cwd(&mut self, workspace_root: &Path) {
+        for diagnostic in self.warnings.iter_mut().chain(self.errors.iter_mut()) {
+            if let Some(filename) = &diagnostic.filename {
+                if let Ok(mapped) = map_path_from_workspace_to_cwd(filename, workspace_root) {
+                    diagnostic.filename = Some(mapped);
+                }
+            }
+        }
+    }
+
+    pub fn summary(&self) -> DiagnosticSummary {
+        DiagnosticSummary {
+            errors: self.errors.len(),
+            warnings: self.warnings.len(),
+        }
+    }
+
+    /// Print all diagnostics to stderr, warnings first so errors end up closest to the prompt
+    pub fn render(&self, show_source: bool) {
+        for (index, warning) in self.warnings.iter().enumerate() {
+            eprintln!(
+                "{}",
+                render_diagnostic(
+                    warning,
+                    Severity::Warning,
+                    index + 1,
+                    self.warnings.len(),
+                    show_source
+                )
+            );
+        }
+        for (index, error) in self.errors.iter().enumerate() {
+            eprintln!(
+                "{}",
+                render_diagnostic(
+                    error,
+                    Severity::Error,
+                    index + 1,
+                    self.errors.len(),
+                    show_source
+                )
+            );
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}, {} {}",
+            self.errors,
+            if self.errors == 1 { "error" } else { "errors" },
+            self.warnings,
+            if self.warnings == 1 {
+                "warning"
+            } else {
+                "warnings"
+            }
+        )
+    }
+}
+
+/// Render a diagnostic in a format similar to purs' own human readable output,
+/// with the offending source lines included when requested and the file can be read.
+pub fn render_diagnostic(
+    diagnostic: &Diagnostic,
+    severity: Severity,
+    index: usize,
+    total: usize,
+    show_source: bool,
+) -> String {
+    let label = match severity {
+        Severity::Error => format!("[ERROR {}/{} {}]", index, total, diagnostic.error_code)
+            .red()
+            .bold(),
+        Severity::Warning => format!("[WARNING {}/{} {}]", index, total, diagnostic.error_code)
+            .yellow()
+            .bold(),
+    };
+
+    let location = match (&diagnostic.filename, &diagnostic.position) {
+        (Some(filename), Some(position)) => format!(
+            "{}:{}:{}",
+            filename, position.start_line, position.start_column
+        ),
+        (Some(filename), None) => filename.clone(),
+        _ => diagnostic.module_name.clone().unwrap_or_default(),
+    };
+
+    let mut rendered = format!("{} {}\n", label, location.cyan());
+
+    if let (true, Some(filename), Some(position)) =
+        (show_source, &diagnostic.filename, &diagnostic.position)
+    {
+        if let Some(excerpt) = source_excerpt(Path::new(filename), position) {
+            rendered.push('\n');
+            rendered.push_str(&excerpt);
+        }
+    }
+
+    rendered.push('\n');
+    for line in diagnostic.message.trim_end().lines() {
+        if line.is_empty() {
+            rendered.push('\n');
+        } else {
+            rendered.push_str(&format!("  {}\n", line));
+        }
+    }
+
+    if let Some(suggestion) = &diagnostic.suggestion {
+        let replacement = suggestion.replacement.trim_end();
+        if !replacement.is_empty() {
+            rendered.push_str(&format!("\n  {}\n", "Suggestion:".dimmed()));
+            for line in replacement.lines() {
+                rendered.push_str(&format!("    {}\n", line.green()));
+            }
+        }
+    }
+
+    if let Some(link) = &diagnostic.error_link {
+        rendered.push_str(&format!("\n  {}\n", link.dimmed()));
+    }
+
+    rendered
+}
+
+/// The source lines covered by a span, with the span underlined
+fn source_excerpt(file: &Path, position: &Position) -> Option<String> {
+    let content = fs::read_to_string(file).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let start = position.start_line.checked_sub(1)?;
+    let end = position.end_line.min(lines.len());
+    if start >= end {
+        return None;
+    }
+
+    let gutter_width = end.to_string().len();
+    let mut excerpt = String::new();
+    for (offset, line) in lines[start..end].iter().enumerate() {
+        let line_number = start + offset + 1;
+        excerpt.push_str(&format!(
+            "  {:>width$}  {}\n",
+            line_number.to_string().dimmed(),
+            line,
+            width = gutter_width
+        ));
+    }
+
+    // Only underline single line spans, multi-line ones are clear enough
+    if position.start_line == position.end_line && position.end_column > position.start_column {
+        excerpt.push_str(&format!(
+            "  {:width$}  {}{}\n",
+            "",
+            " ".repeat(position.start_column.saturating_sub(1)),
+            "^".repeat(position.end_column - position.start_column)
+                .red(),
+            width = gutter_width
+        ));
+    }
+
+    Some(excerpt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{"warnings":[{"position":{"startLine":3,"startColumn":1,"endLine":3,"endColumn":18},"message":"The import of Data.Maybe is redundant\n","errorCode":"UnusedImport","errorLink":"https://github.com/purescript/documentation/blob/master/errors/UnusedImport.md","filename":"src/Main.purs","moduleName":"Main","suggestion":{"replacement":"","replaceRange":{"startLine":3,"startColumn":1,"endLine":3,"endColumn":18}},"allSpans":[]}],"errors":[{"position":null,"message":"Unknown module Foo","errorCode":"ModuleNotFound","errorLink":"https://example.com","filename":null,"moduleName":null,"suggestion":null,"allSpans":[]}]}"#;
+
+    #[test]
+    fn test_parse_line_json_errors() {
+        let output = CompilerOutput::parse_line(SAMPLE).unwrap();
+
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.errors.len(), 1);
+
+        let warning = &output.warnings[0];
+        assert_eq!(warning.error_code, "UnusedImport");
+        assert_eq!(warning.module_name.as_deref(), Some("Main"));
+        assert_eq!(warning.filename.as_deref(), Some("src/Main.purs"));
+        assert_eq!(
+            warning.position,
+            Some(Position {
+                start_line: 3,
+                start_column: 1,
+                end_line: 3,
+                end_column: 18
+            })
+        );
+        assert!(warning.suggestion.is_some());
+
+        let error = &output.errors[0];
+        assert_eq!(error.error_code, "ModuleNotFound");
+        assert!(error.filename.is_none());
+        assert!(error.position.is_none());
+    }
+
+    #[test]
+    fn test_parse_line_ignores_non_json() {
+        assert!(CompilerOutput::parse_line("[1 of 3] Compiling Main").is_none());
+        assert!(CompilerOutput::parse_line("").is_none());
+    }
+
+    #[test]
+    fn test_summary_display() {
+        let summary = DiagnosticSummary {
+            errors: 1,
+            warnings: 2,
+        };
+        assert_eq!(summary.to_string(), "1 error, 2 warnings");
+
+        let summary = DiagnosticSummary {
+            errors: 0,
+            warnings: 1,
+        };
+        assert_eq!(summary.to_string(), "0 errors, 1 warning");
+    }
+
+    #[test]
+    fn test_render_diagnostic_includes_location_and_message() {
+        colored::control::set_override(false);
+        let output = CompilerOutput::parse_line(SAMPLE).unwrap();
+
+        let rendered = render_diagnostic(&output.warnings[0], Severity::Warning, 1, 1, true);
+
+        assert!(rendered.contains("[WARNING 1/1 UnusedImport]"));
+        assert!(rendered.contains("src/Main.purs:3:1"));
+        assert!(rendered.contains("The import of Data.Maybe is redundant"));
+    }
+}
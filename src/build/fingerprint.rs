@@ -0,0 +1,173 @@
+//! Fingerprints of build inputs, used to skip the compiler when nothing changed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use sha2::{Digest, Sha256};
+
+use crate::build::compiler::CompilerOptions;
+use crate::compiler::version::installed_version;
+
+/// Stored in the output directory so deleting the output also forces a rebuild
+const FINGERPRINT_FILE: &str = ".ragu-fingerprint";
+
+/// Hash everything that affects the compiler output: each source file's
+/// path, size and modification time (including FFI files), the compiler
+/// binary and version, and the compiler settings.
+pub async fn compute(sources: &[String], options: &CompilerOptions) -> Result<String> {
+    let mut hasher = Sha256::new();
+
+    let purs_version = installed_version(&options.purs)
+        .await
+        .map(|version| version.to_string())
+        .unwrap_or_default();
+    hasher.update(options.purs.to_string_lossy().as_bytes());
+    hasher.update(purs_version.as_bytes());
+
+    hasher.update(options.output_dir.to_string_lossy().as_bytes());
+    for arg in &options.compiler_args {
+        hasher.update(arg.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(serde_json::to_string(&options.codegen)?.as_bytes());
+    hasher.update([options.source_maps as u8]);
+    hasher.update(serde_json::to_string(&options.psa_options)?.as_bytes());
+    hasher.update(serde_json::to_string(&options.backend)?.as_bytes());
+
+    for source in sources {
+        hasher.update(source.as_bytes());
+        hasher.update([0]);
+    }
+
+    for entry in file_entries(sources)? {
+        hasher.update(entry.as_bytes());
+        hasher.update([b'\n']);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// One `path:size:mtime` line per file matched by the source globs, sorted
+fn file_entries(sources: &[String]) -> Result<Vec<String>> {
+    let files: Vec<PathBuf> = sources
+        .par_iter()
+        .flat_map_iter(|source| {
+            glob::glob(source)
+                .into_iter()
+                .flatten()
+                .filter_map(|path| path.ok())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut entries: Vec<String> = files
+        .par_iter()
+        .flat_map_iter(|purs| {
+            // Foreign imports live next to the module
+            let ffi = purs.with_extension("js");
+            [Some(purs.clone()), ffi.exists().then_some(ffi)]
+                .into_iter()
+                .flatten()
+                .filter_map(|path| file_entry(&path))
+        })
+        .collect();
+
+    entries.sort_unstable();
+    entries.dedup();
+    Ok(entries)
+}
+
+fn file_entry(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos();
+    Some(format!(
+        "{}:{}:{}",
+        path.display(),
+        metadata.len(),
+        modified
+    ))
+}
+
+fn fingerprint_path(output_dir: &Path) -> PathBuf {
+    output_dir.join(FINGERPRINT_FILE)
+}
+
+/// Whether the last successful build had the same fingerprint
+pub fn is_up_to_date(output_dir: &Path, fingerprint: &str) -> bool {
+    fs::read_to_string(fingerprint_path(output_dir))
+        .map(|stored| stored.trim() == fingerprint)
+        .unwrap_or(false)
+}
+
+/// Record the fingerprint of a successful build
+pub fn save(output_dir: &Path, fingerprint: &str) -> Result<()> {
+    fs::write(fingerprint_path(output_dir), fingerprint)
+        .context("Failed to write build fingerprint")
+}
+
+/// Forget the last fingerprint. A failed compile can leave the output
+/// partially updated, so it must not match the previous inputs.
+pub fn clear(output_dir: &Path) -> Result<()> {
+    let path = fingerprint_path(output_dir);
+    if path.exists() {
+        fs::remove_file(&path).context("Failed to remove build fingerprint")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_entries_include_ffi_files() {
+        let temp = TempDir::new().unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("Main.purs"), "module Main where").unwrap();
+        fs::write(src.join("Main.js"), "export const x = 1;").unwrap();
+        fs::write(src.join("Other.purs"), "module Other where").unwrap();
+
+        let glob = format!("{}/**/*.purs", src.display());
+        let entries = file_entries(&[glob]).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert!(entries.iter().any(|e| e.contains("Main.js:")));
+    }
+
+    #[test]
+    fn test_file_entries_change_with_contents() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("Main.purs");
+        fs::write(&file, "module Main where").unwrap();
+        let glob = format!("{}/*.purs", temp.path().display());
+
+        let before = file_entries(std::slice::from_ref(&glob)).unwrap();
+        fs::write(&file, "module Main where\n\nx = 1").unwrap();
+        let after = file_entries(&[glob]).unwrap();
+
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn test_save_and_clear() {
+        let temp = TempDir::new().unwrap();
+
+        assert!(!is_up_to_date(temp.path(), "abc"));
+        save(temp.path(), "abc").unwrap();
+        assert!(is_up_to_date(temp.path(), "abc"));
+        assert!(!is_up_to_date(temp.path(), "def"));
+
+        clear(temp.path()).unwrap();
+        assert!(!is_up_to_date(temp.path(), "abc"));
+    }
+}
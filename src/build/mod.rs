@@ -1,22 +1,61 @@
+pub mod backend;
 pub mod compiler;
+pub mod diagnostics;
+pub mod fingerprint;
+pub mod pedantic;
+pub mod psa;
 pub mod run_from_root;
+pub mod timings;
+pub mod watch;
+
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use colored::Colorize;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::compiler::version::check_compiler_version;
 use crate::sources::BuildSources;
 use crate::{install::install_all_dependencies, test::TEST_SOURCES};
 
+/// Options for the build command
+#[derive(Debug, Clone, Default)]
+pub struct BuildArgs {
+    pub watch: bool,
+    pub clear: bool,
+    /// Include test sources and dependencies
+    pub test: bool,
+    pub compiler_args: Vec<String>,
+    pub include_rts_stats: bool,
+    pub json_errors: bool,
+    /// Compile even when the inputs match the last successful build
+    pub force: bool,
+    /// Check imports against declared dependencies after compiling
+    pub pedantic_packages: bool,
+    /// Report how long each phase of the build took
+    pub timings: bool,
+    /// Also write the timings report to this file as JSON
+    pub timings_json: Option<PathBuf>,
+    pub verbose: bool,
+}
+
 /// Execute the build command
-pub async fn execute(
-    watch: bool,
-    clear: bool,
-    test: bool,
-    compiler_args: Vec<String>,
-    include_rts_stats: bool,
-    verbose: bool,
-) -> Result<()> {
+pub async fn execute(args: BuildArgs) -> Result<()> {
+    let BuildArgs {
+        watch,
+        clear,
+        test,
+        compiler_args,
+        include_rts_stats,
+        json_errors,
+        force,
+        pedantic_packages,
+        timings: report_timings,
+        timings_json,
+        verbose,
+    } = args;
+    let mut timings = timings::Timings::start();
+
     if verbose {
         println!("{} Build command executing", "→".cyan());
         println!("  Watch: {}", watch);
@@ -26,14 +65,133 @@ pub async fn execute(
     // Load spago.yaml configuration
     let config =
         crate::config::load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    timings.finish_phase("Load config");
+
+    check_compiler_version(&config, verbose).await?;
+    timings.finish_phase("Check compiler version");
 
     let package_set = config.package_set().await?;
+    timings.finish_phase("Resolve package set");
 
     install_all_dependencies(&config, &package_set, test).await?;
+    timings.finish_phase("Install dependencies");
+
+    let all_sources = resolve_build_sources(&config, package_set.clone(), test, verbose).await?;
+    timings.finish_phase("Generate sources");
+
+    if watch {
+        let settings = watch::WatchSettings {
+            test,
+            compiler_args,
+            include_rts_stats,
+            json_errors,
+            verbose,
+        };
+        return watch::execute(config, package_set, all_sources, settings).await;
+    }
+
+    let mut options = compiler::CompilerOptions::from_config(&config, verbose)?;
+    options.compiler_args = compiler_args;
+    options.include_rts_stats = include_rts_stats;
+    options.capture_rts_stats = report_timings || timings_json.is_some();
+    options.json_errors = json_errors;
+
+    let fingerprint = fingerprint::compute(&all_sources, &options).await?;
+    // Skipping the compile would leave no diagnostics to report as JSON
+    let up_to_date =
+        !force && !json_errors && fingerprint::is_up_to_date(&options.output_dir, &fingerprint);
+    timings.finish_phase("Check fingerprint");
+
+    let summary = if up_to_date {
+        None
+    } else {
+        // Execute the purs compiler
+        let outcome = compiler::run_compiler(&all_sources, &options).await?;
+        timings.finish_phase("Compile");
+        timings.compiled = true;
+        timings.rts = outcome.rts_stats;
+        if !outcome.success {
+            compiler::report_failure(&outcome);
+            // A failed build's timings are still worth having
+            report(timings, report_timings, timings_json.as_deref())?;
+            std::process::exit(1);
+        }
+        fingerprint::save(&options.output_dir, &fingerprint)?;
+        outcome.summary
+    };
+
+    if pedantic_packages || config.pedantic_packages() {
+        pedantic::check(&config, package_set, test, None, verbose).await?;
+        timings.finish_phase("Check packages");
+    }
+
+    let message = match summary {
+        Some(summary) if summary.warnings > 0 => {
+            format!("{} Build successful: {}", "✓".green(), summary)
+        }
+        _ if up_to_date => format!("{} Build up to date", "✓".green()),
+        _ => format!("{} Build successful", "✓".green()),
+    };
+    // stdout only carries the JSON with --json-errors
+    if json_errors {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+
+    report(timings, report_timings, timings_json.as_deref())
+}
+
+/// Print the timings and write them as JSON, as asked
+fn report(mut timings: timings::Timings, print: bool, json_path: Option<&Path>) -> Result<()> {
+    timings.finish();
+    if print {
+        // stderr keeps stdout clean for --json-errors
+        eprintln!("\n{}", timings.render());
+    }
+    if let Some(path) = json_path {
+        timings.write_json(path)?;
+    }
+    Ok(())
+}
+
+/// Generate the source globs to hand to the compiler, dropping any that
+/// don't match a single .purs file (purs errors on empty globs).
+pub async fn resolve_build_sources(
+    config: &crate::config::SpagoConfig,
+    package_set: crate::registry::PackageSet,
+    test: bool,
+    verbose: bool,
+) -> Result<Vec<String>> {
+    let test_sources = if test {
+        vec![TEST_SOURCES.to_string()]
+    } else {
+        vec![]
+    };
+    resolve_sources(config, package_set, test, &test_sources, verbose).await
+}
+
+/// Like [`resolve_build_sources`] with tests, compiling the given test
+/// globs instead of the default ones
+pub async fn resolve_test_sources(
+    config: &crate::config::SpagoConfig,
+    package_set: crate::registry::PackageSet,
+    test_sources: &[String],
+    verbose: bool,
+) -> Result<Vec<String>> {
+    resolve_sources(config, package_set, true, test_sources, verbose).await
+}
 
+async fn resolve_sources(
+    config: &crate::config::SpagoConfig,
+    package_set: crate::registry::PackageSet,
+    test: bool,
+    test_sources: &[String],
+    verbose: bool,
+) -> Result<Vec<String>> {
     // Generate source globs for dependencies
     let sources =
-        crate::sources::generate_sources(&config, Some(package_set), false, test, verbose).await?;
+        crate::sources::generate_sources(config, Some(package_set), false, test, verbose).await?;
 
     if verbose {
         println!(
@@ -46,10 +204,10 @@ pub async fn execute(
         }
     }
 
-    let mut all_sources = collect_build_sources(&sources, test);
+    let all_sources = collect_build_sources(&sources, test_sources);
 
     // Remove any sources that don't contain any .purs files
-    all_sources = all_sources
+    Ok(all_sources
         .into_par_iter()
         .filter(|source| {
             let files = glob::glob(source);
@@ -59,27 +217,11 @@ pub async fn execute(
                 false
             }
         })
-        .collect();
-
-    // Execute the purs compiler
-    compiler::execute_compiler(
-        &all_sources,
-        &config.output_dir(),
-        &config.workspace_root,
-        compiler_args,
-        &config.workspace.psa_options,
-        include_rts_stats,
-        verbose,
-    )
-    .await?;
-
-    println!("{} Build successful", "✓".green());
-
-    Ok(())
+        .collect())
 }
 
 /// Collect all source globs for the compiler from the generated build sources.
-fn collect_build_sources(sources: &BuildSources, test: bool) -> Vec<String> {
+fn collect_build_sources(sources: &BuildSources, test_sources: &[String]) -> Vec<String> {
     let mut all_sources: Vec<String> = sources
         .dependency_globs
         .iter()
@@ -90,9 +232,7 @@ fn collect_build_sources(sources: &BuildSources, test: bool) -> Vec<String> {
         all_sources.push(main.clone());
     }
 
-    if test {
-        all_sources.push(TEST_SOURCES.to_string());
-    }
+    all_sources.extend(test_sources.iter().cloned());
 
     all_sources
 }
@@ -108,6 +248,7 @@ mod tests {
             package_name: "prelude".to_string(),
             glob_pattern: ".spago/prelude/src/**/*.purs".to_string(),
             local_path: PathBuf::from(".spago/prelude"),
+            is_test: false,
         }
     }
 
@@ -118,7 +259,7 @@ mod tests {
             dependency_globs: vec![dep_glob()],
         };
 
-        let result = collect_build_sources(&sources, false);
+        let result = collect_build_sources(&sources, &[]);
 
         assert!(result.contains(&"./src/**/*.purs".to_string()));
         assert!(result.contains(&".spago/prelude/src/**/*.purs".to_string()));
@@ -131,7 +272,7 @@ mod tests {
             dependency_globs: vec![dep_glob()],
         };
 
-        let result = collect_build_sources(&sources, false);
+        let result = collect_build_sources(&sources, &[]);
 
         assert!(!result.contains(&"./src/**/*.purs".to_string()));
         assert!(result.contains(&".spago/prelude/src/**/*.purs".to_string()));
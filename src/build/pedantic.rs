@@ -0,0 +1,230 @@
+//! Pedantic packages mode: fail the build when the current package's imports
+//! don't match the dependencies it declares.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::config::SpagoConfig;
+use crate::imports::{extract_imports_from_globs, extract_imports_from_sources};
+use crate::modules::discover_modules_from_glob;
+use crate::registry::{PackageName, PackageSet};
+use crate::test::test_globs;
+use crate::workspace::{analyze_imports, module_packages, ImportAnalysis};
+
+/// The package name main sources are discovered under
+const MAIN_SOURCES_PACKAGE: &str = "main";
+
+/// Check the current package's imports against its dependencies.
+///
+/// Sources are checked against `package.dependencies`. With `test`, the
+/// sources of `package.test.main` or the named suite are checked against
+/// those plus the test dependencies that suite compiles with.
+/// Prints a report and exits the process when anything doesn't match.
+pub async fn check(
+    config: &SpagoConfig,
+    package_set: PackageSet,
+    test: bool,
+    suite: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    if verbose {
+        println!(
+            "{} Checking imports of {} against its dependencies",
+            "→".cyan(),
+            config.package.name.0
+        );
+    }
+
+    let package = &config.package.name;
+    let sources =
+        crate::sources::generate_sources(config, Some(package_set), false, true, false).await?;
+
+    let mut modules = module_packages(&sources)?;
+    for owner in modules.values_mut() {
+        if owner.0 == MAIN_SOURCES_PACKAGE {
+            *owner = package.clone();
+        }
+    }
+    let suite_config = config.for_test_suite(suite);
+    let test_config = suite_config.package.test.as_ref().filter(|_| test);
+    let tests = test_config.map(test_globs).unwrap_or_default();
+    for glob in &tests {
+        for module in discover_modules_from_glob(glob, &package.0)? {
+            modules.insert(module.name, package.clone());
+        }
+    }
+
+    let dependencies: HashSet<&PackageName> = config.package_dependencies().into_iter().collect();
+    let source_imports = extract_imports_from_sources(Path::new("."))?;
+    let mut source_analysis = analyze_imports(package, &source_imports, &dependencies, &modules);
+    // The compiler has already rejected imports of modules that don't exist
+    source_analysis.not_found.clear();
+
+    let test_analysis = match test_config {
+        Some(test_config) => {
+            let test_dependencies: HashSet<&PackageName> =
+                test_config.all_dependencies().into_iter().collect();
+            let allowed: HashSet<&PackageName> =
+                dependencies.union(&test_dependencies).copied().collect();
+            let test_imports = extract_imports_from_globs(&tests)?;
+
+            let mut analysis = analyze_imports(package, &test_imports, &allowed, &modules);
+            analysis.not_found.clear();
+            // Tests don't have to use the package's own dependencies
+            analysis
+                .unused
+                .retain(|dep| test_dependencies.contains(dep) && !dependencies.contains(dep));
+            Some(analysis)
+        }
+        _ => None,
+    };
+
+    if source_analysis.is_clean() && test_analysis.as_ref().is_none_or(|a| a.is_clean()) {
+        return Ok(());
+    }
+
+    eprint!(
+        "{}",
+        render_report(package, &source_analysis, test_analysis.as_ref())
+    );
+    std::process::exit(1);
+}
+
+/// Describe every mismatch and how to fix it
+fn render_report(
+    package: &PackageName,
+    sources: &ImportAnalysis,
+    tests: Option<&ImportAnalysis>,
+) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "❌ Pedantic packages check failed for {}",
+        package.0.bold()
+    );
+
+    render_undeclared(
+        &mut report,
+        "Sources import from packages that are not dependencies:",
+        sources,
+    );
+    render_unused(
+        &mut report,
+        "Sources don't import from these dependencies:",
+        sources,
+    );
+    if let Some(tests) = tests {
+        render_undeclared(
+            &mut report,
+            "Tests import from packages that are not dependencies or test dependencies:",
+            tests,
+        );
+        render_unused(
+            &mut report,
+            "Tests don't import from these test dependencies:",
+            tests,
+        );
+    }
+
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}", "To fix:".dimmed());
+    if !sources.undeclared.is_empty() {
+        let _ = writeln!(report, "  ragu install {}", join(sources.undeclared.keys()));
+    }
+    if !sources.unused.is_empty() {
+        let _ = writeln!(report, "  ragu uninstall {}", join(sources.unused.iter()));
+    }
+    if let Some(tests) = tests {
+        if !tests.undeclared.is_empty() {
+            let _ = writeln!(
+                report,
+                "  add {} to package.test.dependencies in spago.yaml",
+                join(tests.undeclared.keys())
+            );
+        }
+        if !tests.unused.is_empty() {
+            let _ = writeln!(
+                report,
+                "  remove {} from package.test.dependencies in spago.yaml",
+                join(tests.unused.iter())
+            );
+        }
+    }
+
+    report
+}
+
+fn render_undeclared(report: &mut String, heading: &str, analysis: &ImportAnalysis) {
+    if analysis.undeclared.is_empty() {
+        return;
+    }
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}", heading);
+    for (package, imports) in &analysis.undeclared {
+        let _ = writeln!(report, "  {}", package.0.yellow());
+        for import in imports {
+            let _ = writeln!(
+                report,
+                "    {}:{} imports {}",
+                import.file_path, import.line_number, import.module_name
+            );
+        }
+    }
+}
+
+fn render_unused(report: &mut String, heading: &str, analysis: &ImportAnalysis) {
+    if analysis.unused.is_empty() {
+        return;
+    }
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}", heading);
+    for package in &analysis.unused {
+        let _ = writeln!(report, "  {}", package.0.yellow());
+    }
+}
+
+fn join<'a>(packages: impl Iterator<Item = &'a PackageName>) -> String {
+    packages
+        .map(|p| p.0.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imports::ImportInfo;
+
+    fn import(module_name: &str, line_number: usize) -> ImportInfo {
+        ImportInfo {
+            module_name: module_name.to_string(),
+            file_path: "./src/Main.purs".to_string(),
+            line_number,
+        }
+    }
+
+    #[test]
+    fn test_render_report_lists_problems_and_fixes() {
+        colored::control::set_override(false);
+        let mut sources = ImportAnalysis::default();
+        sources
+            .undeclared
+            .insert(PackageName::new("arrays"), vec![import("Data.Array", 3)]);
+        sources.unused.insert(PackageName::new("strings"));
+        let mut tests = ImportAnalysis::default();
+        tests.unused.insert(PackageName::new("spec"));
+
+        let report = render_report(&PackageName::new("my-app"), &sources, Some(&tests));
+
+        assert!(report.contains("failed for my-app"));
+        assert!(report.contains("./src/Main.purs:3 imports Data.Array"));
+        assert!(report.contains("ragu install arrays"));
+        assert!(report.contains("ragu uninstall strings"));
+        assert!(report.contains("remove spec from package.test.dependencies"));
+        assert!(!report.contains("Tests import from"));
+    }
+}
@@ -0,0 +1,457 @@
+//! Native implementation of the psa (PureScript Adapter) options.
+//!
+//! Rather than shelling out to the Node based `psa` binary, the `psaOptions`
+//! config is applied directly to the diagnostics from `purs compile --json-errors`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::build::diagnostics::{CompilerOutput, Diagnostic};
+use crate::config::PsaOptionsConfig;
+
+/// Where warnings are stashed between builds when no `stashFile` is configured
+pub const DEFAULT_STASH_FILE: &str = ".psa-stash";
+
+/// Directories holding library (dependency) sources rather than project sources
+const LIB_DIRS: [&str; 2] = [".spago", "bower_components"];
+
+/// Warning counts for a single error code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CodeStats {
+    pub src: usize,
+    pub lib: usize,
+}
+
+/// Warning counts for a compilation, before any censoring
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarningStats {
+    pub by_code: BTreeMap<String, CodeStats>,
+}
+
+impl WarningStats {
+    fn record(&mut self, diagnostic: &Diagnostic) {
+        let stats = self
+            .by_code
+            .entry(diagnostic.error_code.clone())
+            .or_default();
+        if is_lib_diagnostic(diagnostic) {
+            stats.lib += 1;
+        } else {
+            stats.src += 1;
+        }
+    }
+
+    /// A per code table of warning counts, as printed by `verboseStats`
+    pub fn render(&self) -> String {
+        let code_width = self
+            .by_code
+            .keys()
+            .map(|code| code.len())
+            .max()
+            .unwrap_or(0)
+            .max("Warnings".len());
+
+        let mut rendered = format!(
+            "{:<width$}  {:>5}  {:>5}  {:>5}\n",
+            "Warnings",
+            "Src",
+            "Lib",
+            "All",
+            width = code_width
+        );
+        let mut totals = CodeStats::default();
+        for (code, stats) in &self.by_code {
+            rendered.push_str(&format!(
+                "{:<width$}  {:>5}  {:>5}  {:>5}\n",
+                code,
+                stats.src,
+                stats.lib,
+                stats.src + stats.lib,
+                width = code_width
+            ));
+            totals.src += stats.src;
+            totals.lib += stats.lib;
+        }
+        rendered.push_str(&format!(
+            "{:<width$}  {:>5}  {:>5}  {:>5}\n",
+            "Total",
+            totals.src,
+            totals.lib,
+            totals.src + totals.lib,
+            width = code_width
+        ));
+        rendered
+    }
+}
+
+/// Apply the censoring, filtering and strict options to a compiler output.
+///
+/// Statistics are gathered from every warning before anything is censored.
+/// Errors are never censored. In strict mode any remaining source warnings
+/// are promoted to errors so the build fails.
+pub fn apply(options: &PsaOptionsConfig, output: CompilerOutput) -> (CompilerOutput, WarningStats) {
+    let mut stats = WarningStats::default();
+    let mut result = CompilerOutput {
+        warnings: Vec::new(),
+        errors: output.errors,
+    };
+
+    for mut warning in output.warnings {
+        stats.record(&warning);
+        if is_censored(options, &warning) {
+            continue;
+        }
+        if !options.verbose_warnings {
+            warning.message = abbreviate_message(&warning.message);
+        }
+        if options.strict && !is_lib_diagnostic(&warning) {
+            result.errors.push(warning);
+        } else {
+            result.warnings.push(warning);
+        }
+    }
+
+    (result, stats)
+}
+
+/// Whether a warning should be hidden under the given options
+fn is_censored(options: &PsaOptionsConfig, warning: &Diagnostic) -> bool {
+    let code = &warning.error_code;
+    let is_lib = is_lib_diagnostic(warning);
+
+    options.censor_warnings
+        || (options.censor_lib && is_lib)
+        || (options.censor_src && !is_lib)
+        || options.censor_codes.contains(code)
+        || (!options.filter_codes.is_empty() && !options.filter_codes.contains(code))
+}
+
+/// Whether a diagnostic comes from a dependency rather than the project itself.
+/// Paths are relative to the workspace root, where purs runs.
+fn is_lib_diagnostic(diagnostic: &Diagnostic) -> bool {
+    diagnostic.filename.as_deref().is_some_and(|filename| {
+        Path::new(filename)
+            .components()
+            .any(|component| match component {
+                Component::Normal(name) => LIB_DIRS.iter().any(|dir| name == *dir),
+                _ => false,
+            })
+    })
+}
+
+/// Drop the trailing context paragraphs purs adds to messages
+/// ("while checking that ...", "in value declaration ..."), keeping the
+/// part that describes the problem.
+fn abbreviate_message(message: &str) -> String {
+    let paragraphs: Vec<&str> = message.trim_end().split("\n\n").collect();
+    let keep = paragraphs
+        .iter()
+        .position(|paragraph| {
+            let first_line = paragraph.trim_start();
+            first_line.starts_with("while ") || first_line.starts_with("in ")
+        })
+        .filter(|&index| index > 0)
+        .unwrap_or(paragraphs.len());
+
+    let mut abbreviated = paragraphs[..keep].join("\n\n");
+    abbreviated.push('\n');
+    abbreviated
+}
+
+/// The stash file location for the given options, relative to the workspace root.
+/// Returns `None` when stashing is disabled.
+pub fn stash_path(options: &PsaOptionsConfig, workspace_root: &Path) -> Option<PathBuf> {
+    match &options.stash_file {
+        Some(file) => Some(workspace_root.join(file)),
+        None if options.stash => Some(workspace_root.join(DEFAULT_STASH_FILE)),
+        None => None,
+    }
+}
+
+/// Restore warnings from previous builds for modules purs did not recompile,
+/// then write the combined warnings back to the stash.
+///
+/// Stashed warnings are dropped when their module was recompiled (it will have
+/// reported its current warnings) or when their source file no longer exists.
+pub fn replay_stash(
+    stash_file: &Path,
+    workspace_root: &Path,
+    output: &mut CompilerOutput,
+    compiled_modules: &HashSet<String>,
+) -> Result<()> {
+    let stashed = read_stash(stash_file);
+
+    let reported_modules: HashSet<&str> = output
+        .warnings
+        .iter()
+        .chain(output.errors.iter())
+        .filter_map(|diagnostic| diagnostic.module_name.as_deref())
+        .collect();
+
+    let replayed: Vec<Diagnostic> = stashed
+        .into_iter()
+        .filter(|warning| match &warning.module_name {
+            Some(module) => {
+                !compiled_modules.contains(module) && !reported_modules.contains(module.as_str())
+            }
+            None => false,
+        })
+        .filter(|warning| {
+            warning
+                .filename
+                .as_ref()
+                .is_some_and(|filename| workspace_root.join(filename).exists())
+        })
+        .collect();
+
+    output.warnings.extend(replayed);
+
+    let stash = CompilerOutput {
+        warnings: output.warnings.clone(),
+        errors: Vec::new(),
+    };
+    let content = serde_json::to_string(&stash).context("Failed to serialize warning stash")?;
+    fs::write(stash_file, content)
+        .with_context(|| format!("Failed to write warning stash {}", stash_file.display()))?;
+
+    Ok(())
+}
+
+/// Previously stashed warnings, empty if the stash is missing or unreadable
+fn read_stash(stash_file: &Path) -> Vec<Diagnostic> {
+    fs::read_to_string(stash_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CompilerOutput>(&content).ok())
+        .map(|stash| stash.warnings)
+        .unwrap_or_default()
+}
+
+/// The module name from a purs progress line such as `[3 of 10] Compiling Data.Foo`
+pub fn compiled_module(line: &str) -> Option<String> {
+    let (_, rest) = line.trim().strip_prefix('[')?.split_once("] Compiling ")?;
+    let module = rest.split_whitespace().next()?;
+    Some(module.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn default_options() -> PsaOptionsConfig {
+        PsaOptionsConfig {
+            verbose_stats: false,
+            verbose_warnings: true,
+            censor_warnings: false,
+            censor_lib: false,
+            censor_src: false,
+            censor_codes: vec![],
+            filter_codes: vec![],
+            no_colors: false,
+            no_source: false,
+            strict: false,
+            stash: false,
+            stash_file: None,
+        }
+    }
+
+    fn warning(code: &str, module: &str, filename: &str) -> Diagnostic {
+        Diagnostic {
+            module_name: Some(module.to_string()),
+            error_code: code.to_string(),
+            error_link: None,
+            message: format!("{} in {}\n", code, module),
+            filename: Some(filename.to_string()),
+            position: None,
+            suggestion: None,
+        }
+    }
+
+    fn sample_output() -> CompilerOutput {
+        CompilerOutput {
+            warnings: vec![
+                warning("UnusedImport", "Main", "src/Main.purs"),
+                warning("ShadowedName", "Main", "src/Main.purs"),
+                warning(
+                    "UnusedImport",
+                    "Data.Foo",
+                    ".spago/foo/v1.0.0/src/Data/Foo.purs",
+                ),
+            ],
+            errors: vec![],
+        }
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&str> {
+        diagnostics.iter().map(|d| d.error_code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_apply_without_options_keeps_everything() {
+        let (output, _) = apply(&default_options(), sample_output());
+        assert_eq!(output.warnings.len(), 3);
+        assert!(output.errors.is_empty());
+    }
+
+    #[test]
+    fn test_apply_censor_lib_and_src() {
+        let mut options = default_options();
+        options.censor_lib = true;
+        let (output, _) = apply(&options, sample_output());
+        assert_eq!(
+            codes(&output.warnings),
+            vec!["UnusedImport", "ShadowedName"]
+        );
+
+        let mut options = default_options();
+        options.censor_src = true;
+        let (output, _) = apply(&options, sample_output());
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].module_name.as_deref(), Some("Data.Foo"));
+    }
+
+    #[test]
+    fn test_apply_censor_and_filter_codes() {
+        let mut options = default_options();
+        options.censor_codes = vec!["UnusedImport".to_string()];
+        let (output, _) = apply(&options, sample_output());
+        assert_eq!(codes(&output.warnings), vec!["ShadowedName"]);
+
+        let mut options = default_options();
+        options.filter_codes = vec!["UnusedImport".to_string()];
+        let (output, _) = apply(&options, sample_output());
+        assert_eq!(
+            codes(&output.warnings),
+            vec!["UnusedImport", "UnusedImport"]
+        );
+    }
+
+    #[test]
+    fn test_apply_censor_warnings_never_hides_errors() {
+        let mut options = default_options();
+        options.censor_warnings = true;
+        let mut input = sample_output();
+        input
+            .errors
+            .push(warning("TypesDoNotUnify", "Main", "src/Main.purs"));
+
+        let (output, stats) = apply(&options, input);
+
+        assert!(output.warnings.is_empty());
+        assert_eq!(codes(&output.errors), vec!["TypesDoNotUnify"]);
+        // Stats still count censored warnings
+        assert_eq!(stats.by_code["UnusedImport"], CodeStats { src: 1, lib: 1 });
+    }
+
+    #[test]
+    fn test_apply_strict_promotes_src_warnings_only() {
+        let mut options = default_options();
+        options.strict = true;
+        let (output, _) = apply(&options, sample_output());
+
+        assert_eq!(codes(&output.errors), vec!["UnusedImport", "ShadowedName"]);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].module_name.as_deref(), Some("Data.Foo"));
+    }
+
+    #[test]
+    fn test_apply_strict_ignores_censored_warnings() {
+        let mut options = default_options();
+        options.strict = true;
+        options.censor_codes = vec!["UnusedImport".to_string(), "ShadowedName".to_string()];
+        let (output, _) = apply(&options, sample_output());
+
+        assert!(output.errors.is_empty());
+    }
+
+    #[test]
+    fn test_abbreviate_message_drops_context() {
+        let message =
+            "Name x was shadowed.\n\nwhile inferring the type of y\n\nin value declaration main\n";
+        assert_eq!(abbreviate_message(message), "Name x was shadowed.\n");
+        assert_eq!(abbreviate_message("Plain message\n"), "Plain message\n");
+    }
+
+    #[test]
+    fn test_stats_render() {
+        let (_, stats) = apply(&default_options(), sample_output());
+        let rendered = stats.render();
+
+        assert!(rendered.contains("ShadowedName"));
+        assert!(rendered
+            .lines()
+            .any(|line| line.starts_with("UnusedImport") && line.ends_with("1      1      2")));
+        assert!(rendered
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("2      1      3"));
+    }
+
+    #[test]
+    fn test_stash_path() {
+        let root = Path::new("/project");
+        assert_eq!(stash_path(&default_options(), root), None);
+
+        let mut options = default_options();
+        options.stash = true;
+        assert_eq!(
+            stash_path(&options, root),
+            Some(PathBuf::from("/project/.psa-stash"))
+        );
+
+        options.stash_file = Some("build/warnings.json".to_string());
+        assert_eq!(
+            stash_path(&options, root),
+            Some(PathBuf::from("/project/build/warnings.json"))
+        );
+    }
+
+    #[test]
+    fn test_replay_stash_restores_warnings_for_cached_modules() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/Main.purs"), "module Main where").unwrap();
+        fs::write(root.join("src/Util.purs"), "module Util where").unwrap();
+        let stash_file = root.join(DEFAULT_STASH_FILE);
+
+        // First build compiles everything
+        let mut first = CompilerOutput {
+            warnings: vec![
+                warning("UnusedImport", "Main", "src/Main.purs"),
+                warning("ShadowedName", "Util", "src/Util.purs"),
+                warning("UnusedImport", "Gone", "src/Gone.purs"),
+            ],
+            errors: vec![],
+        };
+        let compiled = HashSet::from(["Main".to_string(), "Util".to_string()]);
+        replay_stash(&stash_file, root, &mut first, &compiled).unwrap();
+        assert_eq!(first.warnings.len(), 3);
+
+        // Second build only recompiles Main, which is now clean
+        let mut second = CompilerOutput::default();
+        let compiled = HashSet::from(["Main".to_string()]);
+        replay_stash(&stash_file, root, &mut second, &compiled).unwrap();
+
+        assert_eq!(codes(&second.warnings), vec!["ShadowedName"]);
+        assert_eq!(read_stash(&stash_file).len(), 1);
+    }
+
+    #[test]
+    fn test_compiled_module() {
+        assert_eq!(
+            compiled_module("[3 of 10] Compiling Data.Foo"),
+            Some("Data.Foo".to_string())
+        );
+        assert_eq!(
+            compiled_module("[ 3 of 10] Compiling Main"),
+            Some("Main".to_string())
+        );
+        assert_eq!(compiled_module("Compiling Main"), None);
+        assert_eq!(compiled_module("[1 of 2] Linking"), None);
+    }
+}
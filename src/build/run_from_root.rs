@@ -1,7 +1,9 @@
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 
 use anyhow::{Context, Result};
 use regex::Regex;
+use serde_json::Value;
 
 /// Map sources to be relative to the output directory instead of the current working directory.
 ///
@@ -126,21 +128,7 @@ fn map_diagnostic_paths_from_output_to_cwd_impl(
     let output_dir = resolve_to_absolute(workspace_root, cwd)?;
 
     // Helper function to map a single path
-    let map_path = |path_str: &str| -> String {
-        let path = Path::new(path_str);
-
-        // Skip if it's already an absolute path (starts with /)
-        if path.is_absolute() {
-            return path_str.to_string();
-        }
-
-        // Join the path with the output directory and normalize
-        let joined = output_dir.join(path);
-        let normalized = normalize_path(&joined);
-
-        // Make it relative to CWD
-        make_relative(&normalized, &cwd)
-    };
+    let map_path = |path_str: &str| -> String { map_path_to_cwd(path_str, &output_dir, cwd) };
 
     // Pattern 1: Match paths in JSON format like "filename":"../path/to/file.purs" or "name":"../path/to/file.purs"
     let json_path_pattern =
@@ -159,6 +147,92 @@ fn map_diagnostic_paths_from_output_to_cwd_impl(
     Ok(result.to_string())
 }
 
+/// Map a single path from workspace-root relative to CWD relative.
+///
+/// Used for structured diagnostics, where the file path is already known
+/// and doesn't need to be found within a line of output.
+pub fn map_path_from_workspace_to_cwd(path: &str, workspace_root: &Path) -> Result<String> {
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let workspace_root = resolve_to_absolute(workspace_root, &cwd)?;
+    Ok(map_path_to_cwd(path, &workspace_root, &cwd))
+}
+
+/// Rewrite the `sources` of a purs source map to resolve from the map's directory.
+///
+/// purs writes each source as one `../` per component of `--output`, plus one
+/// for the module directory, followed by the path relative to where it ran
+/// (the workspace root). That only works while the output directory sits
+/// beneath the workspace root, which a nested package's custom output may not.
+pub fn map_source_map_paths(
+    map_file: &Path,
+    relative_output: &str,
+    workspace_root: &Path,
+) -> Result<()> {
+    let cwd = std::env::current_dir().context("Failed to get current working directory")?;
+    let workspace_root = resolve_to_absolute(workspace_root, &cwd)?;
+    let map_dir = resolve_to_absolute(map_file.parent().unwrap_or(Path::new(".")), &cwd)?;
+
+    let contents = fs::read_to_string(map_file)
+        .with_context(|| format!("Failed to read source map {}", map_file.display()))?;
+    let mut source_map: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Invalid source map {}", map_file.display()))?;
+
+    let depth = Path::new(relative_output)
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .count()
+        + 1;
+    if let Some(sources) = source_map.get_mut("sources").and_then(Value::as_array_mut) {
+        for source in sources.iter_mut() {
+            if let Some(path) = source.as_str() {
+                *source = Value::String(map_source_path(path, depth, &workspace_root, &map_dir));
+            }
+        }
+    }
+
+    fs::write(map_file, serde_json::to_string(&source_map)?)
+        .with_context(|| format!("Failed to write source map {}", map_file.display()))
+}
+
+/// Map one source map entry written `depth` levels below the workspace root
+/// to be relative to the (absolute) map directory instead.
+fn map_source_path(path: &str, depth: usize, workspace_root: &Path, map_dir: &Path) -> String {
+    let source = Path::new(path);
+    if source.is_absolute() {
+        return make_relative(source, map_dir);
+    }
+
+    let components: Vec<Component> = source.components().collect();
+    let parents = components
+        .iter()
+        .take_while(|c| matches!(c, Component::ParentDir))
+        .count();
+    if parents < depth {
+        return path.to_string(); // Not a path purs wrote
+    }
+
+    let from_root: PathBuf = components[depth..].iter().collect();
+    make_relative(&normalize_path(&workspace_root.join(from_root)), map_dir)
+}
+
+/// Map a path relative to the (absolute) workspace root to be relative to CWD.
+/// Absolute paths are returned unchanged.
+fn map_path_to_cwd(path_str: &str, workspace_root: &Path, cwd: &Path) -> String {
+    let path = Path::new(path_str);
+
+    // Skip if it's already an absolute path (starts with /)
+    if path.is_absolute() {
+        return path_str.to_string();
+    }
+
+    // Join the path with the workspace root and normalize
+    let joined = workspace_root.join(path);
+    let normalized = normalize_path(&joined);
+
+    // Make it relative to CWD
+    make_relative(&normalized, cwd)
+}
+
 /// Normalize a path by resolving `.` and `..` components.
 /// Works even if the path doesn't exist on disk.
 fn normalize_path(path: &Path) -> PathBuf {
@@ -421,6 +495,25 @@ mod tests {
         assert!(result.starts_with("output/Main.purs:5:10:"));
     }
 
+    #[test]
+    fn test_map_path_to_cwd_from_nested_package() {
+        let workspace_root = Path::new("/project");
+        let cwd = Path::new("/project/packages/app");
+
+        assert_eq!(
+            map_path_to_cwd("packages/app/src/Main.purs", workspace_root, cwd),
+            "src/Main.purs"
+        );
+        assert_eq!(
+            map_path_to_cwd("packages/lib/src/Lib.purs", workspace_root, cwd),
+            "../lib/src/Lib.purs"
+        );
+        assert_eq!(
+            map_path_to_cwd("/abs/Main.purs", workspace_root, cwd),
+            "/abs/Main.purs"
+        );
+    }
+
     #[test]
     fn test_make_path_relative_to_workspace_nested_project_with_custom_output() {
         // Scenario: root at /project, output configured as "purs-projects",
@@ -454,4 +547,64 @@ mod tests {
         let result = make_path_relative_to_workspace_impl(output_dir, workspace_root, cwd).unwrap();
         assert_eq!(result, "output");
     }
+
+    #[test]
+    fn test_map_source_path_output_inside_workspace() {
+        let root = Path::new("/workspace");
+        let map_dir = Path::new("/workspace/output/Main");
+
+        assert_eq!(
+            map_source_path("../../src/Main.purs", 2, root, map_dir),
+            "../../src/Main.purs"
+        );
+        assert_eq!(
+            map_source_path("../../packages/lib/src/Lib.purs", 2, root, map_dir),
+            "../../packages/lib/src/Lib.purs"
+        );
+    }
+
+    #[test]
+    fn test_map_source_path_output_outside_workspace() {
+        // --output ../build/output from /workspace: purs wrote four `../`
+        let root = Path::new("/workspace");
+        let map_dir = Path::new("/build/output/Main");
+
+        assert_eq!(
+            map_source_path("../../../../src/Main.purs", 4, root, map_dir),
+            "../../../workspace/src/Main.purs"
+        );
+    }
+
+    #[test]
+    fn test_map_source_path_leaves_other_paths() {
+        let root = Path::new("/workspace");
+        let map_dir = Path::new("/workspace/output/Main");
+
+        assert_eq!(map_source_path("Main.purs", 2, root, map_dir), "Main.purs");
+        assert_eq!(
+            map_source_path("/workspace/src/Main.purs", 2, root, map_dir),
+            "../../src/Main.purs"
+        );
+    }
+
+    #[test]
+    fn test_map_source_map_paths_rewrites_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = temp.path().join("workspace");
+        let map_dir = temp.path().join("build/Main");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&map_dir).unwrap();
+        let map_file = map_dir.join("index.js.map");
+        std::fs::write(
+            &map_file,
+            r#"{"version":3,"file":"index.js","sources":["../../../src/Main.purs"],"mappings":""}"#,
+        )
+        .unwrap();
+
+        map_source_map_paths(&map_file, "../build", &root).unwrap();
+
+        let rewritten: Value =
+            serde_json::from_str(&std::fs::read_to_string(&map_file).unwrap()).unwrap();
+        assert_eq!(rewritten["sources"][0], "../../workspace/src/Main.purs");
+    }
 }
@@ -0,0 +1,306 @@
+//! Per-phase build timings and the compiler's GHC runtime statistics.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// The first line GHC prints for `+RTS -s`, everything after it is statistics
+const RTS_STATS_START: &str = "bytes allocated in the heap";
+
+/// Runtime statistics parsed from the compiler's `+RTS -s` output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RtsStats {
+    pub bytes_allocated: Option<u64>,
+    pub bytes_copied_during_gc: Option<u64>,
+    pub max_residency_bytes: Option<u64>,
+    /// Memory the runtime held at its peak
+    pub peak_memory_mb: Option<u64>,
+    pub mutator_cpu_seconds: Option<f64>,
+    pub mutator_elapsed_seconds: Option<f64>,
+    pub gc_cpu_seconds: Option<f64>,
+    pub gc_elapsed_seconds: Option<f64>,
+    pub total_cpu_seconds: Option<f64>,
+    pub total_elapsed_seconds: Option<f64>,
+}
+
+impl RtsStats {
+    /// Whether a line of compiler stderr starts the statistics block
+    pub fn is_start(line: &str) -> bool {
+        line.contains(RTS_STATS_START)
+    }
+
+    /// Record any figure found on a line of `+RTS -s` output
+    pub fn parse_line(&mut self, line: &str) {
+        let line = line.trim();
+        let first_number = || parse_number(line.split_whitespace().next()?);
+
+        if line.ends_with(RTS_STATS_START) {
+            self.bytes_allocated = first_number();
+        } else if line.ends_with("bytes copied during GC") {
+            self.bytes_copied_during_gc = first_number();
+        } else if line.contains("bytes maximum residency") {
+            self.max_residency_bytes = first_number();
+        } else if line.contains("total memory in use") {
+            self.peak_memory_mb = first_number();
+        } else if let Some((label, cpu, elapsed)) = parse_time_line(line) {
+            match label {
+                "MUT" => {
+                    self.mutator_cpu_seconds = Some(cpu);
+                    self.mutator_elapsed_seconds = elapsed;
+                }
+                "GC" => {
+                    self.gc_cpu_seconds = Some(cpu);
+                    self.gc_elapsed_seconds = elapsed;
+                }
+                "Total" => {
+                    self.total_cpu_seconds = Some(cpu);
+                    self.total_elapsed_seconds = elapsed;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut rendered = String::from("Compiler runtime\n");
+        let mut row = |label: &str, value: Option<String>| {
+            if let Some(value) = value {
+                rendered.push_str(&format!("  {:<16} {:>12}\n", label, value));
+            }
+        };
+
+        row("Allocated", self.bytes_allocated.map(format_bytes));
+        row("Max residency", self.max_residency_bytes.map(format_bytes));
+        row(
+            "Peak memory",
+            self.peak_memory_mb.map(|mb| format!("{} MB", mb)),
+        );
+        row(
+            "Mutator time",
+            self.mutator_elapsed_seconds.map(format_seconds),
+        );
+        row("GC time", self.gc_elapsed_seconds.map(format_seconds));
+        row(
+            "GC share",
+            self.gc_elapsed_seconds
+                .zip(self.total_elapsed_seconds)
+                .filter(|(_, total)| *total > 0.0)
+                .map(|(gc, total)| format!("{:.1}%", gc / total * 100.0)),
+        );
+        rendered
+    }
+}
+
+/// `<label> time <cpu>s (<elapsed>s elapsed)`
+fn parse_time_line(line: &str) -> Option<(&str, f64, Option<f64>)> {
+    let mut tokens = line.split_whitespace();
+    let label = tokens.next()?;
+    if tokens.next()? != "time" {
+        return None;
+    }
+    let mut seconds = tokens.filter_map(|token| {
+        token
+            .trim_matches(|c| c == '(' || c == ')')
+            .strip_suffix('s')?
+            .parse::<f64>()
+            .ok()
+    });
+    let cpu = seconds.next()?;
+    Some((label, cpu, seconds.next()))
+}
+
+/// Numbers in RTS output use thousands separators
+fn parse_number(token: &str) -> Option<u64> {
+    token.replace(',', "").parse().ok()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format!("{:.3}s", seconds)
+}
+
+/// How long one phase of the build took
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTiming {
+    pub name: String,
+    pub seconds: f64,
+}
+
+/// Wall time of each build phase, recorded one after the other
+#[derive(Debug, Serialize)]
+pub struct Timings {
+    /// Unix time the build started, to line reports up over time
+    pub started_at: u64,
+    pub phases: Vec<PhaseTiming>,
+    pub total_seconds: f64,
+    /// False when the build was skipped as up to date
+    pub compiled: bool,
+    pub rts: Option<RtsStats>,
+    #[serde(skip)]
+    started: Instant,
+    #[serde(skip)]
+    phase_started: Instant,
+}
+
+impl Timings {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            phases: vec![],
+            total_seconds: 0.0,
+            compiled: false,
+            rts: None,
+            started: now,
+            phase_started: now,
+        }
+    }
+
+    /// Record the time since the previous phase finished
+    pub fn finish_phase(&mut self, name: &str) {
+        let now = Instant::now();
+        self.phases.push(PhaseTiming {
+            name: name.to_string(),
+            seconds: (now - self.phase_started).as_secs_f64(),
+        });
+        self.phase_started = now;
+    }
+
+    /// Stop the clock on the whole build
+    pub fn finish(&mut self) {
+        self.total_seconds = self.started.elapsed().as_secs_f64();
+    }
+
+    pub fn render(&self) -> String {
+        let width = self
+            .phases
+            .iter()
+            .map(|phase| phase.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Total".len());
+
+        let mut rendered = String::from("Build timings\n");
+        for phase in &self.phases {
+            rendered.push_str(&format!(
+                "  {:<width$}  {:>10}\n",
+                phase.name,
+                format_seconds(phase.seconds),
+                width = width
+            ));
+        }
+        rendered.push_str(&format!(
+            "  {:<width$}  {:>10}\n",
+            "Total",
+            format_seconds(self.total_seconds),
+            width = width
+        ));
+
+        if let Some(rts) = &self.rts {
+            rendered.push('\n');
+            rendered.push_str(&rts.render());
+        }
+        rendered
+    }
+
+    /// Write the report as JSON for tracking builds over time
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).context("Failed to create timings directory")?;
+            }
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write timings to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RTS_OUTPUT: &str = "\
+ 105,800,243,312 bytes allocated in the heap
+  12,345,678,901 bytes copied during GC
+     987,654,321 bytes maximum residency (12 sample(s))
+      12,345,678 bytes maximum slop
+            2345 MiB total memory in use (0 MB lost due to fragmentation)
+
+                                     Tot time (elapsed)  Avg pause  Max pause
+  Gen  0      1234 colls,  1234 par   20.123s   5.456s     0.0044s    0.1234s
+
+  INIT    time    0.001s  (  0.001s elapsed)
+  MUT     time   45.123s  ( 20.456s elapsed)
+  GC      time   30.789s  ( 10.123s elapsed)
+  EXIT    time    0.001s  (  0.001s elapsed)
+  Total   time   75.914s  ( 30.581s elapsed)
+
+  %GC     time      40.6%  (33.1% elapsed)
+";
+
+    #[test]
+    fn test_parse_rts_stats() {
+        let mut stats = RtsStats::default();
+        for line in RTS_OUTPUT.lines() {
+            stats.parse_line(line);
+        }
+
+        assert_eq!(stats.bytes_allocated, Some(105_800_243_312));
+        assert_eq!(stats.bytes_copied_during_gc, Some(12_345_678_901));
+        assert_eq!(stats.max_residency_bytes, Some(987_654_321));
+        assert_eq!(stats.peak_memory_mb, Some(2345));
+        assert_eq!(stats.mutator_cpu_seconds, Some(45.123));
+        assert_eq!(stats.gc_elapsed_seconds, Some(10.123));
+        assert_eq!(stats.total_cpu_seconds, Some(75.914));
+        assert_eq!(stats.total_elapsed_seconds, Some(30.581));
+    }
+
+    #[test]
+    fn test_rts_stats_start() {
+        assert!(RtsStats::is_start(RTS_OUTPUT.lines().next().unwrap()));
+        assert!(!RtsStats::is_start("[1 of 3] Compiling Main"));
+    }
+
+    #[test]
+    fn test_timings_json() {
+        let mut timings = Timings::start();
+        timings.finish_phase("Load config");
+        timings.finish_phase("Compile");
+        timings.finish();
+
+        let json: serde_json::Value = serde_json::to_value(&timings).unwrap();
+        let phases: Vec<&str> = json["phases"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|phase| phase["name"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(phases, vec!["Load config", "Compile"]);
+        assert!(json["total_seconds"].as_f64().is_some());
+        assert!(json["rts"].is_null());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512.0 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::build::compiler::{self, CompilerOptions};
+use crate::build::resolve_build_sources;
+use crate::config::SpagoConfig;
+use crate::install::install_all_dependencies;
+use crate::registry::{PackageQuery, PackageSet};
+
+/// How long to wait for more file events before acting on a change.
+/// Editors tend to write several files (or the same file several times) per save.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// What kind of rebuild a batch of file events requires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// Only source files changed, recompile with the existing globs
+    Sources,
+    /// A spago.yaml changed, dependencies and globs need resolving again
+    Config,
+}
+
+/// Compiler settings that stay fixed for the lifetime of a watch session
+pub struct WatchSettings {
+    pub test: bool,
+    pub compiler_args: Vec<String>,
+    pub include_rts_stats: bool,
+    pub json_errors: bool,
+    pub verbose: bool,
+}
+
+impl WatchSettings {
+    /// Compiler options for the current config, which may change between rebuilds
+    fn compiler_options(&self, config: &SpagoConfig) -> Result<CompilerOptions> {
+        let mut options = CompilerOptions::from_config(config, self.verbose)?;
+        options.compiler_args = self.compiler_args.clone();
+        options.include_rts_stats = self.include_rts_stats;
+        options.json_errors = self.json_errors;
+        Ok(options)
+    }
+}
+
+/// Watch the build inputs and recompile whenever they change.
+///
+/// The package set and source globs are resolved once up front and only
+/// re-resolved (with a reinstall) when a spago.yaml changes.
+pub async fn execute(
+    mut config: SpagoConfig,
+    mut package_set: PackageSet,
+    mut sources: Vec<String>,
+    settings: WatchSettings,
+) -> Result<()> {
+    let mut watcher = SourceWatcher::new(
+        &sources,
+        &config_files(&config, &package_set),
+        &config.output_dir(),
+    )?;
+
+    compile(&config, &sources, &settings).await?;
+
+    loop {
+        println!("{}", "Watching for changes...".dimmed());
+
+        let change = match watcher.next_change().await {
+            Some(change) => change,
+            None => return Ok(()),
+        };
+
+        if change == ChangeKind::Config {
+            println!("{} spago.yaml changed, reinstalling", "→".cyan());
+            match reload(settings.test, settings.verbose).await {
+                Ok((new_config, new_package_set, new_sources)) => {
+                    config = new_config;
+                    package_set = new_package_set;
+                    sources = new_sources;
+                    watcher = SourceWatcher::new(
+                        &sources,
+                        &config_files(&config, &package_set),
+                        &config.output_dir(),
+                    )?;
+                }
+                Err(e) => {
+                    eprintln!("{} {:#}", "Error:".red().bold(), e);
+                    continue;
+                }
+            }
+        }
+
+        compile(&config, &sources, &settings).await?;
+    }
+}
+
+/// Run a single compilation, reporting the outcome without exiting on failure
+async fn compile(config: &SpagoConfig, sources: &[String], settings: &WatchSettings) -> Result<()> {
+    let outcome = compiler::run_compiler(sources, &settings.compiler_options(config)?).await?;
+
+    if outcome.success {
+        println!("{} Build successful", "✓".green());
+    } else {
+        compiler::report_failure(&outcome);
+    }
+
+    Ok(())
+}
+
+/// Reload the configuration, reinstall dependencies and regenerate the source globs
+async fn reload(test: bool, verbose: bool) -> Result<(SpagoConfig, PackageSet, Vec<String>)> {
+    let config =
+        crate::config::load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    let package_set = config.package_set().await?;
+    install_all_dependencies(&config, &package_set, test).await?;
+    let sources = resolve_build_sources(&config, package_set.clone(), test, verbose).await?;
+    Ok((config, package_set, sources))
+}
+
+/// All spago.yaml files that can affect the build: the current package,
+/// the workspace root and every local workspace package.
+pub fn config_files(config: &SpagoConfig, package_set: &PackageSet) -> Vec<PathBuf> {
+    let mut files = vec![
+        PathBuf::from("spago.yaml"),
+        config.workspace_root.join("spago.yaml"),
+    ];
+    let query = PackageQuery::new(package_set);
+    for package in query.local_packages() {
+        files.push(package.path.join("spago.yaml"));
+    }
+    files
+}
+
+/// File system watcher over a set of source globs and config files
+pub struct SourceWatcher {
+    // Kept alive for as long as we want to receive events
+    _watcher: RecommendedWatcher,
+    events: UnboundedReceiver<notify::Result<Event>>,
+    config_files: HashSet<PathBuf>,
+    output_dir: Option<PathBuf>,
+}
+
+impl SourceWatcher {
+    pub fn new(sources: &[String], config_files: &[PathBuf], output_dir: &Path) -> Result<Self> {
+        let (sender, events) = unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver only goes away when we stop watching
+            let _ = sender.send(event);
+        })
+        .context("Failed to start file watcher")?;
+
+        for root in watch_roots(sources) {
+            if root.exists() {
+                watcher
+                    .watch(&root, RecursiveMode::Recursive)
+                    .context(format!("Failed to watch {}", root.display()))?;
+            }
+        }
+
+        // Watch the directories containing the config files rather than the
+        // files themselves, editors often replace files on save.
+        let mut config_dirs = HashSet::new();
+        let mut canonical_configs = HashSet::new();
+        for file in config_files {
+            if let Ok(canonical) = file.canonicalize() {
+                if let Some(parent) = canonical.parent() {
+                    config_dirs.insert(parent.to_path_buf());
+                }
+                canonical_configs.insert(canonical);
+            }
+        }
+        for dir in config_dirs {
+            watcher
+                .watch(&dir, RecursiveMode::NonRecursive)
+                .context(format!("Failed to watch {}", dir.display()))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            config_files: canonical_configs,
+            output_dir: output_dir.canonicalize().ok(),
+        })
+    }
+
+    /// Wait for the next relevant change, then keep collecting events until
+    /// things have been quiet for the debounce period.
+    ///
+    /// Returns `None` if the watcher has shut down.
+    pub async fn next_change(&mut self) -> Option<ChangeKind> {
+        let mut change = None;
+        loop {
+            let event = if change.is_none() {
+                self.events.recv().await?
+            } else {
+                match tokio::time::timeout(DEBOUNCE, self.events.recv()).await {
+                    Ok(Some(event)) => event,
+                    Ok(None) | Err(_) => return change,
+                }
+            };
+
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("{} {}", "Watch error:".yellow(), e);
+                    continue;
+                }
+            };
+
+            if let Some(kind) = self.classify(&event) {
+                change = change.max(Some(kind));
+            }
+        }
+    }
+
+    /// Decide whether an event should trigger a rebuild
+    fn classify(&self, event: &Event) -> Option<ChangeKind> {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return None;
+        }
+
+        event
+            .paths
+            .iter()
+            .filter_map(|path| {
+                if let Some(output_dir) = &self.output_dir {
+                    if path.starts_with(output_dir) {
+                        return None;
+                    }
+                }
+                if path.file_name().and_then(|n| n.to_str()) == Some("spago.yaml") {
+                    // The file itself may be mid-replace, so resolve through its directory
+                    let canonical = path
+                        .parent()
+                        .and_then(|parent| parent.canonicalize().ok())
+                        .map(|parent| parent.join("spago.yaml"))
+                        .unwrap_or_else(|| path.clone());
+                    return self
+                        .config_files
+                        .contains(&canonical)
+                        .then_some(ChangeKind::Config);
+                }
+                is_source_file(path).then_some(ChangeKind::Sources)
+            })
+            .max()
+    }
+}
+
+/// PureScript sources and their JavaScript FFI files
+fn is_source_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("purs") | Some("js")
+    )
+}
+
+/// The directories to watch recursively for a set of source globs.
+///
+/// Each glob is reduced to the directory before its first wildcard, and
+/// directories already covered by a parent are dropped.
+pub fn watch_roots(sources: &[String]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = sources.iter().map(|s| glob_base_dir(s)).collect();
+    roots.sort();
+    roots.dedup();
+
+    let mut result: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        if !result.iter().any(|existing| root.starts_with(existing)) {
+            result.push(root);
+        }
+    }
+    result
+}
+
+/// The non-wildcard directory prefix of a glob pattern
+fn glob_base_dir(pattern: &str) -> PathBuf {
+    let base = match pattern.find(['*', '?', '[']) {
+        Some(index) => &pattern[..index],
+        None => pattern,
+    };
+
+    match base.rfind('/') {
+        Some(0) => PathBuf::from("/"),
+        Some(index) => PathBuf::from(&base[..index]),
+        None => PathBuf::from("."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_base_dir() {
+        assert_eq!(glob_base_dir("./src/**/*.purs"), PathBuf::from("./src"));
+        assert_eq!(
+            glob_base_dir(".spago/prelude/src/**/*.purs"),
+            PathBuf::from(".spago/prelude/src")
+        );
+        assert_eq!(glob_base_dir("*.purs"), PathBuf::from("."));
+        assert_eq!(glob_base_dir("src/Main.purs"), PathBuf::from("src"));
+    }
+
+    #[test]
+    fn test_watch_roots_drops_nested_directories() {
+        let sources = vec![
+            "./src/**/*.purs".to_string(),
+            "./src/nested/**/*.purs".to_string(),
+            ".spago/prelude/src/**/*.purs".to_string(),
+            "./test/**/*.purs".to_string(),
+        ];
+
+        let roots = watch_roots(&sources);
+
+        assert_eq!(
+            roots,
+            vec![
+                PathBuf::from("./src"),
+                PathBuf::from("./test"),
+                PathBuf::from(".spago/prelude/src"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_source_file() {
+        assert!(is_source_file(Path::new("src/Main.purs")));
+        assert!(is_source_file(Path::new("src/Main.js")));
+        assert!(!is_source_file(Path::new("src/Main.purs.swp")));
+        assert!(!is_source_file(Path::new("README.md")));
+    }
+
+    #[test]
+    fn test_change_kind_ordering() {
+        assert!(ChangeKind::Config > ChangeKind::Sources);
+        assert_eq!(
+            Some(ChangeKind::Sources).max(Some(ChangeKind::Config)),
+            Some(ChangeKind::Config)
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use tokio::process::Command;
+
+use crate::config::{BundleConfig, BundleType, Platform, SpagoConfig};
+
+const DEFAULT_MODULE: &str = "Main";
+const DEFAULT_OUTFILE: &str = "index.js";
+const DEFAULT_BUNDLER: &str = "esbuild";
+
+/// Bundle options from the command line, each overriding `package.bundle`
+#[derive(Debug, Default)]
+pub struct BundleArgs {
+    pub module: Option<String>,
+    pub platform: Option<Platform>,
+    pub bundle_type: Option<BundleType>,
+    pub outfile: Option<PathBuf>,
+    pub minify: bool,
+    pub extra_args: Vec<String>,
+}
+
+/// Fully resolved bundle settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleSettings {
+    pub module: String,
+    pub platform: Platform,
+    pub bundle_type: BundleType,
+    pub outfile: PathBuf,
+    pub minify: bool,
+    pub bundler: String,
+    pub extra_args: Vec<String>,
+}
+
+impl BundleSettings {
+    /// Combine command line arguments with the config, falling back to defaults
+    pub fn resolve(args: BundleArgs, config: Option<&BundleConfig>) -> Self {
+        let config = config.cloned().unwrap_or_default();
+
+        let mut extra_args = config.extra_args;
+        extra_args.extend(args.extra_args);
+
+        Self {
+            module: args
+                .module
+                .or(config.module)
+                .unwrap_or_else(|| DEFAULT_MODULE.to_string()),
+            platform: args.platform.or(config.platform).unwrap_or_default(),
+            bundle_type: args.bundle_type.or(config.bundle_type).unwrap_or_default(),
+            outfile: args
+                .outfile
+                .or(config.outfile.map(PathBuf::from))
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTFILE)),
+            minify: args.minify || config.minify,
+            bundler: config
+                .bundler
+                .unwrap_or_else(|| DEFAULT_BUNDLER.to_string()),
+            extra_args,
+        }
+    }
+
+    /// Arguments for an esbuild compatible bundler
+    fn bundler_args(&self, entry: &Path) -> Vec<String> {
+        let format = match (self.platform, self.bundle_type) {
+            (Platform::Browser, BundleType::App) => "iife",
+            _ => "esm",
+        };
+        let platform = match self.platform {
+            Platform::Node => "node",
+            Platform::Browser => "browser",
+        };
+
+        let mut args = vec![
+            entry.to_string_lossy().to_string(),
+            "--bundle".to_string(),
+            format!("--outfile={}", self.outfile.display()),
+            format!("--platform={}", platform),
+            format!("--format={}", format),
+        ];
+        if self.minify {
+            args.push("--minify".to_string());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+/// Build the project then bundle a module with the configured bundler
+pub async fn execute(args: BundleArgs, skip_compilation: bool, verbose: bool) -> Result<()> {
+    let config =
+        crate::config::load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    let settings = BundleSettings::resolve(args, config.package.bundle.as_ref());
+
+    if !skip_compilation {
+        crate::build::execute(crate::build::BuildArgs {
+            verbose,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    let module_index = config
+        .run_output_dir()
+        .join(&settings.module)
+        .join("index.js");
+    if !module_index.exists() {
+        anyhow::bail!(
+            "Module {} has not been compiled ({} not found)",
+            settings.module,
+            module_index.display()
+        );
+    }
+
+    let entry = write_entry_file(&config, &settings, &module_index)?;
+
+    if let Some(parent) = settings.outfile.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).context("Failed to create bundle output directory")?;
+        }
+    }
+
+    let bundler = resolve_bundler(&settings.bundler, &config.workspace_root);
+    let bundler_args = settings.bundler_args(&entry);
+
+    println!(
+        "{} Bundling {} with {}",
+        "→".cyan(),
+        settings.module.bright_cyan(),
+        settings.bundler
+    );
+    if verbose {
+        println!("  {} {}", bundler.display(), bundler_args.join(" "));
+    }
+
+    let status = Command::new(&bundler)
+        .args(&bundler_args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .with_context(|| format!("Failed to start bundler '{}'", settings.bundler))?;
+
+    if !status.success() {
+        eprintln!("❌ Bundling failed");
+        std::process::exit(1);
+    }
+
+    println!(
+        "{} Bundled {} to {}",
+        "✓".green(),
+        settings.module,
+        settings.outfile.display()
+    );
+
+    Ok(())
+}
+
+/// Write the JavaScript entry point handed to the bundler.
+///
+/// Apps import and run `main`, modules re-export everything.
+fn write_entry_file(
+    config: &SpagoConfig,
+    settings: &BundleSettings,
+    module_index: &Path,
+) -> Result<PathBuf> {
+    let entry_dir = config.spago_dir().join("bundle");
+    fs::create_dir_all(&entry_dir).context("Failed to create bundle entry directory")?;
+
+    let module_path = module_index
+        .canonicalize()
+        .context("Failed to resolve the module's compiled output")?;
+    let entry = entry_dir.join(format!("{}.js", settings.module));
+    fs::write(&entry, entry_source(settings.bundle_type, &module_path))
+        .context("Failed to write bundle entry file")?;
+
+    Ok(entry)
+}
+
+fn entry_source(bundle_type: BundleType, module_path: &Path) -> String {
+    // JSON string escaping is also valid JavaScript
+    let import_path = serde_json::to_string(&module_path.to_string_lossy()).unwrap_or_default();
+    match bundle_type {
+        BundleType::App => format!("import {{ main }} from {};\nmain();\n", import_path),
+        BundleType::Module => format!("export * from {};\n", import_path),
+    }
+}
+
+/// Prefer a bundler installed in node_modules, like npm scripts would
+fn resolve_bundler(bundler: &str, workspace_root: &Path) -> PathBuf {
+    [PathBuf::from("."), workspace_root.to_path_buf()]
+        .iter()
+        .map(|dir| dir.join("node_modules/.bin").join(bundler))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| PathBuf::from(bundler))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_uses_defaults() {
+        let settings = BundleSettings::resolve(BundleArgs::default(), None);
+
+        assert_eq!(settings.module, "Main");
+        assert_eq!(settings.platform, Platform::Browser);
+        assert_eq!(settings.bundle_type, BundleType::App);
+        assert_eq!(settings.outfile, PathBuf::from("index.js"));
+        assert!(!settings.minify);
+        assert_eq!(settings.bundler, "esbuild");
+    }
+
+    #[test]
+    fn test_resolve_cli_overrides_config() {
+        let config = BundleConfig {
+            module: Some("App.Main".to_string()),
+            platform: Some(Platform::Node),
+            outfile: Some("dist/app.js".to_string()),
+            extra_args: vec!["--sourcemap".to_string()],
+            ..Default::default()
+        };
+        let args = BundleArgs {
+            outfile: Some(PathBuf::from("dist/other.js")),
+            minify: true,
+            extra_args: vec!["--target=es2020".to_string()],
+            ..Default::default()
+        };
+
+        let settings = BundleSettings::resolve(args, Some(&config));
+
+        assert_eq!(settings.module, "App.Main");
+        assert_eq!(settings.platform, Platform::Node);
+        assert_eq!(settings.outfile, PathBuf::from("dist/other.js"));
+        assert!(settings.minify);
+        assert_eq!(settings.extra_args, vec!["--sourcemap", "--target=es2020"]);
+    }
+
+    #[test]
+    fn test_bundler_args() {
+        let mut settings = BundleSettings::resolve(BundleArgs::default(), None);
+        settings.minify = true;
+
+        let args = settings.bundler_args(Path::new("/tmp/entry.js"));
+        assert_eq!(
+            args,
+            vec![
+                "/tmp/entry.js",
+                "--bundle",
+                "--outfile=index.js",
+                "--platform=browser",
+                "--format=iife",
+                "--minify"
+            ]
+        );
+
+        settings.platform = Platform::Node;
+        let args = settings.bundler_args(Path::new("/tmp/entry.js"));
+        assert!(args.contains(&"--format=esm".to_string()));
+    }
+
+    #[test]
+    fn test_entry_source() {
+        let path = Path::new("/project/output/Main/index.js");
+        assert_eq!(
+            entry_source(BundleType::App, path),
+            "import { main } from \"/project/output/Main/index.js\";\nmain();\n"
+        );
+        assert_eq!(
+            entry_source(BundleType::Module, path),
+            "export * from \"/project/output/Main/index.js\";\n"
+        );
+    }
+
+    #[test]
+    fn test_bundle_config_from_yaml() {
+        let config: BundleConfig = serde_yaml::from_str(
+            "module: App.Main\nplatform: node\ntype: module\nminify: true\nextraArgs: [\"--sourcemap\"]\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.module.as_deref(), Some("App.Main"));
+        assert_eq!(config.platform, Some(Platform::Node));
+        assert_eq!(config.bundle_type, Some(BundleType::Module));
+        assert!(config.minify);
+        assert_eq!(config.extra_args, vec!["--sourcemap"]);
+    }
+}
@@ -0,0 +1,467 @@
+//! Keeping the compiler output directory in step with the sources.
+//!
+//! purs never deletes modules from its output, so deleted or renamed modules
+//! and modules compiled from an older version of a package linger there.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::bench::BENCH_SOURCES;
+use crate::build::fingerprint;
+use crate::config::{load_config, load_config_cwd, SpagoConfig};
+use crate::install::InstallManager;
+use crate::modules::discover_all_modules;
+use crate::registry::{PackageName, PackageQuery, PackageSet};
+use crate::sources::{find_package_directory, BuildSources, DependencyGlob};
+use crate::test::{test_globs, TEST_SOURCES};
+
+/// Records which package version each compiled module came from
+const MODULE_OWNERS_FILE: &str = ".ragu-modules.json";
+/// The compiler's incremental build cache
+const CACHE_DB_FILE: &str = "cache-db.json";
+/// The version recorded for workspace packages, which aren't versioned
+const LOCAL_VERSION: &str = "local";
+/// A package's own sources
+const MAIN_SOURCES: &str = "./src/**/*.purs";
+
+/// The package a module belongs to, at the version it was compiled from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ModuleOwner {
+    package: PackageName,
+    version: String,
+}
+
+/// Execute the clean command
+pub async fn execute(stale: bool, verbose: bool) -> Result<()> {
+    let config = load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    let output_dir = config.output_dir();
+
+    if stale {
+        let package_set = config.package_set().await?;
+        let removed = prune_stale_output(&config, &package_set, true, verbose)?;
+        if removed.is_empty() {
+            println!(
+                "{} No stale modules in {}",
+                "✓".green(),
+                output_dir.display()
+            );
+        } else {
+            println!(
+                "{} Removed {} stale modules from {}",
+                "✓".green(),
+                removed.len(),
+                output_dir.display()
+            );
+        }
+        return Ok(());
+    }
+
+    if output_dir.exists() {
+        fs::remove_dir_all(&output_dir)
+            .with_context(|| format!("Failed to remove {}", output_dir.display()))?;
+    }
+    println!("{} Removed {}", "✓".green(), output_dir.display());
+
+    Ok(())
+}
+
+/// Remove compiled modules that no longer have a source file, or whose
+/// package has changed version since they were compiled, along with their
+/// `cache-db.json` entries. Returns the removed module names.
+///
+/// Modules that weren't recorded as belonging to a package, such as those
+/// compiled from extra globs, are only removed when `prune_unknown` is set.
+pub fn prune_stale_output(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+    prune_unknown: bool,
+    verbose: bool,
+) -> Result<Vec<String>> {
+    let output_dir = config.output_dir();
+    if !output_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let (current, unavailable) = current_module_owners(config, package_set)?;
+    let previous = load_module_owners(&output_dir);
+    let mut cache_db = load_cache_db(&output_dir)?;
+
+    let mut compiled = compiled_modules(&output_dir)?;
+    if let Some(modules) = cache_db.as_mut().and_then(cache_db_modules) {
+        compiled.extend(modules.keys().cloned());
+    }
+
+    let stale = find_stale(&compiled, &current, &previous, &unavailable, prune_unknown);
+
+    for module in &stale {
+        let module_dir = output_dir.join(module);
+        if module_dir.exists() {
+            fs::remove_dir_all(&module_dir)
+                .with_context(|| format!("Failed to remove {}", module_dir.display()))?;
+        }
+        if verbose {
+            println!("  {} {}", "→".red(), module.dimmed());
+        }
+    }
+
+    if let Some(mut db) = cache_db {
+        if let Some(modules) = cache_db_modules(&mut db) {
+            for module in &stale {
+                modules.remove(module);
+            }
+        }
+        fs::write(output_dir.join(CACHE_DB_FILE), serde_json::to_string(&db)?)
+            .context("Failed to write cache-db.json")?;
+    }
+
+    if !stale.is_empty() {
+        fingerprint::clear(&output_dir)?;
+    }
+
+    // Keep what we knew about modules whose package isn't installed right now
+    let mut owners: BTreeMap<String, ModuleOwner> = previous
+        .into_iter()
+        .filter(|(module, owner)| {
+            !current.contains_key(module)
+                && !stale.contains(module)
+                && unavailable.contains(&owner.package)
+        })
+        .collect();
+    owners.extend(current);
+    fs::write(
+        output_dir.join(MODULE_OWNERS_FILE),
+        serde_json::to_string_pretty(&owners)?,
+    )
+    .context("Failed to write module owners")?;
+
+    Ok(stale)
+}
+
+/// Compiled modules that should go: those whose package no longer has
+/// them, unless it isn't installed to check, and those compiled from another
+/// package or version. Modules no package ever had only go when
+/// `prune_unknown` is set.
+fn find_stale(
+    compiled: &BTreeSet<String>,
+    current: &HashMap<String, ModuleOwner>,
+    previous: &HashMap<String, ModuleOwner>,
+    unavailable: &HashSet<PackageName>,
+    prune_unknown: bool,
+) -> Vec<String> {
+    compiled
+        .iter()
+        .filter(
+            |module| match (current.get(*module), previous.get(*module)) {
+                (Some(owner), Some(previous_owner)) => owner != previous_owner,
+                (Some(_), None) => false,
+                (None, Some(previous_owner)) => !unavailable.contains(&previous_owner.package),
+                (None, None) => prune_unknown,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+/// Every module the workspace can currently compile, with its owner, and
+/// the packages whose sources aren't installed
+fn current_module_owners(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+) -> Result<(HashMap<String, ModuleOwner>, HashSet<PackageName>)> {
+    let query = PackageQuery::new(package_set);
+    let spago_dir = config.spago_dir();
+    let manager = InstallManager::new(&spago_dir)?;
+
+    // The output directory is shared by the whole workspace, so every
+    // package's modules (tests included) need to be accounted for
+    let root_config = if config.is_workspace_root() {
+        config.clone()
+    } else {
+        load_config(config.workspace_root.join("spago.yaml"), false)?
+    };
+
+    let mut direct_dependencies: Vec<PackageName> =
+        config.all_dependencies().into_iter().cloned().collect();
+    direct_dependencies.extend(root_config.all_dependencies().into_iter().cloned());
+    direct_dependencies.extend(config.bench_dependencies().into_iter().cloned());
+    direct_dependencies.extend(root_config.bench_dependencies().into_iter().cloned());
+    for local_package in query.local_packages() {
+        if local_package.name == config.package.name {
+            continue; // Use the current config, which may be newer than the package set
+        }
+        direct_dependencies.push(local_package.name.clone());
+        direct_dependencies.extend(local_package.dependencies.iter().cloned());
+        direct_dependencies.extend(local_package.test_dependencies.iter().cloned());
+    }
+
+    let mut all_dependencies = HashSet::new();
+    let mut processed = HashSet::new();
+    for dependency in &direct_dependencies {
+        manager.collect_dependencies_recursive(
+            dependency,
+            &query,
+            &mut all_dependencies,
+            &mut processed,
+        )?;
+    }
+
+    let local_packages: HashSet<&PackageName> = query
+        .local_packages()
+        .into_iter()
+        .map(|p| &p.name)
+        .collect();
+    let mut dependency_globs = Vec::new();
+    let mut unavailable = HashSet::new();
+    for package_name in all_dependencies {
+        if package_name == config.package.name || package_name == root_config.package.name {
+            continue; // Added below from their own directories
+        }
+        match find_package_directory(&package_name, &spago_dir, package_set)
+            .ok()
+            .flatten()
+        {
+            Some(dir) if local_packages.contains(&package_name) => {
+                let local_config = load_config(dir.join("spago.yaml"), true).ok();
+                dependency_globs.extend(workspace_package_globs(
+                    &package_name,
+                    &dir,
+                    local_config.as_ref(),
+                    true,
+                ));
+            }
+            Some(dir) => {
+                dependency_globs.push(source_glob(&package_name, &dir, MAIN_SOURCES, false));
+            }
+            None => {
+                unavailable.insert(package_name);
+            }
+        }
+    }
+
+    dependency_globs.extend(workspace_package_globs(
+        &root_config.package.name,
+        &config.workspace_root,
+        Some(&root_config),
+        !root_config.skip_root_src(),
+    ));
+    if !config.is_workspace_root() {
+        dependency_globs.extend(workspace_package_globs(
+            &config.package.name,
+            Path::new("."),
+            Some(config),
+            true,
+        ));
+    }
+
+    let sources = BuildSources {
+        dependency_globs,
+        main_sources: None,
+    };
+    let owners = discover_all_modules(&sources)?
+        .into_iter()
+        .map(|module| {
+            let package = PackageName::new(&module.package_name);
+            let version = query
+                .get(&package)
+                .and_then(|p| p.version())
+                .cloned()
+                .unwrap_or_else(|| LOCAL_VERSION.to_string());
+            (module.name, ModuleOwner { package, version })
+        })
+        .collect();
+
+    Ok((owners, unavailable))
+}
+
+/// Everything a workspace package compiles: its sources, the sources of
+/// its tests and every test suite, and its benchmarks
+fn workspace_package_globs(
+    package_name: &PackageName,
+    dir: &Path,
+    config: Option<&SpagoConfig>,
+    include_src: bool,
+) -> Vec<DependencyGlob> {
+    let mut globs = Vec::new();
+    if include_src {
+        globs.push(source_glob(package_name, dir, MAIN_SOURCES, false));
+    }
+    let tests = match config.and_then(|config| config.package.test.as_ref()) {
+        Some(test_config) => test_globs(test_config),
+        None => vec![TEST_SOURCES.to_string()],
+    };
+    for test in &tests {
+        globs.push(source_glob(package_name, dir, test, true));
+    }
+    globs.push(source_glob(package_name, dir, BENCH_SOURCES, false));
+    globs
+}
+
+/// A package's glob, which is relative to its directory
+fn source_glob(
+    package_name: &PackageName,
+    dir: &Path,
+    glob: &str,
+    is_test: bool,
+) -> DependencyGlob {
+    DependencyGlob {
+        package_name: package_name.0.clone(),
+        glob_pattern: dir
+            .join(glob.trim_start_matches("./"))
+            .to_string_lossy()
+            .to_string(),
+        local_path: dir.to_path_buf(),
+        is_test,
+    }
+}
+
+/// Module directories in the compiler output
+fn compiled_modules(output_dir: &Path) -> Result<BTreeSet<String>> {
+    let mut modules = BTreeSet::new();
+    for entry in fs::read_dir(output_dir).context("Failed to read output directory")? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Module names start with a capital; skip anything else that ends up in output
+        if entry.path().is_dir() && name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            modules.insert(name);
+        }
+    }
+    Ok(modules)
+}
+
+fn load_module_owners(output_dir: &Path) -> HashMap<String, ModuleOwner> {
+    fs::read_to_string(output_dir.join(MODULE_OWNERS_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn load_cache_db(output_dir: &Path) -> Result<Option<Value>> {
+    let path = output_dir.join(CACHE_DB_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path).context("Failed to read cache-db.json")?;
+    Ok(serde_json::from_str(&contents).ok())
+}
+
+/// The module entries of a cache-db.json. purs 0.15 nests them under
+/// `modules`, earlier versions keep them at the top level.
+fn cache_db_modules(db: &mut Value) -> Option<&mut serde_json::Map<String, Value>> {
+    let has_modules = db.get("modules").is_some_and(Value::is_object);
+    let db = db.as_object_mut()?;
+    if has_modules {
+        db.get_mut("modules").and_then(Value::as_object_mut)
+    } else {
+        Some(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn owner(package: &str, version: &str) -> ModuleOwner {
+        ModuleOwner {
+            package: PackageName::new(package),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_stale() {
+        let compiled: BTreeSet<String> = ["Main", "Deleted", "Data.Array", "Test.Spec", "Prelude"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let current = HashMap::from([
+            ("Main".to_string(), owner("app", "local")),
+            ("Data.Array".to_string(), owner("arrays", "v7.2.0")),
+            ("Prelude".to_string(), owner("prelude", "v6.0.1")),
+        ]);
+        let previous = HashMap::from([
+            ("Data.Array".to_string(), owner("arrays", "v7.1.0")),
+            ("Prelude".to_string(), owner("prelude", "v6.0.1")),
+            ("Test.Spec".to_string(), owner("spec", "v7.0.0")),
+        ]);
+
+        let stale = find_stale(&compiled, &current, &previous, &HashSet::new(), true);
+        assert_eq!(stale, vec!["Data.Array", "Deleted", "Test.Spec"]);
+
+        // Modules of packages that aren't installed can't be checked
+        let unavailable = HashSet::from([PackageName::new("spec")]);
+        let stale = find_stale(&compiled, &current, &previous, &unavailable, true);
+        assert_eq!(stale, vec!["Data.Array", "Deleted"]);
+
+        // A module no package had may come from sources ragu doesn't track
+        let stale = find_stale(&compiled, &current, &previous, &HashSet::new(), false);
+        assert_eq!(stale, vec!["Data.Array", "Test.Spec"]);
+    }
+
+    #[test]
+    fn test_workspace_package_globs() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("spago.yaml"),
+            "workspace: {}\npackage:\n  name: app\n  dependencies: []\n  test:\n    main: Test.Main\n    suites:\n      - name: e2e\n        main: Test.E2E\n        sources: [\"e2e/**/*.purs\"]\n",
+        )
+        .unwrap();
+        let config = load_config(dir.path().join("spago.yaml"), false).unwrap();
+        let lib = Path::new("lib");
+        let globs = |config: Option<&SpagoConfig>, include_src| {
+            workspace_package_globs(&PackageName::new("app"), lib, config, include_src)
+                .into_iter()
+                .map(|glob| (glob.glob_pattern, glob.is_test))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            globs(Some(&config), true),
+            vec![
+                ("lib/src/**/*.purs".to_string(), false),
+                ("lib/test/**/*.purs".to_string(), true),
+                ("lib/e2e/**/*.purs".to_string(), true),
+                ("lib/bench/**/*.purs".to_string(), false),
+            ]
+        );
+        // Without a config, or skipping src, the default directories remain
+        assert_eq!(
+            globs(None, false),
+            vec![
+                ("lib/test/**/*.purs".to_string(), true),
+                ("lib/bench/**/*.purs".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_db_modules_handles_both_layouts() {
+        let mut nested: Value =
+            serde_json::from_str(r#"{"version":"0.15.15","modules":{"Main":{}}}"#).unwrap();
+        let mut flat: Value = serde_json::from_str(r#"{"Main":{}}"#).unwrap();
+
+        cache_db_modules(&mut nested).unwrap().remove("Main");
+        cache_db_modules(&mut flat).unwrap().remove("Main");
+
+        assert_eq!(nested.to_string(), r#"{"modules":{},"version":"0.15.15"}"#);
+        assert_eq!(flat.to_string(), "{}");
+    }
+
+    #[test]
+    fn test_compiled_modules_skips_non_module_entries() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("Data.Maybe")).unwrap();
+        fs::create_dir_all(temp.path().join(".cache")).unwrap();
+        fs::write(temp.path().join("cache-db.json"), "{}").unwrap();
+        fs::write(temp.path().join("package.json"), "{}").unwrap();
+
+        let modules = compiled_modules(temp.path()).unwrap();
+        assert_eq!(modules, BTreeSet::from(["Data.Maybe".to_string()]));
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::Result;
 use colored::Colorize;
 
-use crate::cli::{CacheAction, Cli, Command};
+use crate::cli::{CacheAction, Cli, Command, CompilerAction, IdeAction};
 use crate::registry::{PackageName, PackageQuery};
 use crate::{
-    cache, config, imports, init, install, package_info, package_sets, print_output, run,
-    src_as_sources, test, workspace,
+    bench, bundle, cache, clean, compiler, config, docs, ide, imports, init, install, package_info,
+    package_sets, print_output, repl, run, src_as_sources, test, workspace,
 };
 
 use super::execution_context::ExecutionContext;
@@ -41,9 +41,9 @@ pub async fn execute(cli: Cli) -> Result<()> {
             let pkg_query = PackageQuery::new(&ctx.package_set);
             package_info::search::execute(&pkg_query, &query, details)
         }
-        Command::Install { packages } => {
+        Command::Install { packages, frozen } => {
             let ctx = ExecutionContext::load().await?;
-            install::command::execute(&packages, &ctx.package_set, cli.verbose).await
+            install::command::execute(&packages, &ctx.package_set, frozen, cli.verbose).await
         }
         Command::Uninstall { packages } => {
             let ctx = ExecutionContext::load().await?;
@@ -59,6 +59,11 @@ pub async fn execute(cli: Cli) -> Result<()> {
             clear,
             exclude_test_deps,
             quick_build,
+            json_errors,
+            force,
+            pedantic_packages,
+            timings,
+            timings_json,
             compiler_args,
         } => {
             if quick_build {
@@ -67,39 +72,170 @@ pub async fn execute(cli: Cli) -> Result<()> {
                     true,
                     compiler_args,
                     cli.include_rts_stats,
+                    json_errors,
                     cli.verbose,
                 )
                 .await
             } else {
-                crate::build::execute(
+                crate::build::execute(crate::build::BuildArgs {
                     watch,
                     clear,
-                    !exclude_test_deps,
+                    test: !exclude_test_deps,
                     compiler_args,
-                    cli.include_rts_stats,
-                    cli.verbose,
-                )
+                    include_rts_stats: cli.include_rts_stats,
+                    json_errors,
+                    force,
+                    pedantic_packages,
+                    timings,
+                    timings_json,
+                    verbose: cli.verbose,
+                })
                 .await
             }
         }
         Command::OutputDir => print_output::execute(),
-        Command::Test { quick_test } => test::execute(quick_test, cli.verbose).await,
+        Command::Clean { stale } => clean::execute(stale, cli.verbose).await,
+        Command::Bundle {
+            module,
+            platform,
+            bundle_type,
+            outfile,
+            minify,
+            quick_bundle,
+            bundler_args,
+        } => {
+            let args = bundle::BundleArgs {
+                module,
+                platform,
+                bundle_type,
+                outfile,
+                minify,
+                extra_args: bundler_args,
+            };
+            bundle::execute(args, quick_bundle, cli.verbose).await
+        }
+        Command::Test {
+            quick_test,
+            suite,
+            function,
+            runtime,
+            node_args,
+            program_args,
+            workspace,
+            jobs,
+            report,
+            watch,
+        } => {
+            let args = test::TestArgs {
+                suite,
+                function,
+                runtime,
+                node_args,
+                program_args,
+                workspace,
+                jobs,
+                reports: report,
+                watch,
+            };
+            test::execute(args, quick_test, cli.verbose).await
+        }
+        Command::Bench {
+            quick_bench,
+            function,
+            runtime,
+            node_args,
+            baseline,
+            save_baseline,
+            max_regression,
+            program_args,
+        } => {
+            let args = bench::BenchArgs {
+                function,
+                runtime,
+                node_args,
+                program_args,
+                baseline,
+                save_baseline,
+                max_regression,
+            };
+            bench::execute(args, quick_bench, cli.verbose).await
+        }
         Command::Run {
             module,
+            function,
             quick_run,
+            runtime,
             node_args,
-        } => run::execute(module, quick_run, cli.verbose, node_args).await,
-        Command::Sources { quick_sources } => {
+            program_args,
+        } => {
+            let args = run::RunArgs {
+                module,
+                function,
+                runtime,
+                node_args,
+                program_args,
+            };
+            run::execute(args, quick_run, cli.verbose).await
+        }
+        Command::Docs {
+            format,
+            deps_only,
+            no_deps,
+            output,
+            open,
+        } => {
+            let scope = if deps_only {
+                docs::DocsScope::DepsOnly
+            } else if no_deps {
+                docs::DocsScope::NoDeps
+            } else {
+                docs::DocsScope::All
+            };
+            let args = docs::DocsArgs {
+                format,
+                scope,
+                output,
+                open,
+            };
+            docs::execute(args, cli.verbose).await
+        }
+        Command::Repl { test, repl_args } => {
+            repl::execute(repl::ReplArgs { test, repl_args }, cli.verbose).await
+        }
+        Command::Sources {
+            quick_sources,
+            json,
+            absolute,
+        } => {
             if quick_sources {
-                src_as_sources::execute(false, false, vec![], false, cli.verbose).await
+                src_as_sources::execute(false, false, vec![], false, false, cli.verbose).await
             } else {
-                crate::sources::execute_sources(cli.verbose).await
+                crate::sources::execute_sources(
+                    crate::sources::SourcesArgs { json, absolute },
+                    cli.verbose,
+                )
+                .await
             }
         }
+        Command::Compiler { action } => match action {
+            CompilerAction::Which => compiler::command::which().await,
+            CompilerAction::List => compiler::command::list(),
+            CompilerAction::Install { tarball } => compiler::command::install(&tarball).await,
+        },
         Command::Cache { action } => match action {
             CacheAction::Info => cache::info().await,
             CacheAction::Clear { all } => cache::clear(all).await,
         },
+        Command::Ide { action } => {
+            let command = match action {
+                IdeAction::Start { port } => ide::IdeCommand::Start { port },
+                IdeAction::Stop => ide::IdeCommand::Stop,
+                IdeAction::Status => ide::IdeCommand::Status,
+                IdeAction::Load => ide::IdeCommand::Load,
+                IdeAction::Rebuild { file } => ide::IdeCommand::Rebuild { file },
+            };
+            ide::execute(command, cli.verbose).await
+        }
         Command::Stats => {
             let ctx = ExecutionContext::load().await?;
             let query = PackageQuery::new(&ctx.package_set);
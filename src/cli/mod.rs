@@ -1,8 +1,14 @@
 pub mod execute_command;
 mod execution_context;
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
+use crate::config::{BundleType, Platform, Runtime};
+use crate::docs::DocsFormat;
+use crate::test::ReportTarget;
+
 /// 🍝 Ragu - A rust port of the popular PureScript package manager
 #[derive(Parser, Debug)]
 #[command(name = "ragu")]
@@ -72,6 +78,11 @@ pub enum Command {
     Install {
         /// Packages to install
         packages: Vec<String>,
+
+        /// Fail instead of updating spago.lock when it's missing or out of date,
+        /// and when installed packages don't match it. For CI.
+        #[arg(long, conflicts_with = "packages")]
+        frozen: bool,
     },
 
     /// Uninstall packages
@@ -101,24 +112,93 @@ pub enum Command {
         #[arg(short = 'q', long)]
         quick_build: bool,
 
+        /// Print compiler diagnostics as JSON (with paths relative to the CWD)
+        /// instead of rendering them
+        #[arg(long)]
+        json_errors: bool,
+
+        /// Always run the compiler, even if no inputs changed since the last build
+        #[arg(long)]
+        force: bool,
+
+        /// Fail if the package imports from packages it doesn't depend on,
+        /// or depends on packages it doesn't import from
+        #[arg(long)]
+        pedantic_packages: bool,
+
+        /// Report the time taken by each build phase and the compiler's
+        /// memory and GC statistics
+        #[arg(long, conflicts_with = "watch")]
+        timings: bool,
+
+        /// Write the timings report to a JSON file
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        timings_json: Option<PathBuf>,
+
         /// Arguments to pass to the node command (everything after --)
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         compiler_args: Vec<String>,
     },
 
-    /// Run the project
+    /// Run the project.
+    /// Defaults are read from the package.run section of spago.yaml.
     Run {
-        /// Module to run
+        /// Module to run (default Main)
         #[arg(short = 'm', long)]
         module: Option<String>,
 
+        /// Exported function to call (default main)
+        #[arg(long, value_name = "FUNCTION")]
+        function: Option<String>,
+
         /// Attempt run without compilation
         #[arg(short = 'q', long)]
         quick_run: bool,
 
-        /// Arguments to pass to the node command (everything after --)
-        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        /// JavaScript runtime: node (default), bun, deno or a command template
+        /// such as "tsx {entry} {args}"
+        #[arg(long, value_name = "RUNTIME")]
+        runtime: Option<Runtime>,
+
+        /// Flag to pass to the runtime itself, can be repeated
+        #[arg(long = "node-arg", value_name = "FLAG", allow_hyphen_values = true)]
         node_args: Vec<String>,
+
+        /// Arguments to pass to the program (everything after --)
+        #[arg(last = true)]
+        program_args: Vec<String>,
+    },
+
+    /// Build and bundle a module into a single JavaScript file.
+    /// Defaults are read from the package.bundle section of spago.yaml.
+    Bundle {
+        /// Module to bundle (default Main)
+        #[arg(short = 'm', long)]
+        module: Option<String>,
+
+        /// Platform the bundle runs on
+        #[arg(long, value_enum)]
+        platform: Option<Platform>,
+
+        /// Bundle an app that runs main, or a module exporting its values
+        #[arg(long = "type", value_enum)]
+        bundle_type: Option<BundleType>,
+
+        /// Output file (default index.js)
+        #[arg(short = 'o', long)]
+        outfile: Option<PathBuf>,
+
+        /// Minify the bundle
+        #[arg(long)]
+        minify: bool,
+
+        /// Attempt bundling without compilation
+        #[arg(short = 'q', long)]
+        quick_bundle: bool,
+
+        /// Arguments to pass to the bundler (everything after --)
+        #[arg(last = true)]
+        bundler_args: Vec<String>,
     },
 
     /// Test the project
@@ -126,6 +206,113 @@ pub enum Command {
         /// Quick test (skip compilation)
         #[arg(short = 'q', long)]
         quick_test: bool,
+
+        /// Run a named suite from package.test.suites
+        #[arg(short = 's', long)]
+        suite: Option<String>,
+
+        /// Exported function of the test module to call (default main)
+        #[arg(long, value_name = "FUNCTION")]
+        function: Option<String>,
+
+        /// JavaScript runtime: node (default), bun, deno or a command template
+        /// such as "tsx {entry} {args}"
+        #[arg(long, value_name = "RUNTIME")]
+        runtime: Option<Runtime>,
+
+        /// Flag to pass to the runtime itself, can be repeated
+        #[arg(long = "node-arg", value_name = "FLAG", allow_hyphen_values = true)]
+        node_args: Vec<String>,
+
+        /// Run the tests of every package in the workspace
+        #[arg(long)]
+        workspace: bool,
+
+        /// How many packages to test at once with --workspace
+        #[arg(short = 'j', long, default_value_t = 1, requires = "workspace")]
+        jobs: usize,
+
+        /// Watch sources, tests and dependencies, re-running the tests on changes
+        #[arg(short = 'w', long, conflicts_with_all = ["workspace", "quick_test", "report"])]
+        watch: bool,
+
+        /// Write a test report, junit=PATH or json=PATH. Can be repeated
+        #[arg(long, value_name = "FORMAT=PATH")]
+        report: Vec<ReportTarget>,
+
+        /// Arguments to pass to the tests (everything after --)
+        #[arg(last = true)]
+        program_args: Vec<String>,
+    },
+
+    /// Compile and run the benchmarks in package.bench
+    Bench {
+        /// Quick bench (skip compilation)
+        #[arg(short = 'q', long)]
+        quick_bench: bool,
+
+        /// Exported function of the bench module to call (default main)
+        #[arg(long, value_name = "FUNCTION")]
+        function: Option<String>,
+
+        /// JavaScript runtime: node (default), bun, deno or a command template
+        /// such as "tsx {entry} {args}"
+        #[arg(long, value_name = "RUNTIME")]
+        runtime: Option<Runtime>,
+
+        /// Flag to pass to the runtime itself, can be repeated
+        #[arg(long = "node-arg", value_name = "FLAG", allow_hyphen_values = true)]
+        node_args: Vec<String>,
+
+        /// Compare the results against a baseline saved with --save-baseline
+        #[arg(long, value_name = "PATH")]
+        baseline: Option<PathBuf>,
+
+        /// Save the results as a baseline JSON file
+        #[arg(long, value_name = "PATH")]
+        save_baseline: Option<PathBuf>,
+
+        /// Fail when a benchmark is more than PERCENT slower than the baseline
+        #[arg(long, value_name = "PERCENT", requires = "baseline")]
+        max_regression: Option<f64>,
+
+        /// Arguments to pass to the benchmarks (everything after --)
+        #[arg(last = true)]
+        program_args: Vec<String>,
+    },
+
+    /// Generate documentation for the project and its dependencies
+    Docs {
+        /// Docs format
+        #[arg(short = 'f', long, value_enum, default_value_t = DocsFormat::Html)]
+        format: DocsFormat,
+
+        /// Only document dependencies from the package set
+        #[arg(long, conflicts_with = "no_deps")]
+        deps_only: bool,
+
+        /// Only document the workspace's own packages
+        #[arg(long)]
+        no_deps: bool,
+
+        /// Where to write the docs (default generated-docs/<format> in the workspace root)
+        #[arg(short = 'o', long, value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// Open the generated HTML docs in a browser
+        #[arg(long)]
+        open: bool,
+    },
+
+    /// Start a purs repl with the project's sources and dependencies
+    Repl {
+        /// Include test sources and dependencies
+        #[arg(short = 't', long)]
+        test: bool,
+
+        /// Arguments to pass to purs repl (everything after --)
+        #[arg(last = true)]
+        repl_args: Vec<String>,
     },
 
     /// Output source file globs for piping to other tools
@@ -133,6 +320,21 @@ pub enum Command {
         /// Only output the exact project sources from /src with the required imports
         #[arg(short = 'q', long)]
         quick_sources: bool,
+
+        /// Output a record per package (name, glob, path, type, version) as JSON,
+        /// including test dependencies
+        #[arg(long, conflicts_with = "quick_sources")]
+        json: bool,
+
+        /// Output absolute globs and paths
+        #[arg(long, conflicts_with = "quick_sources")]
+        absolute: bool,
+    },
+
+    /// Manage purs compilers
+    Compiler {
+        #[command(subcommand)]
+        action: CompilerAction,
     },
 
     /// Manage cache
@@ -141,6 +343,12 @@ pub enum Command {
         action: CacheAction,
     },
 
+    /// Manage the workspace's purs ide server
+    Ide {
+        #[command(subcommand)]
+        action: IdeAction,
+    },
+
     /// Show package set statistics
     Stats,
 
@@ -211,6 +419,55 @@ pub enum Command {
     /// For nested workspace projects, this will be the output dir for the root project.
     #[command(alias = "o")]
     OutputDir,
+
+    /// Remove the output directory
+    Clean {
+        /// Only remove modules whose source was deleted or whose package
+        /// version changed since they were compiled, and modules no package
+        /// provides
+        #[arg(long)]
+        stale: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CompilerAction {
+    /// Show which purs binary is used and why
+    Which,
+
+    /// List compilers installed in the ragu cache
+    List,
+
+    /// Install a compiler from a purs release tarball
+    Install {
+        /// Path to the release tarball (e.g. linux64.tar.gz)
+        tarball: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IdeAction {
+    /// Start the server, restarting it if sources or config changed
+    Start {
+        /// Port to listen on (default a free port)
+        #[arg(short = 'p', long)]
+        port: Option<u16>,
+    },
+
+    /// Stop the server
+    Stop,
+
+    /// Show whether the server is running and up to date
+    Status,
+
+    /// Load all modules into the server
+    Load,
+
+    /// Typecheck a file with the server
+    Rebuild {
+        /// The .purs file to rebuild
+        file: PathBuf,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::compiler::store;
+use crate::compiler::toolchain::{resolve_toolchain, ToolchainSource};
+use crate::compiler::version::{expected_version, installed_version};
+use crate::config::load_config_cwd;
+
+/// Show which purs binary the workspace resolves to and why
+pub async fn which() -> Result<()> {
+    let config = load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    let toolchain = resolve_toolchain(&config)?;
+
+    let version = match installed_version(&toolchain.purs).await {
+        Ok(version) => version.to_string().bright_green().bold(),
+        Err(_) => "unknown version".red(),
+    };
+
+    println!(
+        "{} purs {} at {}",
+        "→".cyan(),
+        version,
+        toolchain.purs.display().to_string().cyan()
+    );
+    println!("  {} {}", "Picked because:".dimmed(), toolchain.source);
+    if matches!(
+        toolchain.source,
+        ToolchainSource::NodeModules | ToolchainSource::SystemPath
+    ) {
+        println!(
+            "  {}",
+            "Pin a compiler with workspace.compiler.path or workspace.compiler.version".dimmed()
+        );
+    }
+
+    if let Ok(Some(expected)) = expected_version(&config).await {
+        println!("  {} {}", "Package set expects:".dimmed(), expected);
+    }
+
+    Ok(())
+}
+
+/// List the compilers installed in ragu's cache
+pub fn list() -> Result<()> {
+    let compilers_dir = store::compilers_dir()?;
+    let compilers = store::installed_compilers(&compilers_dir)?;
+
+    if compilers.is_empty() {
+        println!("No compilers installed in {}", compilers_dir.display());
+        println!(
+            "  {}",
+            "Install one with `ragu compiler install <tarball>`".dimmed()
+        );
+        return Ok(());
+    }
+
+    for (version, purs) in &compilers {
+        println!(
+            "  {} {} {}",
+            "·".dimmed(),
+            version.to_string().bright_green(),
+            purs.display().to_string().dimmed()
+        );
+    }
+    println!("\n{} {} compilers installed", "✓".green(), compilers.len());
+
+    Ok(())
+}
+
+/// Install a compiler from a release tarball
+pub async fn install(tarball: &Path) -> Result<()> {
+    println!("{} Installing purs from {}", "→".cyan(), tarball.display());
+
+    let (version, purs) = store::install_from_tarball(tarball).await?;
+
+    println!(
+        "{} Installed purs {} to {}",
+        "✓".green(),
+        version,
+        purs.display()
+    );
+    println!(
+        "  {}",
+        format!(
+            "Use it with `compiler: {{ version: {} }}` in the workspace section of spago.yaml",
+            version
+        )
+        .dimmed()
+    );
+
+    Ok(())
+}
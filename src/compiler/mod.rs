@@ -0,0 +1,4 @@
+pub mod command;
+pub mod store;
+pub mod toolchain;
+pub mod version;
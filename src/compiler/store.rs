@@ -0,0 +1,107 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use walkdir::WalkDir;
+
+use crate::compiler::toolchain::PURS;
+use crate::compiler::version::{installed_version, PursVersion};
+use crate::registry::get_cache_dir;
+
+/// The directory holding ragu managed compilers, one subdirectory per version
+pub fn compilers_dir() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("compilers"))
+}
+
+/// Where the purs binary for a version lives in the compiler store
+pub fn binary_path(compilers_dir: &Path, version: &str) -> PathBuf {
+    compilers_dir
+        .join(version)
+        .join(format!("{}{}", PURS, std::env::consts::EXE_SUFFIX))
+}
+
+/// All installed compilers, newest first
+pub fn installed_compilers(compilers_dir: &Path) -> Result<Vec<(PursVersion, PathBuf)>> {
+    if !compilers_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut compilers = Vec::new();
+    for entry in fs::read_dir(compilers_dir).context("Failed to read compilers directory")? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Ok(version) = name.parse::<PursVersion>() {
+            let purs = binary_path(compilers_dir, &name);
+            if purs.is_file() {
+                compilers.push((version, purs));
+            }
+        }
+    }
+
+    compilers.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    Ok(compilers)
+}
+
+/// Install purs from a release tarball (e.g. `linux64.tar.gz` from the
+/// purescript GitHub releases) into the compiler store.
+///
+/// The version is taken from the binary itself rather than the file name.
+pub async fn install_from_tarball(tarball: &Path) -> Result<(PursVersion, PathBuf)> {
+    let file =
+        fs::File::open(tarball).with_context(|| format!("Failed to open {}", tarball.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+    let temp_dir = tempfile::tempdir().context("Failed to create temporary directory")?;
+    archive
+        .unpack(temp_dir.path())
+        .with_context(|| format!("Failed to extract {}", tarball.display()))?;
+
+    let binary_name = format!("{}{}", PURS, std::env::consts::EXE_SUFFIX);
+    let extracted = WalkDir::new(temp_dir.path())
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_type().is_file() && e.file_name().to_str() == Some(&binary_name))
+        .map(|e| e.into_path())
+        .with_context(|| format!("No {} binary found in {}", binary_name, tarball.display()))?;
+
+    let version = installed_version(&extracted)
+        .await
+        .context("Failed to determine the version of the extracted compiler")?;
+
+    let destination = binary_path(&compilers_dir()?, &version.to_string());
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).context("Failed to create compiler directory")?;
+    }
+    fs::copy(&extracted, &destination)
+        .with_context(|| format!("Failed to copy compiler to {}", destination.display()))?;
+
+    Ok((version, destination))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_installed_compilers_newest_first() {
+        let temp = TempDir::new().unwrap();
+        for version in ["0.14.9", "0.15.15", "0.15.2"] {
+            let purs = binary_path(temp.path(), version);
+            fs::create_dir_all(purs.parent().unwrap()).unwrap();
+            fs::write(&purs, "").unwrap();
+        }
+        // Directories that aren't versions, or have no binary, are ignored
+        fs::create_dir_all(temp.path().join("tmp")).unwrap();
+        fs::create_dir_all(temp.path().join("0.13.8")).unwrap();
+
+        let versions: Vec<String> = installed_compilers(temp.path())
+            .unwrap()
+            .into_iter()
+            .map(|(version, _)| version.to_string())
+            .collect();
+
+        assert_eq!(versions, vec!["0.15.15", "0.15.2", "0.14.9"]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::compiler::store;
+use crate::config::{CompilerConfig, SpagoConfig};
+
+/// The compiler binary name
+pub const PURS: &str = "purs";
+
+/// Where a purs binary was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolchainSource {
+    /// `workspace.compiler.path` in spago.yaml
+    ConfiguredPath,
+    /// `workspace.compiler.version` in spago.yaml, installed in ragu's cache
+    PinnedVersion(String),
+    /// A project local npm install
+    NodeModules,
+    /// The first purs on PATH
+    SystemPath,
+}
+
+impl fmt::Display for ToolchainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolchainSource::ConfiguredPath => write!(f, "workspace.compiler.path in spago.yaml"),
+            ToolchainSource::PinnedVersion(version) => write!(
+                f,
+                "workspace.compiler.version {} pinned in spago.yaml",
+                version
+            ),
+            ToolchainSource::NodeModules => write!(f, "found in node_modules/.bin"),
+            ToolchainSource::SystemPath => write!(f, "found on PATH"),
+        }
+    }
+}
+
+/// The purs binary to run for a workspace, and why it was picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    pub purs: PathBuf,
+    pub source: ToolchainSource,
+}
+
+/// Work out which purs binary to use.
+///
+/// In order of preference:
+/// 1. An explicit `workspace.compiler.path`
+/// 2. A `workspace.compiler.version` installed in ragu's compiler cache
+/// 3. `node_modules/.bin/purs` in the current package or the workspace root
+/// 4. `purs` from PATH
+pub fn resolve_toolchain(config: &SpagoConfig) -> Result<Toolchain> {
+    resolve_toolchain_with(config, &store::compilers_dir()?, which::which(PURS).ok())
+}
+
+fn resolve_toolchain_with(
+    config: &SpagoConfig,
+    compilers_dir: &Path,
+    system_purs: Option<PathBuf>,
+) -> Result<Toolchain> {
+    let compiler_config: Option<&CompilerConfig> = config.workspace.compiler.as_ref();
+
+    if let Some(path) = compiler_config.and_then(|c| c.path.as_ref()) {
+        let purs = config.workspace_root.join(path);
+        if !purs.is_file() {
+            anyhow::bail!(
+                "workspace.compiler.path points to {}, which does not exist",
+                purs.display()
+            );
+        }
+        return Ok(Toolchain {
+            purs,
+            source: ToolchainSource::ConfiguredPath,
+        });
+    }
+
+    if let Some(version) = compiler_config.and_then(|c| c.version.as_ref()) {
+        let purs = store::binary_path(compilers_dir, version);
+        if !purs.is_file() {
+            anyhow::bail!(
+                "purs {} is pinned in workspace.compiler.version but not installed. \
+                 Install it with `ragu compiler install <tarball>`",
+                version
+            );
+        }
+        return Ok(Toolchain {
+            purs,
+            source: ToolchainSource::PinnedVersion(version.clone()),
+        });
+    }
+
+    let node_modules_candidates = [
+        PathBuf::from("node_modules/.bin").join(PURS),
+        config.workspace_root.join("node_modules/.bin").join(PURS),
+    ];
+    if let Some(purs) = node_modules_candidates.into_iter().find(|p| p.is_file()) {
+        return Ok(Toolchain {
+            purs,
+            source: ToolchainSource::NodeModules,
+        });
+    }
+
+    match system_purs {
+        Some(purs) => Ok(Toolchain {
+            purs,
+            source: ToolchainSource::SystemPath,
+        }),
+        None => anyhow::bail!(
+            "Could not find purs. Install it on your PATH or in node_modules, \
+             or configure workspace.compiler in spago.yaml"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PackageConfig, WorkspaceConfig};
+    use crate::registry::PackageName;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn config_in(root: &Path, compiler: Option<CompilerConfig>) -> SpagoConfig {
+        SpagoConfig {
+            package: PackageConfig {
+                name: PackageName::new("test-package"),
+                dependencies: vec![],
+                test: None,
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
+            },
+            workspace: WorkspaceConfig {
+                compiler,
+                ..Default::default()
+            },
+            workspace_root: root.to_path_buf(),
+        }
+    }
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn test_configured_path_wins() {
+        let temp = TempDir::new().unwrap();
+        touch(&temp.path().join("bin/purs"));
+        touch(&temp.path().join("node_modules/.bin/purs"));
+        let config = config_in(
+            temp.path(),
+            Some(CompilerConfig {
+                path: Some("bin/purs".to_string()),
+                ..Default::default()
+            }),
+        );
+
+        let toolchain = resolve_toolchain_with(&config, temp.path(), None).unwrap();
+
+        assert_eq!(toolchain.purs, temp.path().join("bin/purs"));
+        assert_eq!(toolchain.source, ToolchainSource::ConfiguredPath);
+    }
+
+    #[test]
+    fn test_pinned_version_uses_compiler_cache() {
+        let temp = TempDir::new().unwrap();
+        let compilers_dir = temp.path().join("compilers");
+        touch(&store::binary_path(&compilers_dir, "0.15.15"));
+        let config = config_in(
+            temp.path(),
+            Some(CompilerConfig {
+                version: Some("0.15.15".to_string()),
+                ..Default::default()
+            }),
+        );
+
+        let toolchain = resolve_toolchain_with(&config, &compilers_dir, None).unwrap();
+        assert_eq!(
+            toolchain.purs,
+            store::binary_path(&compilers_dir, "0.15.15")
+        );
+        assert_eq!(
+            toolchain.source,
+            ToolchainSource::PinnedVersion("0.15.15".to_string())
+        );
+
+        let config = config_in(
+            temp.path(),
+            Some(CompilerConfig {
+                version: Some("0.14.0".to_string()),
+                ..Default::default()
+            }),
+        );
+        assert!(resolve_toolchain_with(&config, &compilers_dir, None).is_err());
+    }
+
+    #[test]
+    fn test_node_modules_before_system_path() {
+        let temp = TempDir::new().unwrap();
+        touch(&temp.path().join("node_modules/.bin/purs"));
+        let config = config_in(temp.path(), None);
+
+        let toolchain =
+            resolve_toolchain_with(&config, temp.path(), Some(PathBuf::from("/usr/bin/purs")))
+                .unwrap();
+
+        assert_eq!(toolchain.purs, temp.path().join("node_modules/.bin/purs"));
+        assert_eq!(toolchain.source, ToolchainSource::NodeModules);
+    }
+
+    #[test]
+    fn test_falls_back_to_system_path() {
+        let temp = TempDir::new().unwrap();
+        let config = config_in(temp.path(), None);
+
+        let toolchain =
+            resolve_toolchain_with(&config, temp.path(), Some(PathBuf::from("/usr/bin/purs")))
+                .unwrap();
+        assert_eq!(toolchain.source, ToolchainSource::SystemPath);
+
+        assert!(resolve_toolchain_with(&config, temp.path(), None).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use tokio::process::Command;
+
+use crate::compiler::toolchain::resolve_toolchain;
+use crate::config::{extract_tag_from_url, SpagoConfig, VersionCheck};
+use crate::registry::get_registry_compiler_version;
+
+/// A purs compiler version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PursVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl PursVersion {
+    /// Whether code written for `expected` can be compiled with this version.
+    ///
+    /// purs is still on 0.x, where minor releases are breaking, so the
+    /// major and minor versions must match exactly. Newer patch releases are fine.
+    pub fn is_compatible_with(&self, expected: &PursVersion) -> bool {
+        self.major == expected.major && self.minor == expected.minor && self.patch >= expected.patch
+    }
+}
+
+impl FromStr for PursVersion {
+    type Err = anyhow::Error;
+
+    /// Parse versions like `0.15.15`, `v0.15.15` or `purs --version` output
+    /// such as `0.15.15 [development build; commit: ...]`
+    fn from_str(s: &str) -> Result<Self> {
+        let version = s
+            .split_whitespace()
+            .next()
+            .context("Empty compiler version")?;
+        let version = version.strip_prefix('v').unwrap_or(version);
+
+        let parts: Vec<u32> = version
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<std::result::Result<_, _>>()
+            .with_context(|| format!("Invalid compiler version '{}'", s))?;
+
+        match parts.as_slice() {
+            [major, minor, patch] => Ok(PursVersion {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            }),
+            _ => anyhow::bail!("Invalid compiler version '{}'", s),
+        }
+    }
+}
+
+impl fmt::Display for PursVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The compiler version encoded in a package set tag such as `psc-0.15.15-20251004`
+pub fn version_from_tag(tag: &str) -> Option<PursVersion> {
+    let rest = tag.strip_prefix("psc-")?;
+    let version = rest.split('-').next()?;
+    version.parse().ok()
+}
+
+/// Ask a purs binary for its version
+pub async fn installed_version(purs: &Path) -> Result<PursVersion> {
+    let output = Command::new(purs)
+        .arg("--version")
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {} --version", purs.display()))?;
+
+    if !output.status.success() {
+        anyhow::bail!("{} --version exited with {}", purs.display(), output.status);
+    }
+
+    String::from_utf8_lossy(&output.stdout).trim().parse()
+}
+
+/// The compiler version the configured package set was published for
+pub async fn expected_version(config: &SpagoConfig) -> Result<Option<PursVersion>> {
+    let package_set = match &config.workspace.package_set {
+        Some(package_set) => package_set,
+        None => return Ok(None),
+    };
+
+    if let Some(url) = &package_set.url {
+        return Ok(extract_tag_from_url(url).and_then(|tag| version_from_tag(&tag)));
+    }
+
+    if let Some(registry) = &package_set.registry {
+        let compiler = get_registry_compiler_version(registry).await?;
+        return Ok(Some(compiler.parse()?));
+    }
+
+    Ok(None)
+}
+
+/// The installed purs doesn't match the package set's compiler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub installed: PursVersion,
+    pub expected: PursVersion,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "purs {} is installed but the package set expects purs {}",
+            self.installed, self.expected
+        )
+    }
+}
+
+/// Compare the installed purs against the package set's compiler version.
+///
+/// Problems determining either version are not treated as a mismatch,
+/// a missing purs is reported by the compile step itself.
+pub async fn find_version_mismatch(config: &SpagoConfig, verbose: bool) -> Option<VersionMismatch> {
+    let expected = match expected_version(config).await {
+        Ok(Some(expected)) => expected,
+        Ok(None) => return None,
+        Err(e) => {
+            if verbose {
+                eprintln!(
+                    "{} Could not determine the package set's compiler version: {:#}",
+                    "⚠".yellow(),
+                    e
+                );
+            }
+            return None;
+        }
+    };
+
+    // An unresolvable toolchain is reported when the compiler is run
+    let toolchain = resolve_toolchain(config).ok()?;
+    let installed = match installed_version(&toolchain.purs).await {
+        Ok(installed) => installed,
+        Err(e) => {
+            if verbose {
+                eprintln!("{} {:#}", "⚠".yellow(), e);
+            }
+            return None;
+        }
+    };
+
+    if verbose {
+        println!(
+            "{} purs {} (package set expects {})",
+            "→".cyan(),
+            installed,
+            expected
+        );
+    }
+
+    (!installed.is_compatible_with(&expected)).then_some(VersionMismatch {
+        installed,
+        expected,
+    })
+}
+
+/// Warn or fail on a compiler version mismatch, depending on
+/// `workspace.compiler.versionCheck`
+pub async fn check_compiler_version(config: &SpagoConfig, verbose: bool) -> Result<()> {
+    let mode = config.compiler_version_check();
+    if mode == VersionCheck::Ignore {
+        return Ok(());
+    }
+
+    let mismatch = match find_version_mismatch(config, verbose).await {
+        Some(mismatch) => mismatch,
+        None => return Ok(()),
+    };
+
+    match mode {
+        VersionCheck::Error => anyhow::bail!(
+            "{}. Install a matching compiler, or set workspace.compiler.versionCheck to 'warn'",
+            mismatch
+        ),
+        _ => {
+            eprintln!("{} {}", "⚠".yellow().bold(), mismatch.to_string().yellow());
+            eprintln!(
+                "  {}",
+                "Set workspace.compiler.versionCheck to 'ignore' to silence this".dimmed()
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u32, minor: u32, patch: u32) -> PursVersion {
+        PursVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            "0.15.15".parse::<PursVersion>().unwrap(),
+            version(0, 15, 15)
+        );
+        assert_eq!("v0.14.9".parse::<PursVersion>().unwrap(), version(0, 14, 9));
+        assert_eq!(
+            "0.15.10 [development build; commit: abc123]"
+                .parse::<PursVersion>()
+                .unwrap(),
+            version(0, 15, 10)
+        );
+        assert!("0.15".parse::<PursVersion>().is_err());
+        assert!("latest".parse::<PursVersion>().is_err());
+    }
+
+    #[test]
+    fn test_version_from_tag() {
+        assert_eq!(
+            version_from_tag("psc-0.15.15-20251004"),
+            Some(version(0, 15, 15))
+        );
+        assert_eq!(version_from_tag("psc-0.14.0"), Some(version(0, 14, 0)));
+        assert_eq!(version_from_tag("62.1.0"), None);
+    }
+
+    #[test]
+    fn test_is_compatible_with() {
+        let expected = version(0, 15, 10);
+
+        assert!(version(0, 15, 10).is_compatible_with(&expected));
+        assert!(version(0, 15, 15).is_compatible_with(&expected));
+        assert!(!version(0, 15, 9).is_compatible_with(&expected));
+        assert!(!version(0, 14, 10).is_compatible_with(&expected));
+        assert!(!version(0, 16, 0).is_compatible_with(&expected));
+    }
+}
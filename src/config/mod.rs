@@ -3,7 +3,9 @@ mod update;
 mod validation;
 
 pub use types::{
-    ExtraPackageConfig, PackageConfig, PsaOptionsConfig, SpagoConfig, WorkspaceConfig,
+    BackendConfig, BenchConfig, BundleConfig, BundleType, CompilerConfig, ExtraPackageConfig,
+    PackageConfig, PackageSetConfig, Platform, PsaOptionsConfig, RtsConfig, RunConfig, Runtime,
+    SpagoConfig, TestConfig, VersionCheck, WorkspaceConfig,
 };
 pub use update::{add_packages_to_config, remove_packages_from_config};
 pub use validation::{validate_config, validate_transitive_deps};
@@ -16,6 +18,9 @@ use std::{fs, path::PathBuf};
 use crate::config::types::{JustPackageConfig, JustWorkspaceConfig};
 use crate::registry::PackageName;
 
+/// The package name given to a workspace root spago.yaml without a package
+pub const WORKSPACE_ROOT_PACKAGE: &str = "workspace_root";
+
 /// Load and parse a spago.yaml file
 pub fn load_config(path: impl AsRef<Path>, ignore_when_workspace: bool) -> Result<SpagoConfig> {
     let path = path.as_ref();
@@ -25,9 +30,13 @@ pub fn load_config(path: impl AsRef<Path>, ignore_when_workspace: bool) -> Resul
     let package_config: PackageConfig = serde_yaml::from_str::<JustPackageConfig>(&contents)
         .unwrap_or(JustPackageConfig {
             package: PackageConfig {
-                name: PackageName::new("workspace_root"),
+                name: PackageName::new(WORKSPACE_ROOT_PACKAGE),
                 dependencies: vec![],
                 test: None,
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
             },
         })
         .package;
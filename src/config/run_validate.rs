@@ -1,7 +1,9 @@
 use anyhow::Result;
 use colored::Colorize;
 
-use crate::config::{load_config_cwd, validate_config, validate_transitive_deps};
+use crate::compiler::version::find_version_mismatch;
+use crate::config::validation::ValidationError;
+use crate::config::{load_config_cwd, validate_config, validate_transitive_deps, VersionCheck};
 use crate::registry::PackageQuery;
 
 pub async fn execute(verbose: bool) -> Result<()> {
@@ -28,7 +30,18 @@ pub async fn execute(verbose: bool) -> Result<()> {
         println!("Validating dependencies...");
     }
 
-    let result = validate_config(&config, &query);
+    let mut result = validate_config(&config, &query);
+
+    let version_check = config.compiler_version_check();
+    if version_check != VersionCheck::Ignore {
+        if let Some(mismatch) = find_version_mismatch(&config, verbose).await {
+            if version_check == VersionCheck::Error {
+                result.add_error(ValidationError::CompilerVersion(mismatch));
+            } else {
+                result.add_warning(mismatch.to_string());
+            }
+        }
+    }
 
     // Always validate transitive dependencies
     let trans_result = validate_transitive_deps(&config, &query);
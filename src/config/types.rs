@@ -1,9 +1,16 @@
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::registry::{PackageName, PackageSet};
 
+/// Where backends write their output unless configured otherwise
+const DEFAULT_BACKEND_OUTPUT: &str = "output-es";
+
 /// Complete spago.yaml configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpagoConfig {
@@ -31,15 +38,203 @@ pub struct PackageConfig {
     pub dependencies: Vec<PackageName>,
     #[serde(default)]
     pub test: Option<TestConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<BundleConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<PackageBuildConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bench: Option<BenchConfig>,
 }
 
 /// Test configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestConfig {
-    /// The entry point for tests (purescript module name)
+    /// The entry point for tests (purescript module name), optional when
+    /// suites are configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<PackageName>,
+    /// The runtime tests run with, defaults to `package.run.runtime`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<Runtime>,
+    /// Named suites with their own entry points, run with `ragu test --suite`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suites: Vec<TestSuiteConfig>,
+}
+
+impl TestConfig {
+    /// Test dependencies including those of every suite, or only of the
+    /// selected one after [`SpagoConfig::for_test_suite`]
+    pub fn all_dependencies(&self) -> Vec<&PackageName> {
+        let mut deps: Vec<&PackageName> = self
+            .dependencies
+            .iter()
+            .chain(self.suites.iter().flat_map(|suite| &suite.dependencies))
+            .collect();
+        deps.sort_unstable();
+        deps.dedup();
+        deps
+    }
+}
+
+/// A named test suite, e.g. `unit` or `integration`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSuiteConfig {
+    pub name: String,
+    /// The suite's entry point (purescript module name)
+    pub main: String,
+    /// Source globs compiled for the suite (default ./test/**/*.purs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// Dependencies on top of `package.test.dependencies`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<PackageName>,
+}
+
+/// Benchmark configuration for `ragu bench`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchConfig {
+    /// The entry point for benchmarks (purescript module name)
     pub main: String,
+    /// Dependencies only the benchmarks need
     #[serde(default)]
     pub dependencies: Vec<PackageName>,
+    /// The runtime benchmarks run with, defaults to `package.run.runtime`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<Runtime>,
+}
+
+/// The JavaScript runtime that runs compiled programs.
+///
+/// Anything other than `node`, `bun` or `deno` is a command template split on
+/// whitespace, e.g. `tsx --no-cache {entry} {args}`. Runtime flags are added
+/// after the executable, and without `{entry}` the entry script and
+/// arguments are appended.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Runtime {
+    #[default]
+    Node,
+    Bun,
+    Deno,
+    Command(String),
+}
+
+impl FromStr for Runtime {
+    type Err = Infallible;
+
+    fn from_str(runtime: &str) -> Result<Self, Self::Err> {
+        Ok(match runtime.trim() {
+            "node" => Runtime::Node,
+            "bun" => Runtime::Bun,
+            "deno" => Runtime::Deno,
+            template => Runtime::Command(template.to_string()),
+        })
+    }
+}
+
+impl From<String> for Runtime {
+    fn from(runtime: String) -> Self {
+        match runtime.parse() {
+            Ok(runtime) => runtime,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl From<Runtime> for String {
+    fn from(runtime: Runtime) -> Self {
+        runtime.to_string()
+    }
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Runtime::Node => write!(f, "node"),
+            Runtime::Bun => write!(f, "bun"),
+            Runtime::Deno => write!(f, "deno"),
+            Runtime::Command(template) => write!(f, "{}", template),
+        }
+    }
+}
+
+/// Defaults for `ragu run`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunConfig {
+    /// The module to run (default Main)
+    #[serde(default)]
+    pub main: Option<String>,
+    /// The exported function to call (default main). spago always calls
+    /// main.
+    #[serde(default)]
+    pub function: Option<String>,
+    /// Arguments passed to the program
+    #[serde(default)]
+    pub exec_args: Vec<String>,
+    /// Flags passed to the runtime itself
+    #[serde(default)]
+    pub node_args: Vec<String>,
+    /// node (default), bun, deno or a command template
+    #[serde(default)]
+    pub runtime: Option<Runtime>,
+}
+
+/// Per package build settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageBuildConfig {
+    /// Fail the build when imports don't match the declared dependencies
+    #[serde(default)]
+    pub pedantic_packages: bool,
+}
+
+/// The environment the bundle will run in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Node,
+    #[default]
+    Browser,
+}
+
+/// What the bundle is for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleType {
+    /// An executable bundle that calls the module's `main`
+    #[default]
+    App,
+    /// A library bundle exporting the module's values
+    Module,
+}
+
+/// Defaults for `ragu bundle`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleConfig {
+    /// The module to bundle (default Main)
+    #[serde(default)]
+    pub module: Option<String>,
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    #[serde(default, rename = "type")]
+    pub bundle_type: Option<BundleType>,
+    /// The bundle file, relative to the package (default index.js)
+    #[serde(default)]
+    pub outfile: Option<String>,
+    #[serde(default)]
+    pub minify: bool,
+    /// An esbuild compatible bundler executable (default esbuild)
+    #[serde(default)]
+    pub bundler: Option<String>,
+    /// Extra arguments passed to the bundler
+    #[serde(default)]
+    pub extra_args: Vec<String>,
 }
 
 /// Workspace configuration
@@ -55,9 +250,24 @@ pub struct WorkspaceConfig {
     #[serde(default)]
     pub build_opts: Option<BuildOptsConfig>,
     #[serde(default)]
+    pub compiler: Option<CompilerConfig>,
+    #[serde(default)]
+    pub backend: Option<BackendConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<DocsConfig>,
+    #[serde(default)]
     pub extra_packages: HashMap<PackageName, ExtraPackageConfig>,
 }
 
+/// Settings for `ragu docs`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocsConfig {
+    /// Directory docs are written to, relative to the workspace root
+    /// (default generated-docs)
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BuildOptsConfig {
@@ -66,10 +276,87 @@ pub struct BuildOptsConfig {
     /// When true, excludes ./src/**/*.purs from sources when building from the workspace root.
     #[serde(default)]
     pub skip_root_src: bool,
+    /// Targets for purs `--codegen`, e.g. `[js, corefn, docs]` (purs defaults to js)
+    #[serde(default)]
+    pub codegen: Option<Vec<String>>,
+    /// Also emit source maps for the generated JavaScript
+    #[serde(default)]
+    pub source_maps: bool,
+    /// Compiler runtime settings. Memory settings left unset are tuned from
+    /// the machine's memory, threads are left to purs.
+    #[serde(default)]
+    pub rts: Option<RtsConfig>,
+}
+
+/// GHC runtime settings for purs, sizes use RTS syntax such as `128m` or `4g`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RtsConfig {
+    /// Allocation area size (`-A`)
+    #[serde(default)]
+    pub allocation_area: Option<String>,
+    /// Nursery chunk size (`-n`)
+    #[serde(default)]
+    pub nursery: Option<String>,
+    /// Capabilities to run on (`-N`), 0 lets the runtime use every core.
+    /// Unset, purs picks its own.
+    #[serde(default)]
+    pub threads: Option<u32>,
+    /// Maximum heap size (`-M`)
+    #[serde(default)]
+    pub max_heap: Option<String>,
+}
+
+/// An alternative code generator fed with the compiler's corefn output.
+///
+/// `cmd` runs from the workspace root after every successful compile, with
+/// `args` and no other arguments. It's told what to generate through the
+/// environment:
+///
+/// - `RAGU_OUTPUT_DIR`: the compiler output directory holding the corefn,
+///   relative to the workspace root
+/// - `RAGU_CHANGED_MODULES`: the comma separated names of the modules purs
+///   recompiled, empty when nothing was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendConfig {
+    /// The backend executable, e.g. purs-backend-es
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Where the backend writes its JavaScript, relative to the workspace root.
+    /// Defaults to output-es.
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// PureScript compiler settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompilerConfig {
+    /// Path to a purs binary, relative to the workspace root
+    #[serde(default)]
+    pub path: Option<String>,
+    /// A purs version installed with `ragu compiler install`
+    #[serde(default)]
+    pub version: Option<String>,
+    /// What to do when the installed purs doesn't match the package set
+    #[serde(default)]
+    pub version_check: VersionCheck,
+}
+
+/// How to react to a compiler version that doesn't match the package set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionCheck {
+    #[default]
+    Warn,
+    Error,
+    Ignore,
 }
 
 /// Purescript PSA options
-/// If left out, will just use the standard purs compiler without PSA
+/// Applied natively to the compiler's JSON diagnostics, the psa binary is not required
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PsaOptionsConfig {
@@ -117,7 +404,7 @@ pub struct ExtraPackageConfig {
 }
 
 /// Package set configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PackageSetConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
@@ -143,12 +430,31 @@ impl SpagoConfig {
         self.package.dependencies.iter().collect()
     }
 
-    /// Get only test dependencies
+    /// Get only test dependencies, including those of every test suite
     pub fn test_dependencies(&self) -> Vec<&PackageName> {
         self.package
             .test
             .as_ref()
-            .map(|t| t.dependencies.iter().collect())
+            .map(|t| t.all_dependencies())
+            .unwrap_or_default()
+    }
+
+    /// This config with only the named test suite, or none for
+    /// `package.test.main`, so only that suite's dependencies are compiled
+    pub fn for_test_suite(&self, suite: Option<&str>) -> SpagoConfig {
+        let mut config = self.clone();
+        if let Some(test) = &mut config.package.test {
+            test.suites.retain(|s| Some(s.name.as_str()) == suite);
+        }
+        config
+    }
+
+    /// Get only benchmark dependencies
+    pub fn bench_dependencies(&self) -> Vec<&PackageName> {
+        self.package
+            .bench
+            .as_ref()
+            .map(|b| b.dependencies.iter().collect())
             .unwrap_or_default()
     }
 
@@ -184,6 +490,23 @@ impl SpagoConfig {
         ))
     }
 
+    /// How a compiler version mismatch should be reported
+    pub fn compiler_version_check(&self) -> VersionCheck {
+        self.workspace
+            .compiler
+            .as_ref()
+            .map(|compiler| compiler.version_check)
+            .unwrap_or_default()
+    }
+
+    /// Whether `package.build.pedanticPackages` is enabled
+    pub fn pedantic_packages(&self) -> bool {
+        self.package
+            .build
+            .as_ref()
+            .is_some_and(|build| build.pedantic_packages)
+    }
+
     pub fn spago_dir(&self) -> PathBuf {
         self.workspace_root.join(".spago")
     }
@@ -198,10 +521,49 @@ impl SpagoConfig {
         }
     }
 
+    /// The directory to load runnable JavaScript from: the backend's
+    /// output when a backend is configured, otherwise the compiler output
+    pub fn run_output_dir(&self) -> PathBuf {
+        match &self.workspace.backend {
+            Some(backend) => self.workspace_root.join(
+                backend
+                    .output
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_BACKEND_OUTPUT.to_string()),
+            ),
+            None => self.output_dir(),
+        }
+    }
+
     pub fn is_workspace_root(&self) -> bool {
         self.workspace_root == PathBuf::from(".")
     }
 
+    /// Configured `--codegen` targets
+    pub fn codegen(&self) -> Option<Vec<String>> {
+        self.workspace
+            .build_opts
+            .as_ref()
+            .and_then(|opts| opts.codegen.clone())
+    }
+
+    /// Whether `buildOpts.sourceMaps` is enabled
+    pub fn source_maps(&self) -> bool {
+        self.workspace
+            .build_opts
+            .as_ref()
+            .is_some_and(|opts| opts.source_maps)
+    }
+
+    /// Configured compiler runtime settings
+    pub fn rts_config(&self) -> RtsConfig {
+        self.workspace
+            .build_opts
+            .as_ref()
+            .and_then(|opts| opts.rts.clone())
+            .unwrap_or_default()
+    }
+
     pub fn skip_root_src(&self) -> bool {
         self.workspace
             .build_opts
@@ -221,15 +583,60 @@ mod tests {
                 name: PackageName::new("test-package"),
                 dependencies: vec![PackageName::new("prelude"), PackageName::new("effect")],
                 test: Some(TestConfig {
-                    main: "Test.Main".to_string(),
+                    main: Some("Test.Main".to_string()),
                     dependencies: vec![PackageName::new("console"), PackageName::new("effect")],
+                    runtime: None,
+                    suites: vec![],
                 }),
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
             },
             workspace: WorkspaceConfig::default(),
             workspace_root: PathBuf::from("."),
         }
     }
 
+    #[test]
+    fn test_compiler_version_check_config() {
+        let config = create_test_config();
+        assert_eq!(config.compiler_version_check(), VersionCheck::Warn);
+
+        let workspace: WorkspaceConfig =
+            serde_yaml::from_str("compiler:\n  versionCheck: error\n").unwrap();
+        assert_eq!(
+            workspace.compiler.map(|c| c.version_check),
+            Some(VersionCheck::Error)
+        );
+    }
+
+    #[test]
+    fn test_pedantic_packages_config() {
+        let mut config = create_test_config();
+        assert!(!config.pedantic_packages());
+
+        config.package =
+            serde_yaml::from_str("name: test-package\nbuild:\n  pedanticPackages: true\n").unwrap();
+        assert!(config.pedantic_packages());
+    }
+
+    #[test]
+    fn test_run_output_dir_uses_backend_output() {
+        let mut config = create_test_config();
+        assert_eq!(config.run_output_dir(), PathBuf::from("./output"));
+
+        config.workspace.backend = Some(BackendConfig {
+            cmd: "purs-backend-es".to_string(),
+            args: vec!["build".to_string()],
+            output: None,
+        });
+        assert_eq!(config.run_output_dir(), PathBuf::from("./output-es"));
+
+        config.workspace.backend.as_mut().unwrap().output = Some("dist".to_string());
+        assert_eq!(config.run_output_dir(), PathBuf::from("./dist"));
+    }
+
     #[test]
     fn test_all_dependencies() {
         let config = create_test_config();
@@ -261,4 +668,50 @@ mod tests {
         assert!(deps.contains(&&PackageName::new("console")));
         assert!(deps.contains(&&PackageName::new("effect")));
     }
+
+    #[test]
+    fn test_test_dependencies_include_suites() {
+        let mut config = create_test_config();
+        config.package.test = Some(
+            serde_yaml::from_str(
+                "dependencies: [spec]\nsuites:\n  - name: integration\n    main: Test.Integration\n    sources: [\"test/integration/**/*.purs\"]\n    dependencies: [node-fs, spec]\n",
+            )
+            .unwrap(),
+        );
+
+        let deps = config.test_dependencies();
+        assert_eq!(
+            deps,
+            vec![&PackageName::new("node-fs"), &PackageName::new("spec")]
+        );
+        assert_eq!(
+            config.for_test_suite(None).test_dependencies(),
+            vec![&PackageName::new("spec")]
+        );
+        assert_eq!(
+            config
+                .for_test_suite(Some("integration"))
+                .test_dependencies(),
+            deps
+        );
+        let test = config.package.test.unwrap();
+        assert_eq!(test.main, None);
+        assert_eq!(test.suites[0].sources, vec!["test/integration/**/*.purs"]);
+    }
+
+    #[test]
+    fn test_runtime_from_yaml() {
+        let runtimes: Vec<Runtime> =
+            serde_yaml::from_str("[node, bun, deno, \"tsx {entry}\"]").unwrap();
+        assert_eq!(
+            runtimes,
+            vec![
+                Runtime::Node,
+                Runtime::Bun,
+                Runtime::Deno,
+                Runtime::Command("tsx {entry}".to_string())
+            ]
+        );
+        assert_eq!(serde_yaml::to_string(&Runtime::Bun).unwrap(), "bun\n");
+    }
 }
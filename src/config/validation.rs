@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+use crate::compiler::version::VersionMismatch;
 use crate::config::SpagoConfig;
 use crate::registry::{PackageName, PackageQuery};
 
@@ -19,6 +20,7 @@ pub enum ValidationError {
         context: DependencyContext,
     },
     EmptyName,
+    CompilerVersion(VersionMismatch),
 }
 
 /// Context where a dependency is declared
@@ -26,6 +28,7 @@ pub enum ValidationError {
 pub enum DependencyContext {
     Package,
     Test,
+    Bench,
 }
 
 impl ValidationResult {
@@ -74,7 +77,7 @@ pub fn validate_config(config: &SpagoConfig, query: &PackageQuery) -> Validation
 
     // Validate test dependencies
     if let Some(test) = &config.package.test {
-        for dep in &test.dependencies {
+        for dep in test.all_dependencies() {
             if !query.exists(dep) {
                 result.add_error(ValidationError::MissingDependency {
                     package: dep.clone(),
@@ -84,6 +87,16 @@ pub fn validate_config(config: &SpagoConfig, query: &PackageQuery) -> Validation
         }
     }
 
+    // Validate bench dependencies
+    for dep in config.bench_dependencies() {
+        if !query.exists(dep) {
+            result.add_error(ValidationError::MissingDependency {
+                package: dep.clone(),
+                context: DependencyContext::Bench,
+            });
+        }
+    }
+
     // Check for duplicate dependencies
     let mut seen = HashSet::new();
     for dep in &config.package.dependencies {
@@ -142,6 +155,7 @@ impl std::fmt::Display for ValidationError {
                 let ctx = match context {
                     DependencyContext::Package => "package dependencies",
                     DependencyContext::Test => "test dependencies",
+                    DependencyContext::Bench => "bench dependencies",
                 };
                 write!(
                     f,
@@ -152,6 +166,7 @@ impl std::fmt::Display for ValidationError {
             ValidationError::EmptyName => {
                 write!(f, "Package name cannot be empty")
             }
+            ValidationError::CompilerVersion(mismatch) => write!(f, "{}", mismatch),
         }
     }
 }
@@ -197,6 +212,10 @@ mod tests {
                 name: PackageName::new("test"),
                 dependencies: vec![PackageName::new("prelude"), PackageName::new("effect")],
                 test: None,
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
             },
             workspace: Default::default(),
             workspace_root: PathBuf::from("."),
@@ -217,6 +236,10 @@ mod tests {
                 name: PackageName::new("test"),
                 dependencies: vec![PackageName::new("nonexistent")],
                 test: None,
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
             },
             workspace: Default::default(),
             workspace_root: PathBuf::from("."),
@@ -0,0 +1,367 @@
+mod prune;
+mod search;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use tokio::process::Command;
+
+use crate::{
+    build::run_from_root::{make_path_relative_to_workspace, map_sources_to_output_dir},
+    compiler::{toolchain::resolve_toolchain, version::check_compiler_version},
+    config::SpagoConfig,
+    install::install_all_dependencies,
+    modules::{discover_all_modules, ModuleInfo},
+    registry::{PackageQuery, PackageSet},
+    sources::BuildSources,
+};
+
+const DEFAULT_OUTPUT: &str = "generated-docs";
+
+/// What `ragu docs` generates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DocsFormat {
+    #[default]
+    Html,
+    Markdown,
+    Ctags,
+    Etags,
+    /// The compiler's docs.json for each module
+    Json,
+}
+
+impl DocsFormat {
+    /// Where the docs go when no output is given, inside the docs directory
+    fn default_output(self) -> &'static str {
+        match self {
+            DocsFormat::Html => "html",
+            DocsFormat::Markdown => "md",
+            DocsFormat::Json => "json",
+            DocsFormat::Ctags => "tags",
+            DocsFormat::Etags => "TAGS",
+        }
+    }
+
+    /// The `purs docs --format` to run. JSON is read from the compile
+    /// output after a markdown run.
+    fn purs_format(self) -> &'static str {
+        match self {
+            DocsFormat::Html => "html",
+            DocsFormat::Markdown | DocsFormat::Json => "markdown",
+            DocsFormat::Ctags => "ctags",
+            DocsFormat::Etags => "etags",
+        }
+    }
+}
+
+/// Which modules to document
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocsScope {
+    #[default]
+    All,
+    /// Only modules from the package set
+    DepsOnly,
+    /// Only modules from the workspace's own packages
+    NoDeps,
+}
+
+/// Docs options from the command line
+#[derive(Debug, Default)]
+pub struct DocsArgs {
+    pub format: DocsFormat,
+    pub scope: DocsScope,
+    /// Overrides `workspace.docs.output`
+    pub output: Option<PathBuf>,
+    /// Open the generated HTML in a browser
+    pub open: bool,
+}
+
+/// Generate docs for every module in the build with `purs docs`.
+///
+/// purs needs every module to typecheck, so it always sees all sources and
+/// modules outside the scope are removed from what it wrote afterwards.
+pub async fn execute(args: DocsArgs, verbose: bool) -> Result<()> {
+    let config =
+        crate::config::load_config_cwd().context("Failed to load spago.yaml configuration")?;
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output(&config, args.format));
+
+    check_compiler_version(&config, verbose).await?;
+    let package_set = config.package_set().await?;
+    install_all_dependencies(&config, &package_set, false).await?;
+    let sources =
+        crate::sources::generate_sources(&config, Some(package_set.clone()), false, false, verbose)
+            .await?;
+
+    let modules = discover_all_modules(&sources)?;
+    let workspace_packages = workspace_packages(&config, &package_set);
+    let documented: Vec<&ModuleInfo> = modules
+        .iter()
+        .filter(|module| in_scope(args.scope, module, &workspace_packages))
+        .collect();
+    if documented.is_empty() {
+        anyhow::bail!("No modules to document");
+    }
+
+    println!(
+        "{} Generating {} docs for {} modules",
+        "→".cyan(),
+        args.format
+            .to_possible_value()
+            .expect("no skipped formats")
+            .get_name(),
+        documented.len()
+    );
+
+    match args.format {
+        DocsFormat::Json => {
+            let markdown = tempfile::tempdir().context("Failed to create a temporary directory")?;
+            run_purs_docs(&config, &sources, args.format, markdown.path(), verbose).await?;
+            write_json_docs(&config.output_dir(), &documented, &output)?;
+        }
+        format => {
+            run_purs_docs(&config, &sources, format, &output, verbose).await?;
+            if args.scope != DocsScope::All {
+                let keep: HashSet<&str> = documented.iter().map(|m| m.name.as_str()).collect();
+                match format {
+                    DocsFormat::Html | DocsFormat::Markdown => {
+                        prune::prune_module_files(&output, format, &modules, &keep)?
+                    }
+                    _ => prune::prune_tags(&output, format, &config.workspace_root, &documented)?,
+                }
+            }
+            if format == DocsFormat::Html {
+                let packages: HashMap<&str, &str> = documented
+                    .iter()
+                    .map(|m| (m.name.as_str(), package_label(m, &config)))
+                    .collect();
+                search::write_search_index(&output, &packages)?;
+            }
+        }
+    }
+
+    println!("{} Docs written to {}", "✓".green(), output.display());
+
+    if args.open {
+        if args.format == DocsFormat::Html {
+            open_in_browser(&output.join("index.html"))?;
+        } else {
+            println!("--open only applies to html docs");
+        }
+    }
+    Ok(())
+}
+
+fn default_output(config: &SpagoConfig, format: DocsFormat) -> PathBuf {
+    let docs_dir = config
+        .workspace
+        .docs
+        .as_ref()
+        .and_then(|docs| docs.output.as_deref())
+        .unwrap_or(DEFAULT_OUTPUT);
+    config
+        .workspace_root
+        .join(docs_dir)
+        .join(format.default_output())
+}
+
+/// The workspace's own packages: the root and every local package
+fn workspace_packages(config: &SpagoConfig, package_set: &PackageSet) -> HashSet<String> {
+    let query = PackageQuery::new(package_set);
+    let mut packages: HashSet<String> = query
+        .local_packages()
+        .into_iter()
+        .map(|package| package.name.0.clone())
+        .collect();
+    // Modules from the current package's sources are labelled main
+    packages.insert("main".to_string());
+    packages.insert(config.package.name.0.clone());
+    packages
+}
+
+fn in_scope(scope: DocsScope, module: &ModuleInfo, workspace_packages: &HashSet<String>) -> bool {
+    let local = workspace_packages.contains(&module.package_name);
+    match scope {
+        DocsScope::All => true,
+        DocsScope::DepsOnly => !local,
+        DocsScope::NoDeps => local,
+    }
+}
+
+fn package_label<'a>(module: &'a ModuleInfo, config: &'a SpagoConfig) -> &'a str {
+    if module.package_name == "main" {
+        &config.package.name.0
+    } else {
+        &module.package_name
+    }
+}
+
+/// Run `purs docs` from the workspace root, the same way the compiler runs,
+/// sharing its output directory
+async fn run_purs_docs(
+    config: &SpagoConfig,
+    sources: &BuildSources,
+    format: DocsFormat,
+    output: &Path,
+    verbose: bool,
+) -> Result<()> {
+    let mut globs: Vec<String> = sources
+        .dependency_globs
+        .iter()
+        .map(|g| g.glob_pattern.clone())
+        .collect();
+    globs.extend(sources.main_sources.iter().cloned());
+    // purs errors on globs that don't match anything
+    globs.retain(|source| glob::glob(source).is_ok_and(|mut files| files.next().is_some()));
+    let globs = map_sources_to_output_dir(&globs, &config.workspace_root)?;
+
+    if let Some(parent) = output.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).context("Failed to create docs output directory")?;
+        }
+    }
+
+    let toolchain = resolve_toolchain(config)?;
+    // purs runs from the workspace root, so relative paths won't resolve
+    let purs = toolchain.purs.canonicalize().unwrap_or(toolchain.purs);
+
+    let mut command = Command::new(&purs);
+    command
+        .arg("docs")
+        .args(&globs)
+        .arg("--format")
+        .arg(format.purs_format())
+        .arg("--output")
+        .arg(make_path_relative_to_workspace(
+            output,
+            &config.workspace_root,
+        )?)
+        .arg("--compile-output")
+        .arg(make_path_relative_to_workspace(
+            &config.output_dir(),
+            &config.workspace_root,
+        )?)
+        .current_dir(&config.workspace_root)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    if verbose {
+        println!("  {:?}", command.as_std());
+    }
+
+    let status = command
+        .status()
+        .await
+        .context("Failed to start purs docs")?;
+    if !status.success() {
+        eprintln!("❌ Generating docs failed");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Copy each module's docs.json from the compile output to `<Module>.json`
+fn write_json_docs(compile_output: &Path, modules: &[&ModuleInfo], output: &Path) -> Result<()> {
+    fs::create_dir_all(output).context("Failed to create docs output directory")?;
+    for module in modules {
+        let docs = compile_output.join(&module.name).join("docs.json");
+        if !docs.exists() {
+            continue;
+        }
+        fs::copy(&docs, output.join(format!("{}.json", module.name)))
+            .with_context(|| format!("Failed to copy docs for {}", module.name))?;
+    }
+    Ok(())
+}
+
+fn open_in_browser(path: &Path) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to open {} with {}", path.display(), opener))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str, package: &str) -> ModuleInfo {
+        ModuleInfo {
+            name: name.to_string(),
+            file_path: PathBuf::from(format!("{}.purs", name)),
+            package_name: package.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_in_scope() {
+        let workspace: HashSet<String> = ["main", "app", "lib"].map(String::from).into();
+        let app = module("App.Main", "main");
+        let lib = module("Lib", "lib");
+        let prelude = module("Prelude", "prelude");
+
+        for (scope, expected) in [
+            (DocsScope::All, [true, true, true]),
+            (DocsScope::DepsOnly, [false, false, true]),
+            (DocsScope::NoDeps, [true, true, false]),
+        ] {
+            let found = [&app, &lib, &prelude].map(|m| in_scope(scope, m, &workspace));
+            assert_eq!(found, expected, "{:?}", scope);
+        }
+    }
+
+    #[test]
+    fn test_default_output() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("spago.yaml");
+        fs::write(&path, "workspace: {}\npackage:\n  name: app\n").unwrap();
+        let config = crate::config::load_config(&path, false).unwrap();
+        assert_eq!(
+            default_output(&config, DocsFormat::Html),
+            root.path().join("generated-docs/html")
+        );
+
+        fs::write(
+            &path,
+            "workspace:\n  docs:\n    output: docs\npackage:\n  name: app\n",
+        )
+        .unwrap();
+        let config = crate::config::load_config(&path, false).unwrap();
+        assert_eq!(
+            default_output(&config, DocsFormat::Ctags),
+            root.path().join("docs/tags")
+        );
+    }
+
+    #[test]
+    fn test_write_json_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let compile_output = dir.path().join("output");
+        fs::create_dir_all(compile_output.join("Data.Lib")).unwrap();
+        fs::write(compile_output.join("Data.Lib/docs.json"), "{}").unwrap();
+
+        let lib = module("Data.Lib", "lib");
+        let missing = module("Data.Missing", "lib");
+        let output = dir.path().join("docs");
+        write_json_docs(&compile_output, &[&lib, &missing], &output).unwrap();
+
+        assert!(output.join("Data.Lib.json").exists());
+        assert!(!output.join("Data.Missing.json").exists());
+    }
+}
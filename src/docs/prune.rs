@@ -0,0 +1,151 @@
+//! Removing modules outside `--deps-only` or `--no-deps` from what
+//! `purs docs` wrote.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::modules::ModuleInfo;
+
+use super::DocsFormat;
+
+/// Delete the pages of modules not being documented, and their links from
+/// the HTML index
+pub fn prune_module_files(
+    output: &Path,
+    format: DocsFormat,
+    modules: &[ModuleInfo],
+    keep: &HashSet<&str>,
+) -> Result<()> {
+    let extension = match format {
+        DocsFormat::Html => "html",
+        _ => "md",
+    };
+    let removed: Vec<&str> = modules
+        .iter()
+        .map(|m| m.name.as_str())
+        .filter(|name| !keep.contains(name))
+        .collect();
+
+    for module in &removed {
+        let page = output.join(format!("{}.{}", module, extension));
+        if page.exists() {
+            fs::remove_file(&page)
+                .with_context(|| format!("Failed to remove {}", page.display()))?;
+        }
+    }
+
+    let index = output.join("index.html");
+    if format == DocsFormat::Html && index.exists() {
+        let html = fs::read_to_string(&index).context("Failed to read docs index")?;
+        fs::write(&index, remove_index_links(&html, &removed))
+            .context("Failed to write docs index")?;
+    }
+    Ok(())
+}
+
+/// Drop the index list items linking to the given module pages
+fn remove_index_links(html: &str, modules: &[&str]) -> String {
+    if modules.is_empty() {
+        return html.to_string();
+    }
+    let pattern = modules
+        .iter()
+        .map(|m| regex::escape(m))
+        .collect::<Vec<_>>()
+        .join("|");
+    let links = Regex::new(&format!(
+        r#"\s*<li>\s*<a href="(?:{})\.html">.*?</a>\s*</li>"#,
+        pattern
+    ))
+    .expect("escaped module names");
+    links.replace_all(html, "").to_string()
+}
+
+/// Keep only the tags for files of documented modules
+pub fn prune_tags(
+    output: &Path,
+    format: DocsFormat,
+    workspace_root: &Path,
+    documented: &[&ModuleInfo],
+) -> Result<()> {
+    let files: HashSet<PathBuf> = documented
+        .iter()
+        .filter_map(|m| m.file_path.canonicalize().ok())
+        .collect();
+    // Tag paths are relative to the workspace root, where purs ran
+    let keep = |path: &str| {
+        workspace_root
+            .join(path)
+            .canonicalize()
+            .is_ok_and(|path| files.contains(&path))
+    };
+
+    let tags = fs::read_to_string(output).context("Failed to read tags file")?;
+    let pruned = match format {
+        DocsFormat::Etags => prune_etags(&tags, keep),
+        _ => prune_ctags(&tags, keep),
+    };
+    fs::write(output, pruned).context("Failed to write tags file")
+}
+
+/// ctags has a line per tag, `name<TAB>file<TAB>address`
+fn prune_ctags(tags: &str, keep: impl Fn(&str) -> bool) -> String {
+    tags.lines()
+        .filter(|line| line.starts_with("!_") || line.split('\t').nth(1).is_none_or(&keep))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// etags has a section per file, each starting with a form feed line then
+/// `file,size`
+fn prune_etags(tags: &str, keep: impl Fn(&str) -> bool) -> String {
+    let mut sections = tags.split('\x0c');
+    // Whatever comes before the first section
+    let mut pruned = sections.next().unwrap_or("").to_string();
+    for section in sections {
+        let header = section
+            .trim_start_matches('\n')
+            .lines()
+            .next()
+            .unwrap_or("");
+        if header.rsplit_once(',').is_none_or(|(file, _)| keep(file)) {
+            pruned.push('\x0c');
+            pruned.push_str(section);
+        }
+    }
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_index_links() {
+        let html = "<ul>\n  <li><a href=\"Data.Array.html\">Data.Array</a></li>\n  <li><a href=\"App.Main.html\">App.Main</a></li>\n</ul>";
+
+        let pruned = remove_index_links(html, &["Data.Array"]);
+        assert!(!pruned.contains("Data.Array"));
+        assert!(pruned.contains("App.Main.html"));
+        assert_eq!(remove_index_links(html, &[]), html);
+    }
+
+    #[test]
+    fn test_prune_ctags() {
+        let tags = "!_TAG_FILE_SORTED\t1\nmap\t.spago/p/src/Data.purs\t3\nmain\tsrc/Main.purs\t1\n";
+        let pruned = prune_ctags(tags, |file| file.starts_with("src/"));
+        assert_eq!(pruned, "!_TAG_FILE_SORTED\t1\nmain\tsrc/Main.purs\t1\n");
+    }
+
+    #[test]
+    fn test_prune_etags() {
+        let tags =
+            "\x0c\n.spago/p/src/Data.purs,20\nmap\x7f3,0\n\x0c\nsrc/Main.purs,10\nmain\x7f1,0\n";
+        let pruned = prune_etags(tags, |file| file.starts_with("src/"));
+        assert_eq!(pruned, "\x0c\nsrc/Main.purs,10\nmain\x7f1,0\n");
+    }
+}
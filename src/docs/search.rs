@@ -0,0 +1,184 @@
+//! A search index over the generated HTML docs, written as
+//! `search-index.json` and a standalone `search.html` that works offline.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+
+/// Declaration anchors: `v:` values, `t:` types and classes, `k:` kinds
+static DECLARATION_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"id="([vtk]):([^"]+)""#).unwrap());
+
+/// `__SEARCH_INDEX__` is replaced with the index entries
+const SEARCH_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Search docs</title>
+<link rel="stylesheet" href="index.css">
+</head>
+<body>
+<main class="container">
+<h1><a href="index.html">Docs</a> search</h1>
+<input id="query" type="search" placeholder="Module, type or value" autofocus>
+<ul id="results"></ul>
+</main>
+<script>
+const index = __SEARCH_INDEX__;
+const query = document.getElementById("query");
+const results = document.getElementById("results");
+function search() {
+  const term = query.value.trim().toLowerCase();
+  results.replaceChildren();
+  if (!term) return;
+  const matches = index
+    .filter((entry) => entry.name.toLowerCase().includes(term))
+    .sort((a, b) => (a.name.toLowerCase() === term ? -1 : 0) - (b.name.toLowerCase() === term ? -1 : 0) || a.name.length - b.name.length)
+    .slice(0, 100);
+  for (const entry of matches) {
+    const item = document.createElement("li");
+    const link = document.createElement("a");
+    link.href = entry.url;
+    link.textContent = entry.name;
+    item.append(link, ` ${entry.kind} in ${entry.module} (${entry.package})`);
+    results.append(item);
+  }
+}
+query.addEventListener("input", search);
+query.value = new URLSearchParams(location.search).get("q") || "";
+search();
+</script>
+</body>
+</html>
+"#;
+
+/// One searchable thing in the docs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchEntry {
+    pub name: String,
+    /// module, value, type or kind
+    pub kind: &'static str,
+    pub module: String,
+    pub package: String,
+    /// Relative to the docs directory
+    pub url: String,
+}
+
+/// Index the module pages in `output`, given each documented module's package
+pub fn write_search_index(output: &Path, packages: &HashMap<&str, &str>) -> Result<()> {
+    let mut modules: Vec<(&str, &str)> = packages.iter().map(|(m, p)| (*m, *p)).collect();
+    modules.sort_unstable();
+
+    let mut entries = Vec::new();
+    for (module, package) in modules {
+        let page = output.join(format!("{}.html", module));
+        let Ok(html) = fs::read_to_string(&page) else {
+            continue;
+        };
+        entries.extend(index_page(module, package, &html));
+    }
+
+    let json = serde_json::to_string(&entries)?;
+    fs::write(output.join("search-index.json"), &json)
+        .context("Failed to write docs search index")?;
+    // Inlined so the page works from file:// without fetching
+    let page = SEARCH_PAGE.replace("__SEARCH_INDEX__", &json.replace("</", "<\\/"));
+    fs::write(output.join("search.html"), page).context("Failed to write docs search page")?;
+    Ok(())
+}
+
+/// The module itself and every declaration anchored in its page
+fn index_page(module: &str, package: &str, html: &str) -> Vec<SearchEntry> {
+    let entry = |name: String, kind, url: String| SearchEntry {
+        name,
+        kind,
+        module: module.to_string(),
+        package: package.to_string(),
+        url,
+    };
+
+    let mut entries = vec![entry(
+        module.to_string(),
+        "module",
+        format!("{}.html", module),
+    )];
+    for captures in DECLARATION_ID.captures_iter(html) {
+        let kind = match &captures[1] {
+            "v" => "value",
+            "t" => "type",
+            _ => "kind",
+        };
+        let id = unescape_html(&captures[2]);
+        let url = format!("{}.html#{}:{}", module, &captures[1], id);
+        if entries.iter().any(|e| e.url == url) {
+            continue;
+        }
+        entries.push(entry(id, kind, url));
+    }
+    entries
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_page() {
+        let html = r##"<div class="decl" id="t:Maybe"></div>
+<div id="v:Just"></div><a href="#v:Just">Just</a><div id="v:Just"></div>
+<div class="decl" id="v:&lt;$&gt;"></div>"##;
+
+        let entries = index_page("Data.Maybe", "maybe", html);
+        let found: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.kind, e.url.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Data.Maybe", "module", "Data.Maybe.html"),
+                ("Maybe", "type", "Data.Maybe.html#t:Maybe"),
+                ("Just", "value", "Data.Maybe.html#v:Just"),
+                ("<$>", "value", "Data.Maybe.html#v:<$>"),
+            ]
+        );
+        assert!(entries.iter().all(|e| e.package == "maybe"));
+    }
+
+    #[test]
+    fn test_write_search_index() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("App.Main.html"),
+            r#"<div class="decl" id="v:main"></div>"#,
+        )
+        .unwrap();
+        let packages = HashMap::from([("App.Main", "app"), ("Not.Generated", "app")]);
+
+        write_search_index(dir.path(), &packages).unwrap();
+
+        let index: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("search-index.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(index.as_array().unwrap().len(), 2);
+        assert_eq!(index[1]["url"], "App.Main.html#v:main");
+
+        let page = fs::read_to_string(dir.path().join("search.html")).unwrap();
+        assert!(page.contains("App.Main.html#v:main"));
+        assert!(!page.contains("__SEARCH_INDEX__"));
+    }
+}
@@ -0,0 +1,455 @@
+mod server;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    build::{
+        diagnostics::{CompilerOutput, Diagnostic},
+        run_from_root::{make_path_relative_to_workspace, map_sources_to_output_dir},
+        watch::config_files,
+    },
+    compiler::{toolchain::resolve_toolchain, version::check_compiler_version},
+    config::{load_config, SpagoConfig},
+    install::install_with_extra_dependencies,
+    registry::{PackageName, PackageQuery, PackageSet},
+    test::{test_globs, TEST_SOURCES},
+};
+
+use server::{ServerOptions, ServerState};
+
+/// What to do with the workspace's ide server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdeCommand {
+    /// Start the server, or restart it when its sources are out of date
+    Start {
+        port: Option<u16>,
+    },
+    Stop,
+    Status,
+    /// Reload every module into the server
+    Load,
+    /// Typecheck a single file with the server
+    Rebuild {
+        file: PathBuf,
+    },
+}
+
+/// Manage the single `purs ide server` shared by the whole workspace.
+///
+/// Starting or loading checks the server was started with the current
+/// sources and spago.yaml files, and restarts it if not. A rebuild, which
+/// editors send on every save, only checks the spago.yaml files.
+pub async fn execute(command: IdeCommand, verbose: bool) -> Result<()> {
+    let config = crate::config::load_config_cwd()?;
+    let spago_dir = config.spago_dir();
+
+    match command {
+        IdeCommand::Stop => stop(&spago_dir).await,
+        IdeCommand::Status => status(&spago_dir).await,
+        IdeCommand::Start { port } => {
+            ensure_server(&config, port, verbose).await?;
+            Ok(())
+        }
+        IdeCommand::Load => {
+            let (state, started) = ensure_server(&config, None, verbose).await?;
+            // A new server has just loaded everything
+            if !started {
+                load(&state).await?;
+            }
+            Ok(())
+        }
+        IdeCommand::Rebuild { file } => {
+            let state = match ServerState::load(&spago_dir) {
+                Some(state) if state.is_alive().await && !configs_changed(&state) => state,
+                _ => ensure_server(&config, None, verbose).await?.0,
+            };
+            rebuild(&config, &state, &file).await
+        }
+    }
+}
+
+/// A running server with up to date sources, and whether it was just started
+async fn ensure_server(
+    config: &SpagoConfig,
+    port: Option<u16>,
+    verbose: bool,
+) -> Result<(ServerState, bool)> {
+    let spago_dir = config.spago_dir();
+    check_compiler_version(config, verbose).await?;
+    let package_set = config.package_set().await?;
+    let sources = workspace_sources(config, &package_set, verbose).await?;
+    let config_files = canonical_files(&config_files(config, &package_set));
+    let config_hash = hash_files(&config_files);
+    let fingerprint = fingerprint(&sources, &config_hash);
+
+    if let Some(state) = ServerState::load(&spago_dir) {
+        if state.is_alive().await {
+            let port_changed = port.is_some_and(|port| port != state.port);
+            if state.fingerprint == fingerprint && !port_changed {
+                println!(
+                    "{} purs ide server running on port {}",
+                    "✓".green(),
+                    state.port
+                );
+                return Ok((state, false));
+            }
+            println!(
+                "{} Sources or config changed, restarting purs ide server",
+                "→".cyan()
+            );
+            server::quit(&state).await?;
+        }
+        ServerState::remove(&spago_dir)?;
+    }
+
+    let toolchain = resolve_toolchain(config)?;
+    let options = ServerOptions {
+        // The server runs from the workspace root, so relative paths won't resolve
+        purs: toolchain.purs.canonicalize().unwrap_or(toolchain.purs),
+        port: match port {
+            Some(port) => port,
+            None => server::free_port()?,
+        },
+        workspace_root: config.workspace_root.clone(),
+        output_dir: make_path_relative_to_workspace(&config.output_dir(), &config.workspace_root)?,
+        sources,
+    };
+    if verbose {
+        println!(
+            "{} Starting purs ide server with {} source globs",
+            "→".cyan(),
+            options.sources.len()
+        );
+    }
+
+    let pid = server::spawn(&options, &spago_dir).await?;
+    let state = ServerState {
+        pid,
+        port: options.port,
+        workspace_root: config
+            .workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| config.workspace_root.clone()),
+        fingerprint,
+        config_files,
+        config_hash,
+    };
+    state.save(&spago_dir)?;
+    println!(
+        "{} Started purs ide server on port {} (pid {})",
+        "✓".green(),
+        state.port,
+        state.pid
+    );
+
+    load(&state).await?;
+    Ok((state, true))
+}
+
+/// Every source in the workspace: each package's sources and tests, and
+/// all of their dependencies. The same from any package in the workspace,
+/// relative to the workspace root where the server runs.
+async fn workspace_sources(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+    verbose: bool,
+) -> Result<Vec<String>> {
+    let root_config = if config.is_workspace_root() {
+        config.clone()
+    } else {
+        crate::config::load_config(config.workspace_root.join("spago.yaml"), false)?
+    };
+
+    let query = PackageQuery::new(package_set);
+    let mut dependencies: Vec<PackageName> = root_config
+        .all_dependencies()
+        .into_iter()
+        .cloned()
+        .collect();
+    dependencies.extend(query.all_workspace_dependencies());
+    dependencies.extend(query.all_workspace_test_dependencies());
+    install_with_extra_dependencies(config, package_set, &dependencies).await?;
+
+    let sources = crate::sources::generate_sources(
+        &root_config,
+        Some(package_set.clone()),
+        true,
+        true,
+        verbose,
+    )
+    .await?;
+
+    let mut globs: Vec<String> = sources
+        .dependency_globs
+        .iter()
+        .map(|g| g.glob_pattern.clone())
+        .collect();
+    // The root's own sources, which generate_sources gives relative to the
+    // current directory
+    let root_glob = |glob: &str| {
+        config
+            .workspace_root
+            .join(glob.trim_start_matches("./"))
+            .to_string_lossy()
+            .to_string()
+    };
+    if sources.main_sources.is_some() {
+        globs.push(root_glob("./src/**/*.purs"));
+    }
+    globs.extend(
+        package_test_globs(&root_config)
+            .iter()
+            .map(|glob| root_glob(glob)),
+    );
+    // Every suite of every local package, wherever its sources are
+    for package in query.local_packages() {
+        let local_config = load_config(package.path.join("spago.yaml"), true).ok();
+        for glob in local_config
+            .as_ref()
+            .map_or_else(|| vec![TEST_SOURCES.to_string()], package_test_globs)
+        {
+            globs.push(
+                package
+                    .path
+                    .join(glob.trim_start_matches("./"))
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+    }
+
+    // purs errors on globs that don't match anything
+    globs.retain(|source| glob::glob(source).is_ok_and(|mut files| files.next().is_some()));
+    let mut globs = map_sources_to_output_dir(&globs, &config.workspace_root)?;
+    globs.sort();
+    globs.dedup();
+    Ok(globs)
+}
+
+/// A package's test globs, relative to its directory. Without a test
+/// config that's just the test directory.
+fn package_test_globs(config: &SpagoConfig) -> Vec<String> {
+    match &config.package.test {
+        Some(test_config) => test_globs(test_config),
+        None => vec![TEST_SOURCES.to_string()],
+    }
+}
+
+/// Hash the server's source globs along with its spago.yaml files' hash,
+/// so dependency or config changes are noticed
+fn fingerprint(sources: &[String], config_hash: &str) -> String {
+    let mut hasher = Sha256::new();
+    for source in sources {
+        hasher.update(source.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(config_hash.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The files that exist, each once
+fn canonical_files(files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|file| file.canonicalize().ok())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Hash the paths and contents of files
+fn hash_files(files: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update(fs::read(file).unwrap_or_default());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Whether any of the spago.yaml files the server was started with changed
+fn configs_changed(state: &ServerState) -> bool {
+    hash_files(&state.config_files) != state.config_hash
+}
+
+async fn load(state: &ServerState) -> Result<()> {
+    let response = server::request(state.port, &json!({ "command": "load", "params": {} })).await?;
+    let message = response.result.as_str().unwrap_or_default().to_string();
+    if !response.is_success() {
+        anyhow::bail!("purs ide server failed to load modules: {}", message);
+    }
+    println!("{} {}", "✓".green(), message);
+    Ok(())
+}
+
+async fn stop(spago_dir: &Path) -> Result<()> {
+    match ServerState::load(spago_dir) {
+        Some(state) if state.is_alive().await => {
+            server::quit(&state).await?;
+            println!(
+                "{} Stopped purs ide server on port {}",
+                "✓".green(),
+                state.port
+            );
+        }
+        _ => println!("purs ide server is not running"),
+    }
+    ServerState::remove(spago_dir)
+}
+
+/// Report on the recorded server without starting, restarting or
+/// installing anything
+async fn status(spago_dir: &Path) -> Result<()> {
+    let state = match ServerState::load(spago_dir) {
+        Some(state) if state.is_alive().await => state,
+        _ => {
+            println!("purs ide server is not running. Start it with ragu ide start");
+            return Ok(());
+        }
+    };
+
+    println!(
+        "{} purs ide server running on port {} (pid {})",
+        "✓".green(),
+        state.port,
+        state.pid
+    );
+    if configs_changed(&state) {
+        println!("⚠️  spago.yaml changed since it started, ragu ide load restarts it");
+    }
+    Ok(())
+}
+
+/// Typecheck a file, reporting its errors and warnings like a build does
+async fn rebuild(config: &SpagoConfig, state: &ServerState, file: &Path) -> Result<()> {
+    let path = file
+        .canonicalize()
+        .with_context(|| format!("File not found: {}", file.display()))?;
+    let response = server::request(
+        state.port,
+        &json!({ "command": "rebuild", "params": { "file": path } }),
+    )
+    .await?;
+
+    let mut output = rebuild_output(&response)?;
+    output.map_paths_to_cwd(&config.workspace_root);
+    output.render(true);
+
+    if !response.is_success() {
+        eprintln!("❌ Rebuild failed: {}", output.summary());
+        std::process::exit(1);
+    }
+    match output.summary() {
+        summary if summary.warnings > 0 => {
+            println!("{} Rebuilt {}: {}", "✓".green(), file.display(), summary)
+        }
+        _ => println!("{} Rebuilt {}", "✓".green(), file.display()),
+    }
+    Ok(())
+}
+
+/// Errors come back as the result of a failed rebuild, warnings as the
+/// result of a successful one
+fn rebuild_output(response: &server::Response) -> Result<CompilerOutput> {
+    if !response.result.is_array() {
+        anyhow::bail!("purs ide server: {}", response.result);
+    }
+    let diagnostics: Vec<Diagnostic> = serde_json::from_value(response.result.clone())
+        .context("Unexpected rebuild response from purs ide server")?;
+    Ok(if response.is_success() {
+        CompilerOutput {
+            warnings: diagnostics,
+            errors: vec![],
+        }
+    } else {
+        CompilerOutput {
+            warnings: vec![],
+            errors: diagnostics,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_changes_with_sources_and_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let spago_yaml = dir.path().join("spago.yaml");
+        fs::write(&spago_yaml, "package:\n  name: app\n").unwrap();
+        let sources = vec!["src/**/*.purs".to_string()];
+        // The same file through another path counts once, missing ones not at all
+        let config_files = canonical_files(&[
+            spago_yaml.clone(),
+            dir.path().join("./spago.yaml"),
+            dir.path().join("lib/spago.yaml"),
+        ]);
+        assert_eq!(config_files, vec![spago_yaml.canonicalize().unwrap()]);
+
+        let config_hash = hash_files(&config_files);
+        let original = fingerprint(&sources, &config_hash);
+        assert_ne!(
+            fingerprint(&["test/**/*.purs".to_string()], &config_hash),
+            original
+        );
+
+        let state = ServerState {
+            pid: 42,
+            port: 15000,
+            workspace_root: dir.path().to_path_buf(),
+            fingerprint: original.clone(),
+            config_files: config_files.clone(),
+            config_hash,
+        };
+        assert!(!configs_changed(&state));
+
+        fs::write(
+            &spago_yaml,
+            "package:\n  name: app\n  dependencies: [prelude]\n",
+        )
+        .unwrap();
+        assert!(configs_changed(&state));
+        assert_ne!(fingerprint(&sources, &hash_files(&config_files)), original);
+    }
+
+    #[test]
+    fn test_rebuild_output() {
+        let diagnostic = json!([{
+            "moduleName": "Main",
+            "errorCode": "UnknownName",
+            "message": "Unknown value foo",
+            "filename": "src/Main.purs",
+            "position": { "startLine": 3, "startColumn": 8, "endLine": 3, "endColumn": 11 }
+        }]);
+
+        let failed = server::Response {
+            result_type: "error".to_string(),
+            result: diagnostic.clone(),
+        };
+        let output = rebuild_output(&failed).unwrap();
+        assert_eq!(output.errors.len(), 1);
+        assert!(output.warnings.is_empty());
+        assert_eq!(output.errors[0].error_code, "UnknownName");
+
+        let succeeded = server::Response {
+            result_type: "success".to_string(),
+            result: diagnostic,
+        };
+        assert_eq!(rebuild_output(&succeeded).unwrap().warnings.len(), 1);
+
+        let message = server::Response {
+            result_type: "error".to_string(),
+            result: json!("File not found"),
+        };
+        assert!(rebuild_output(&message).is_err());
+    }
+}
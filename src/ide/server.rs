@@ -0,0 +1,375 @@
+//! The `purs ide server` process: starting it, talking to it and the
+//! record of it kept in `.spago`.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+const STATE_FILE: &str = "ide.json";
+const LOG_FILE: &str = "ide.log";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a new server gets to start listening
+const STARTUP_ATTEMPTS: u32 = 100;
+const STARTUP_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a server gets to answer `cwd` or `quit`, which are instant
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a server gets to stop listening after `quit`
+const SHUTDOWN_ATTEMPTS: u32 = 20;
+
+/// A running server, as recorded in `.spago/ide.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerState {
+    pub pid: u32,
+    pub port: u16,
+    /// Where the server runs, which it reports back when asked
+    pub workspace_root: PathBuf,
+    /// The sources and configs the server was started with
+    pub fingerprint: String,
+    /// The spago.yaml files the sources came from
+    pub config_files: Vec<PathBuf>,
+    /// Their contents when the server was started
+    pub config_hash: String,
+}
+
+impl ServerState {
+    pub fn load(spago_dir: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(spago_dir.join(STATE_FILE)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self, spago_dir: &Path) -> Result<()> {
+        fs::create_dir_all(spago_dir).context("Failed to create .spago directory")?;
+        fs::write(
+            spago_dir.join(STATE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )
+        .context("Failed to write ide server state")
+    }
+
+    pub fn remove(spago_dir: &Path) -> Result<()> {
+        let path = spago_dir.join(STATE_FILE);
+        if path.exists() {
+            fs::remove_file(&path).context("Failed to remove ide server state")?;
+        }
+        Ok(())
+    }
+
+    /// Whether the server still answers on its port. Something else may
+    /// have the port by now, so it has to be running from this workspace.
+    pub async fn is_alive(&self) -> bool {
+        let cwd = json!({ "command": "cwd" });
+        let reply = tokio::time::timeout(REPLY_TIMEOUT, request(self.port, &cwd)).await;
+        let Ok(Ok(response)) = reply else {
+            return false;
+        };
+        let cwd = response
+            .result
+            .as_str()
+            .and_then(|cwd| Path::new(cwd).canonicalize().ok());
+        response.is_success() && cwd.is_some() && cwd == self.workspace_root.canonicalize().ok()
+    }
+}
+
+/// What to launch `purs ide server` with
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub purs: PathBuf,
+    pub port: u16,
+    pub workspace_root: PathBuf,
+    /// Relative to the workspace root
+    pub output_dir: String,
+    /// Relative to the workspace root
+    pub sources: Vec<String>,
+}
+
+impl ServerOptions {
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.purs);
+        command
+            .arg("ide")
+            .arg("server")
+            .arg("--port")
+            .arg(self.port.to_string())
+            .arg("--output-directory")
+            .arg(&self.output_dir)
+            .args(&self.sources)
+            .current_dir(&self.workspace_root);
+        command
+    }
+}
+
+/// Launch the server in the background, outliving ragu, and wait until it
+/// accepts requests. Its output goes to `.spago/ide.log`.
+pub async fn spawn(options: &ServerOptions, spago_dir: &Path) -> Result<u32> {
+    fs::create_dir_all(spago_dir).context("Failed to create .spago directory")?;
+    let log_path = spago_dir.join(LOG_FILE);
+    let log = File::create(&log_path).context("Failed to create ide server log")?;
+
+    let mut command = options.command();
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().context("Failed to open ide server log")?)
+        .stderr(log);
+    // Keep Ctrl-C in this terminal from reaching the server
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let mut child = command
+        .spawn()
+        .context("Failed to start purs ide server. Is purs installed?")?;
+
+    for _ in 0..STARTUP_ATTEMPTS {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!(
+                "purs ide server exited with {}, see {}",
+                status,
+                log_path.display()
+            );
+        }
+        if TcpStream::connect(("127.0.0.1", options.port))
+            .await
+            .is_ok()
+        {
+            return Ok(child.id());
+        }
+        tokio::time::sleep(STARTUP_INTERVAL).await;
+    }
+
+    let _ = child.kill();
+    anyhow::bail!(
+        "purs ide server didn't start listening on port {}, see {}",
+        options.port,
+        log_path.display()
+    )
+}
+
+/// Ask the server to exit, killing its process when it's still listening
+/// afterwards. A server that's already gone is ignored.
+pub async fn quit(state: &ServerState) -> Result<()> {
+    let quit = json!({ "command": "quit" });
+    let _ = tokio::time::timeout(REPLY_TIMEOUT, request(state.port, &quit)).await;
+    for _ in 0..SHUTDOWN_ATTEMPTS {
+        if TcpStream::connect(("127.0.0.1", state.port)).await.is_err() {
+            return Ok(());
+        }
+        tokio::time::sleep(STARTUP_INTERVAL).await;
+    }
+    kill(state.pid)
+}
+
+fn kill(pid: u32) -> Result<()> {
+    #[cfg(unix)]
+    let status = Command::new("kill")
+        .arg("-KILL")
+        .arg(pid.to_string())
+        .status();
+    #[cfg(windows)]
+    let status = Command::new("taskkill")
+        .args(["/F", "/PID"])
+        .arg(pid.to_string())
+        .status();
+    let status = status.context("Failed to kill purs ide server")?;
+    if !status.success() {
+        anyhow::bail!("Failed to kill purs ide server (pid {})", pid);
+    }
+    Ok(())
+}
+
+/// A port nothing is listening on right now
+pub fn free_port() -> Result<u16> {
+    let listener =
+        std::net::TcpListener::bind(("127.0.0.1", 0)).context("Failed to find a free port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// A server reply, `resultType` is `success` or `error`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub result_type: String,
+    #[serde(default)]
+    pub result: Value,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        self.result_type == "success"
+    }
+}
+
+/// Send one command and read the reply. The server answers each
+/// connection with a single line of JSON.
+pub async fn request(port: u16, command: &Value) -> Result<Response> {
+    let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(("127.0.0.1", port)))
+        .await
+        .context("Timed out connecting to purs ide server")?
+        .with_context(|| format!("Failed to connect to purs ide server on port {}", port))?;
+    let (reader, mut writer) = stream.into_split();
+
+    writer
+        .write_all(format!("{}\n", command).as_bytes())
+        .await
+        .context("Failed to send command to purs ide server")?;
+    writer.flush().await?;
+
+    let mut line = String::new();
+    BufReader::new(reader)
+        .read_line(&mut line)
+        .await
+        .context("Failed to read purs ide server response")?;
+    serde_json::from_str(&line)
+        .with_context(|| format!("Unexpected response from purs ide server: {}", line.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let spago_dir = dir.path().join(".spago");
+        assert_eq!(ServerState::load(&spago_dir), None);
+
+        let state = ServerState {
+            pid: 42,
+            port: 15000,
+            workspace_root: dir.path().to_path_buf(),
+            fingerprint: "abc".to_string(),
+            config_files: vec![dir.path().join("spago.yaml")],
+            config_hash: "def".to_string(),
+        };
+        state.save(&spago_dir).unwrap();
+        assert_eq!(ServerState::load(&spago_dir), Some(state));
+
+        ServerState::remove(&spago_dir).unwrap();
+        assert_eq!(ServerState::load(&spago_dir), None);
+    }
+
+    #[test]
+    fn test_server_command() {
+        let options = ServerOptions {
+            purs: PathBuf::from("/bin/purs"),
+            port: 15000,
+            workspace_root: PathBuf::from("../.."),
+            output_dir: "output".to_string(),
+            sources: vec![".spago/p/prelude/src/**/*.purs".to_string()],
+        };
+        let command = options.command();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "ide",
+                "server",
+                "--port",
+                "15000",
+                "--output-directory",
+                "output",
+                ".spago/p/prelude/src/**/*.purs"
+            ]
+        );
+        assert_eq!(command.get_current_dir(), Some(Path::new("../..")));
+    }
+
+    /// A server that answers one request with `result`, or with the
+    /// command's name when there's none
+    async fn fake_server(result: Option<Value>) -> (u16, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut line = String::new();
+            BufReader::new(reader).read_line(&mut line).await.unwrap();
+            let command: Value = serde_json::from_str(&line).unwrap();
+            let result = result.unwrap_or_else(|| command["command"].clone());
+            let reply = json!({ "resultType": "success", "result": result });
+            writer
+                .write_all(format!("{}\n", reply).as_bytes())
+                .await
+                .unwrap();
+        });
+        (port, server)
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let (port, server) = fake_server(None).await;
+        let response = request(port, &json!({ "command": "cwd" })).await.unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.result, json!("cwd"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_is_alive_checks_workspace() {
+        let workspace = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let state = |port| ServerState {
+            pid: 42,
+            port,
+            workspace_root: workspace.path().to_path_buf(),
+            fingerprint: "abc".to_string(),
+            config_files: vec![],
+            config_hash: "def".to_string(),
+        };
+
+        let (port, server) = fake_server(Some(json!(workspace.path()))).await;
+        assert!(state(port).is_alive().await);
+        server.await.unwrap();
+
+        // Another workspace's server took the port
+        let (port, server) = fake_server(Some(json!(other.path()))).await;
+        assert!(!state(port).is_alive().await);
+        server.await.unwrap();
+
+        let port = free_port().unwrap();
+        assert!(!state(port).is_alive().await);
+    }
+
+    fn state_on(port: u16, pid: u32) -> ServerState {
+        ServerState {
+            pid,
+            port,
+            workspace_root: PathBuf::from("."),
+            fingerprint: "abc".to_string(),
+            config_files: vec![],
+            config_hash: "def".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quit_leaves_a_server_that_exits() {
+        // The fake server stops listening after its one reply, so the
+        // bogus pid is never killed
+        let (port, server) = fake_server(None).await;
+        quit(&state_on(port, u32::MAX)).await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_quit_kills_a_hung_server() {
+        // Accepts connections but never answers or exits
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut process = Command::new("sleep").arg("30").spawn().unwrap();
+
+        quit(&state_on(port, process.id())).await.unwrap();
+
+        let status = process.wait().unwrap();
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(9)
+        );
+    }
+}
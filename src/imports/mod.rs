@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
@@ -12,10 +12,8 @@ pub struct ImportInfo {
     /// The module name being imported
     pub module_name: String,
     /// The file where this import was found
-    #[allow(dead_code)]
     pub file_path: String,
     /// The line number where the import was found
-    #[allow(dead_code)]
     pub line_number: usize,
 }
 
@@ -87,14 +85,35 @@ pub async fn execute(location: bool, package: Option<String>, verbose: bool) ->
 
 /// Extract all import statements from PureScript source files
 pub fn extract_imports_from_sources(dir: &Path) -> Result<Vec<ImportInfo>> {
+    extract_imports_from_subdirectory(dir, "src")
+}
+
+/// Extract all import statements from the PureScript files matching any of
+/// the globs, such as a test suite's sources
+pub fn extract_imports_from_globs(globs: &[String]) -> Result<Vec<ImportInfo>> {
+    let mut files = BTreeSet::new();
+    for pattern in globs {
+        for entry in glob::glob(pattern).context("Failed to read glob pattern")? {
+            files.insert(entry.context("Failed to read glob entry")?);
+        }
+    }
+
     let mut imports = Vec::new();
-    let src_dir = dir.join("src");
+    for file in files {
+        extract_imports_from_file(&file, &mut imports)?;
+    }
+    Ok(imports)
+}
+
+fn extract_imports_from_subdirectory(dir: &Path, subdirectory: &str) -> Result<Vec<ImportInfo>> {
+    let mut imports = Vec::new();
+    let sources_dir = dir.join(subdirectory);
 
-    if !src_dir.exists() {
+    if !sources_dir.exists() {
         return Ok(imports);
     }
 
-    extract_imports_from_directory(&src_dir, &mut imports)?;
+    extract_imports_from_directory(&sources_dir, &mut imports)?;
 
     Ok(imports)
 }
@@ -182,6 +201,28 @@ fn extract_module_name_from_import(import_line: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_imports_from_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let e2e = dir.path().join("e2e");
+        fs::create_dir_all(&e2e).unwrap();
+        fs::write(
+            e2e.join("Main.purs"),
+            "module Test.E2E where\n\nimport Prelude\nimport Test.Spec (describe)\n",
+        )
+        .unwrap();
+        let glob = format!("{}/**/*.purs", e2e.display());
+
+        // A file matched by more than one glob is only read once
+        let imports = extract_imports_from_globs(&[glob.clone(), glob]).unwrap();
+
+        let modules: Vec<(&str, usize)> = imports
+            .iter()
+            .map(|import| (import.module_name.as_str(), import.line_number))
+            .collect();
+        assert_eq!(modules, vec![("Prelude", 3), ("Test.Spec", 4)]);
+    }
+
     #[test]
     fn test_extract_module_name_from_import() {
         // Basic import
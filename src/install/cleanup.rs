@@ -2,7 +2,8 @@ use anyhow::Result;
 use std::collections::HashSet;
 use std::fs;
 
-use crate::config::SpagoConfig;
+use crate::config::{load_config, SpagoConfig};
+use crate::install::lock::local_bench_dependencies;
 use crate::install::InstallManager;
 use crate::registry::{PackageName, PackageQuery, PackageSet};
 
@@ -24,12 +25,27 @@ pub fn cleanup_unused_packages(
 
     let manager = InstallManager::new(&spago_dir)?;
 
+    // Test dependencies are installed too, so they're recorded in spago.lock
     let mut dependencies_to_keep: Vec<PackageName> =
-        config.package_dependencies().into_iter().cloned().collect();
+        config.all_dependencies().into_iter().cloned().collect();
 
     let all_local_deps: Vec<PackageName> = query.all_workspace_dependencies();
 
     dependencies_to_keep.extend(all_local_deps);
+    dependencies_to_keep.extend(query.all_workspace_test_dependencies());
+
+    // Bench dependencies are locked too, so `ragu bench` doesn't have to
+    // fetch them again after every install
+    let root_config = if config.is_workspace_root() {
+        config.clone()
+    } else {
+        load_config(config.workspace_root.join("spago.yaml"), false)?
+    };
+    dependencies_to_keep.extend(config.bench_dependencies().into_iter().cloned());
+    dependencies_to_keep.extend(root_config.bench_dependencies().into_iter().cloned());
+    for local_package in query.local_packages() {
+        dependencies_to_keep.extend(local_bench_dependencies(local_package));
+    }
     dependencies_to_keep.sort_unstable();
     dependencies_to_keep.dedup();
 
@@ -73,3 +89,49 @@ pub fn cleanup_unused_packages(
 
     Ok(removed_packages)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use crate::registry::{Package, RegistryPackage};
+
+    fn registry(name: &str) -> (PackageName, Package) {
+        (
+            PackageName::new(name),
+            Package::Registry(RegistryPackage {
+                name: PackageName::new(name),
+                version: "1.0.0".to_string(),
+                dependencies: vec![],
+            }),
+        )
+    }
+
+    #[test]
+    fn test_cleanup_keeps_bench_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("spago.yaml"),
+            "workspace: {}\npackage:\n  name: app\n  dependencies: [prelude]\n  bench:\n    main: Bench.Main\n    dependencies: [minibench]\n",
+        )
+        .unwrap();
+        for package in ["prelude", "minibench", "unused"] {
+            fs::create_dir_all(root.join(".spago").join(package)).unwrap();
+        }
+        let config = load_config(root.join("spago.yaml"), false).unwrap();
+        let package_set: PackageSet = [
+            registry("prelude"),
+            registry("minibench"),
+            registry("unused"),
+        ]
+        .into_iter()
+        .collect();
+
+        let removed = cleanup_unused_packages(&config, &package_set).unwrap();
+
+        assert_eq!(removed, vec!["unused"]);
+        assert!(root.join(".spago/minibench").is_dir());
+        assert!(root.join(".spago/prelude").is_dir());
+    }
+}
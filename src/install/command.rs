@@ -2,15 +2,21 @@ use anyhow::{Context, Result};
 use colored::Colorize;
 use std::path::PathBuf;
 
+use crate::clean::prune_stale_output;
 use crate::config::{add_packages_to_config, load_config_cwd};
-use crate::install::{cleanup_unused_packages, install_all_dependencies};
+use crate::install::{cleanup_unused_packages, install_locked};
 use crate::registry::{PackageName, PackageQuery, PackageSet};
 
 /// Execute the install command
-pub async fn execute(packages: &[String], package_set: &PackageSet, verbose: bool) -> Result<()> {
+pub async fn execute(
+    packages: &[String],
+    package_set: &PackageSet,
+    frozen: bool,
+    verbose: bool,
+) -> Result<()> {
     if packages.is_empty() {
         // Install all dependencies from spago.yaml
-        install_all_from_config(verbose).await
+        install_all_from_config(frozen, verbose).await
     } else {
         // Install specific packages
         install_specific_packages(packages, package_set, verbose).await
@@ -18,7 +24,7 @@ pub async fn execute(packages: &[String], package_set: &PackageSet, verbose: boo
 }
 
 /// Install all dependencies from spago.yaml
-async fn install_all_from_config(verbose: bool) -> Result<()> {
+async fn install_all_from_config(frozen: bool, verbose: bool) -> Result<()> {
     let config = load_config_cwd().context("Failed to load spago.yaml. Run 'init' first.")?;
 
     if verbose {
@@ -28,12 +34,15 @@ async fn install_all_from_config(verbose: bool) -> Result<()> {
     // Load package set
     let package_set = config.package_set().await?;
 
-    // Install all dependencies
-    let result = install_all_dependencies(&config, &package_set, false).await?;
+    // Install all dependencies and update spago.lock
+    let result = install_locked(&config, &package_set, frozen).await?;
 
     // Clean up unused packages
     let removed_packages = cleanup_unused_packages(&config, &package_set)?;
 
+    // Drop compiled modules from removed or updated packages
+    let removed_modules = prune_stale_output(&config, &package_set, false, verbose)?;
+
     print!("\r\x1B[K"); // Clear current line that contained the install progress
 
     // Report results
@@ -76,6 +85,12 @@ async fn install_all_from_config(verbose: bool) -> Result<()> {
                     removed_packages.len().to_string().yellow()
                 );
             }
+            if !removed_modules.is_empty() {
+                println!(
+                    "  Removed {} stale modules from output",
+                    removed_modules.len().to_string().yellow()
+                );
+            }
         }
     } else {
         println!("{} Installation failed:", "✗".red().bold());
@@ -115,7 +130,7 @@ async fn install_specific_packages(
         .context("Failed to update spago.yaml")?;
 
     // Install packages with all their dependencies
-    install_all_from_config(verbose).await?;
+    install_all_from_config(false, verbose).await?;
 
     Ok(())
 }
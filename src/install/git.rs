@@ -3,6 +3,7 @@ use std::fs;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+use super::lock::COMMIT_FILE;
 use crate::registry::{PackageName, PackageSetPackage};
 
 /// Information about a fetched package
@@ -77,7 +78,7 @@ pub fn fetch_package(package: &PackageSetPackage, spago_dir: &Path) -> Result<Pa
     };
 
     // Try to checkout the reference (could be tag, branch, or commit)
-    let (_object, _reference) = repo
+    let (object, _reference) = repo
         .revparse_ext(&package.version)
         .and_then(|result| {
             // If revparse succeeds, try to checkout the files
@@ -95,9 +96,19 @@ pub fn fetch_package(package: &PackageSetPackage, spago_dir: &Path) -> Result<Pa
             package.version, package_name.0, package.repo
         ))?;
 
+    let commit = object
+        .peel_to_commit()
+        .context(format!(
+            "Ref '{}' for {} is not a commit",
+            package.version, package_name.0
+        ))?
+        .id();
+
     // Prune the package to only keep README and src folders
     prune_package(&package_dir)?;
     add_version_file(&package_dir, &package.version)?;
+    fs::write(package_dir.join(COMMIT_FILE), commit.to_string())
+        .context("Failed to write commit file")?;
 
     Ok(PackageInfo {
         name: package_name,
@@ -125,7 +136,8 @@ pub fn prune_package(package_dir: &Path) -> Result<()> {
             || file_name == "README"
             || file_name == "readme"
             || file_name == "spago.yaml"
-            || file_name == "version.txt";
+            || file_name == "version.txt"
+            || file_name == COMMIT_FILE;
 
         if !should_keep {
             if entry_path.is_dir() {
@@ -139,6 +151,13 @@ pub fn prune_package(package_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// The commit HEAD points to when `package_dir` is itself a git checkout
+pub fn checked_out_commit(package_dir: &Path) -> Option<String> {
+    let repo = git2::Repository::open(package_dir).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
 fn add_version_file(package_dir: &Path, version: &str) -> Result<()> {
     let version_file = package_dir.join("version.txt");
     fs::write(version_file, version).context("Failed to write version file")?;
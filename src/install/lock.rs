@@ -0,0 +1,921 @@
+//! `spago.lock`: what every package in the workspace resolved to when it
+//! was last installed, so installs can be reproduced and checked in CI.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::config::{PackageSetConfig, SpagoConfig, WORKSPACE_ROOT_PACKAGE};
+use crate::registry::{LocalPackage, Package, PackageName, PackageQuery, PackageSet};
+
+pub const LOCKFILE: &str = "spago.lock";
+/// Written next to `version.txt` when a git package is fetched
+pub const COMMIT_FILE: &str = "commit.txt";
+/// Written into a registry package when its tarball is downloaded
+pub const INTEGRITY_FILE: &str = "integrity.txt";
+
+/// The lockfile, laid out like spago's. Locks written by spago can be read too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub workspace: LockWorkspace,
+    /// Every dependency of the workspace's packages, including transitive
+    /// and test dependencies
+    pub packages: BTreeMap<PackageName, LockedPackage>,
+}
+
+/// The parts of the spago.yaml files the lock was resolved from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockWorkspace {
+    pub packages: BTreeMap<PackageName, WorkspacePackage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_set: Option<LockPackageSet>,
+    /// As written in spago.yaml, which spago allows more shapes of than ragu
+    #[serde(default)]
+    pub extra_packages: BTreeMap<PackageName, serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockPackageSet {
+    pub address: PackageSetConfig,
+}
+
+/// A package in the workspace itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspacePackage {
+    /// Relative to the workspace root
+    pub path: String,
+    #[serde(default)]
+    pub core: LockDependencies,
+    #[serde(default)]
+    pub test: LockDependencies,
+    /// `package.bench.dependencies`, which spago doesn't lock
+    #[serde(default, skip_serializing_if = "LockDependencies::is_empty")]
+    pub bench: LockDependencies,
+}
+
+/// spago also writes a `build_plan`, which ragu doesn't need
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockDependencies {
+    #[serde(default, deserialize_with = "dependency_names")]
+    pub dependencies: Vec<PackageName>,
+}
+
+impl LockDependencies {
+    fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+}
+
+/// What a dependency resolved to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LockedPackage {
+    Registry {
+        version: String,
+        /// `sha256-<base64>` of the registry tarball
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        integrity: Option<String>,
+        #[serde(default, deserialize_with = "dependency_names")]
+        dependencies: Vec<PackageName>,
+    },
+    Git {
+        url: String,
+        /// The ref from the package set or extraPackages. spago only
+        /// records the commit.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        /// The commit the ref pointed to
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+        #[serde(default, deserialize_with = "dependency_names")]
+        dependencies: Vec<PackageName>,
+    },
+    Local {
+        path: String,
+        #[serde(default, deserialize_with = "dependency_names")]
+        dependencies: Vec<PackageName>,
+    },
+}
+
+impl LockedPackage {
+    /// How a package set entry is locked, before anything is known about
+    /// what fetching it downloads
+    pub fn from_package(package: &Package, workspace_root: &Path) -> Self {
+        match package {
+            Package::Registry(package) => LockedPackage::Registry {
+                version: package.version.clone(),
+                integrity: None,
+                dependencies: package.dependencies.clone(),
+            },
+            Package::Remote(package) => LockedPackage::Git {
+                url: package.repo.clone(),
+                version: Some(package.version.clone()),
+                rev: None,
+                dependencies: package.dependencies.clone(),
+            },
+            Package::Local(package) => LockedPackage::Local {
+                path: relative_path(&package.path, workspace_root),
+                dependencies: package.dependencies.clone(),
+            },
+        }
+    }
+
+    /// Fill in the commit or tarball hash recorded when the package was
+    /// fetched into `package_dir`
+    pub fn read_installed(mut self, package_dir: &Path) -> Self {
+        let read = |file: &str| {
+            fs::read_to_string(package_dir.join(file))
+                .ok()
+                .map(|contents| contents.trim().to_string())
+                .filter(|contents| !contents.is_empty())
+        };
+        match &mut self {
+            LockedPackage::Registry { integrity, .. } => *integrity = read(INTEGRITY_FILE),
+            LockedPackage::Git { rev, .. } => *rev = read(COMMIT_FILE),
+            LockedPackage::Local { .. } => {}
+        }
+        self
+    }
+
+    pub fn hash(&self) -> Option<&String> {
+        match self {
+            LockedPackage::Registry { integrity, .. } => integrity.as_ref(),
+            LockedPackage::Git { rev, .. } => rev.as_ref(),
+            LockedPackage::Local { .. } => None,
+        }
+    }
+
+    /// Set the commit or tarball hash, recording it alongside the package
+    /// in each of `package_dirs` that exists so it's only looked up once
+    pub fn record_hash(&mut self, hash: String, package_dirs: &[PathBuf]) -> Result<()> {
+        let (field, file) = match self {
+            LockedPackage::Registry { integrity, .. } => (integrity, INTEGRITY_FILE),
+            LockedPackage::Git { rev, .. } => (rev, COMMIT_FILE),
+            LockedPackage::Local { .. } => return Ok(()),
+        };
+        for package_dir in package_dirs.iter().filter(|dir| dir.is_dir()) {
+            fs::write(package_dir.join(file), &hash).context(format!(
+                "Failed to write {}",
+                package_dir.join(file).display()
+            ))?;
+        }
+        *field = Some(hash);
+        Ok(())
+    }
+
+    fn without_hash(&self) -> Self {
+        let mut package = self.clone();
+        match &mut package {
+            LockedPackage::Registry { integrity, .. } => *integrity = None,
+            LockedPackage::Git { rev, .. } => *rev = None,
+            LockedPackage::Local { .. } => {}
+        }
+        package
+    }
+
+    /// Without its hash, and with paths and dependencies in the same form
+    /// whether ragu or spago wrote it
+    fn comparable(&self) -> Self {
+        let mut package = self.without_hash();
+        match &mut package {
+            LockedPackage::Registry { dependencies, .. }
+            | LockedPackage::Git { dependencies, .. } => dependencies.sort(),
+            LockedPackage::Local { path, dependencies } => {
+                *path = normalize_path(path);
+                dependencies.sort();
+            }
+        }
+        package
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            LockedPackage::Registry { version, .. } => version.clone(),
+            LockedPackage::Git {
+                url, version, rev, ..
+            } => match version.as_ref().or(rev.as_ref()) {
+                Some(version) => format!("{}#{}", url, version),
+                None => url.clone(),
+            },
+            LockedPackage::Local { path, .. } => path.clone(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Lock the workspace the root config belongs to against a package set.
+    /// Hashes are left empty until the packages are installed.
+    pub fn resolve(root_config: &SpagoConfig, package_set: &PackageSet) -> Result<Self> {
+        let workspace_root = &root_config.workspace_root;
+        let extra_packages = &root_config.workspace.extra_packages;
+
+        let mut workspace_packages = BTreeMap::new();
+        if root_config.package.name.0 != WORKSPACE_ROOT_PACKAGE {
+            workspace_packages.insert(
+                root_config.package.name.clone(),
+                WorkspacePackage {
+                    path: "./".to_string(),
+                    core: LockDependencies {
+                        dependencies: root_config.package.dependencies.clone(),
+                    },
+                    test: LockDependencies {
+                        dependencies: root_config
+                            .test_dependencies()
+                            .into_iter()
+                            .cloned()
+                            .collect(),
+                    },
+                    bench: LockDependencies {
+                        dependencies: root_config
+                            .bench_dependencies()
+                            .into_iter()
+                            .cloned()
+                            .collect(),
+                    },
+                },
+            );
+        }
+        let query = PackageQuery::new(package_set);
+        for package in query.local_packages() {
+            // Local extraPackages are dependencies, not part of the workspace
+            if extra_packages.contains_key(&package.name) {
+                continue;
+            }
+            workspace_packages.insert(
+                package.name.clone(),
+                WorkspacePackage {
+                    path: relative_path(&package.path, workspace_root),
+                    core: LockDependencies {
+                        dependencies: package.dependencies.clone(),
+                    },
+                    test: LockDependencies {
+                        dependencies: package.test_dependencies.clone(),
+                    },
+                    bench: LockDependencies {
+                        dependencies: local_bench_dependencies(package),
+                    },
+                },
+            );
+        }
+
+        let mut dependencies = BTreeSet::new();
+        for package in workspace_packages.values() {
+            for name in package
+                .core
+                .dependencies
+                .iter()
+                .chain(&package.test.dependencies)
+                .chain(&package.bench.dependencies)
+            {
+                collect_dependencies(name, &query, &mut dependencies)?;
+            }
+        }
+        let packages = dependencies
+            .into_iter()
+            .filter(|name| !workspace_packages.contains_key(name))
+            .filter_map(|name| {
+                let package = query.get(&name)?;
+                Some((name, LockedPackage::from_package(package, workspace_root)))
+            })
+            .collect();
+
+        Ok(Lockfile {
+            workspace: LockWorkspace {
+                packages: workspace_packages,
+                package_set: root_config
+                    .workspace
+                    .package_set
+                    .clone()
+                    .map(|address| LockPackageSet { address }),
+                extra_packages: extra_packages
+                    .iter()
+                    .map(|(name, package)| Ok((name.clone(), serde_yaml::to_value(package)?)))
+                    .collect::<Result<_>>()?,
+            },
+            packages,
+        })
+    }
+
+    /// Every direct dependency of the workspace's packages
+    pub fn workspace_dependencies(&self) -> Vec<PackageName> {
+        let dependencies: BTreeSet<&PackageName> = self
+            .workspace
+            .packages
+            .values()
+            .flat_map(|package| {
+                package
+                    .core
+                    .dependencies
+                    .iter()
+                    .chain(&package.test.dependencies)
+                    .chain(&package.bench.dependencies)
+            })
+            .collect();
+        dependencies.into_iter().cloned().collect()
+    }
+
+    pub fn load(workspace_root: &Path) -> Result<Option<Self>> {
+        let path = workspace_root.join(LOCKFILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path).context("Failed to read spago.lock")?;
+        let lockfile = serde_yaml::from_str(&contents).context("Failed to parse spago.lock")?;
+        Ok(Some(lockfile))
+    }
+
+    /// Write the lockfile, returning false when it was already up to date
+    pub fn save(&self, workspace_root: &Path) -> Result<bool> {
+        let path = workspace_root.join(LOCKFILE);
+        let contents = serde_yaml::to_string(self).context("Failed to serialize spago.lock")?;
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == contents) {
+            return Ok(false);
+        }
+        fs::write(&path, contents).context("Failed to write spago.lock")?;
+        Ok(true)
+    }
+
+    /// Use the installed packages' hashes, keeping the previous lock's for
+    /// unchanged packages that were installed before hashes were recorded
+    pub fn with_hashes(
+        mut self,
+        installed: &BTreeMap<PackageName, LockedPackage>,
+        previous: Option<&Lockfile>,
+    ) -> Self {
+        for (name, package) in self.packages.iter_mut() {
+            let hashed = installed
+                .get(name)
+                .filter(|installed| installed.hash().is_some())
+                .or_else(|| previous.and_then(|previous| previous.packages.get(name)));
+            if let Some(hashed) = hashed {
+                if hashed.without_hash() == *package {
+                    *package = hashed.clone();
+                }
+            }
+        }
+        self
+    }
+
+    /// How this lock differs from one resolved from the current spago.yaml
+    /// files, ignoring hashes
+    pub fn changes(&self, resolved: &Lockfile) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        let workspace_packages = |lockfile: &Lockfile| -> BTreeMap<PackageName, WorkspacePackage> {
+            lockfile
+                .workspace
+                .packages
+                .iter()
+                .map(|(name, package)| {
+                    let mut package = package.clone();
+                    package.path = normalize_path(&package.path);
+                    package.core.dependencies.sort();
+                    package.test.dependencies.sort();
+                    package.bench.dependencies.sort();
+                    (name.clone(), package)
+                })
+                .collect()
+        };
+        let workspace = (workspace_packages(self), workspace_packages(resolved));
+        for (name, change) in map_changes(&workspace.0, &workspace.1, |_, _| None) {
+            changes.push(format!("workspace package {} {}", name.0, change));
+        }
+        if self.workspace.package_set != resolved.workspace.package_set {
+            changes.push("package set changed".to_string());
+        }
+        if self.workspace.extra_packages != resolved.workspace.extra_packages {
+            changes.push("extraPackages changed".to_string());
+        }
+
+        let comparable = |lockfile: &Lockfile| -> BTreeMap<PackageName, LockedPackage> {
+            lockfile
+                .packages
+                .iter()
+                .map(|(name, package)| (name.clone(), package.comparable()))
+                .collect()
+        };
+        let mut locked = comparable(self);
+        let resolved = comparable(resolved);
+        // A git package locked by spago is pinned by its commit alone, which
+        // is checked once it's installed
+        for (name, package) in locked.iter_mut() {
+            if let (
+                LockedPackage::Git {
+                    url,
+                    version: version @ None,
+                    ..
+                },
+                Some(LockedPackage::Git {
+                    url: resolved_url,
+                    version: resolved_version,
+                    ..
+                }),
+            ) = (package, resolved.get(name))
+            {
+                if url == resolved_url {
+                    *version = resolved_version.clone();
+                }
+            }
+        }
+        let describe = |old: &LockedPackage, new: &LockedPackage| {
+            (old.describe() != new.describe())
+                .then(|| format!("{} -> {}", old.describe(), new.describe()))
+        };
+        for (name, change) in map_changes(&locked, &resolved, describe) {
+            changes.push(format!("{} {}", name.0, change));
+        }
+        changes
+    }
+
+    /// Packages whose installed commit or tarball doesn't match the lock, or
+    /// that can't be checked because either side has no hash
+    pub fn mismatched_hashes(
+        &self,
+        installed: &BTreeMap<PackageName, LockedPackage>,
+    ) -> Vec<String> {
+        self.packages
+            .iter()
+            .filter(|(_, locked)| !matches!(locked, LockedPackage::Local { .. }))
+            .filter_map(|(name, locked)| {
+                let found = installed.get(name).and_then(LockedPackage::hash);
+                match (locked.hash(), found) {
+                    (None, _) => Some(format!("{} has no hash in spago.lock", name.0)),
+                    (Some(_), None) => Some(format!("{} couldn't be verified", name.0)),
+                    (Some(expected), Some(found)) if expected != found => Some(format!(
+                        "{} is {}, spago.lock has {}",
+                        name.0, found, expected
+                    )),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A workspace package's bench dependencies, which its package set entry
+/// doesn't carry, read from its spago.yaml
+pub fn local_bench_dependencies(package: &LocalPackage) -> Vec<PackageName> {
+    crate::config::load_config(package.path.join("spago.yaml"), true)
+        .map(|config| config.bench_dependencies().into_iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Added, removed and changed entries between two maps. `describe` can
+/// explain a change, otherwise it's just reported as changed.
+fn map_changes<'a, T: PartialEq>(
+    old: &'a BTreeMap<PackageName, T>,
+    new: &'a BTreeMap<PackageName, T>,
+    describe: impl Fn(&T, &T) -> Option<String>,
+) -> Vec<(&'a PackageName, String)> {
+    let names: BTreeSet<&PackageName> = old.keys().chain(new.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| match (old.get(name), new.get(name)) {
+            (None, Some(_)) => Some((name, "added".to_string())),
+            (Some(_), None) => Some((name, "removed".to_string())),
+            (Some(old), Some(new)) if old != new => Some((
+                name,
+                describe(old, new).unwrap_or_else(|| "changed".to_string()),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collect_dependencies(
+    name: &PackageName,
+    query: &PackageQuery,
+    found: &mut BTreeSet<PackageName>,
+) -> Result<()> {
+    if !found.insert(name.clone()) {
+        return Ok(());
+    }
+    let package = query
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Package '{}' not found in package set", name.0))?;
+    for dependency in package.dependencies() {
+        collect_dependencies(dependency, query, found)?;
+    }
+    Ok(())
+}
+
+/// spago writes dependencies as bare names or as `name: range` maps, ranges
+/// being for publishing, which ragu doesn't do
+fn dependency_names<'de, D>(deserializer: D) -> std::result::Result<Vec<PackageName>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Dependency {
+        Name(PackageName),
+        WithRange(BTreeMap<PackageName, serde_yaml::Value>),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Dependencies {
+        List(Vec<Dependency>),
+        WithRanges(BTreeMap<PackageName, serde_yaml::Value>),
+    }
+
+    Ok(match Dependencies::deserialize(deserializer)? {
+        Dependencies::List(dependencies) => dependencies
+            .into_iter()
+            .flat_map(|dependency| match dependency {
+                Dependency::Name(name) => vec![name],
+                Dependency::WithRange(names) => names.into_keys().collect(),
+            })
+            .collect(),
+        Dependencies::WithRanges(names) => names.into_keys().collect(),
+    })
+}
+
+/// `./lib/`, `lib` and `./lib` are the same package
+fn normalize_path(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if path.is_empty() || path == "." {
+        ".".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// A path relative to the workspace root, the same from any package in it
+fn relative_path(path: &Path, workspace_root: &Path) -> String {
+    path.strip_prefix(workspace_root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{LocalPackage, PackageSetPackage, RegistryPackage};
+
+    fn registry(name: &str, version: &str, dependencies: &[&str]) -> (PackageName, Package) {
+        (
+            PackageName::new(name),
+            Package::Registry(RegistryPackage {
+                name: PackageName::new(name),
+                version: version.to_string(),
+                dependencies: dependencies.iter().map(|d| PackageName::new(d)).collect(),
+            }),
+        )
+    }
+
+    fn workspace(root: &Path) -> (SpagoConfig, PackageSet) {
+        fs::write(
+            root.join("spago.yaml"),
+            "workspace:\n  packageSet:\n    registry: 62.1.0\npackage:\n  name: app\n  dependencies: [lib, console]\n  test:\n    main: Test.Main\n    dependencies: [spec]\n",
+        )
+        .unwrap();
+        let config = crate::config::load_config(root.join("spago.yaml"), false).unwrap();
+
+        let package_set: PackageSet = [
+            registry("prelude", "6.0.1", &[]),
+            registry("console", "6.1.0", &["prelude"]),
+            registry("arrays", "7.3.0", &["prelude"]),
+            (
+                PackageName::new("spec"),
+                Package::Remote(PackageSetPackage {
+                    name: PackageName::new("spec"),
+                    dependencies: vec![PackageName::new("prelude")],
+                    repo: "https://github.com/purescript-spec/purescript-spec.git".to_string(),
+                    version: "v8.1.1".to_string(),
+                }),
+            ),
+            (
+                PackageName::new("lib"),
+                Package::Local(LocalPackage {
+                    name: PackageName::new("lib"),
+                    dependencies: vec![PackageName::new("arrays")],
+                    test_dependencies: vec![],
+                    path: root.join("lib"),
+                }),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        (config, package_set)
+    }
+
+    #[test]
+    fn test_resolve_locks_bench_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, mut package_set) = workspace(dir.path());
+        fs::write(
+            dir.path().join("spago.yaml"),
+            "workspace:\n  packageSet:\n    registry: 62.1.0\npackage:\n  name: app\n  dependencies: [console]\n  bench:\n    main: Bench.Main\n    dependencies: [minibench]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("lib")).unwrap();
+        fs::write(
+            dir.path().join("lib/spago.yaml"),
+            "package:\n  name: lib\n  dependencies: [arrays]\n  bench:\n    main: Bench.Lib\n    dependencies: [benchotron]\n",
+        )
+        .unwrap();
+        let config = crate::config::load_config(dir.path().join("spago.yaml"), false).unwrap();
+        package_set.extend([
+            registry("minibench", "4.0.1", &["console"]),
+            registry("benchotron", "10.0.0", &["arrays"]),
+        ]);
+
+        let lock = Lockfile::resolve(&config, &package_set).unwrap();
+
+        let bench = |name: &str| {
+            lock.workspace.packages[&PackageName::new(name)]
+                .bench
+                .dependencies
+                .clone()
+        };
+        assert_eq!(bench("app"), vec![PackageName::new("minibench")]);
+        assert_eq!(bench("lib"), vec![PackageName::new("benchotron")]);
+        assert!(lock.packages.contains_key(&PackageName::new("minibench")));
+        assert!(lock.packages.contains_key(&PackageName::new("benchotron")));
+        assert!(lock
+            .workspace_dependencies()
+            .contains(&PackageName::new("benchotron")));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let (config, package_set) = workspace(dir.path());
+
+        let lock = Lockfile::resolve(&config, &package_set).unwrap();
+
+        let workspace: Vec<(&str, &str)> = lock
+            .workspace
+            .packages
+            .iter()
+            .map(|(name, package)| (name.0.as_str(), package.path.as_str()))
+            .collect();
+        assert_eq!(workspace, vec![("app", "./"), ("lib", "lib")]);
+        assert_eq!(
+            lock.workspace.packages[&PackageName::new("app")]
+                .test
+                .dependencies,
+            vec![PackageName::new("spec")]
+        );
+
+        // Transitive and test dependencies, but not workspace packages
+        let packages: Vec<&str> = lock.packages.keys().map(|name| name.0.as_str()).collect();
+        assert_eq!(packages, vec!["arrays", "console", "prelude", "spec"]);
+        assert_eq!(
+            lock.packages[&PackageName::new("spec")],
+            LockedPackage::Git {
+                url: "https://github.com/purescript-spec/purescript-spec.git".to_string(),
+                version: Some("v8.1.1".to_string()),
+                rev: None,
+                dependencies: vec![PackageName::new("prelude")],
+            }
+        );
+        assert_eq!(
+            lock.workspace_dependencies(),
+            ["arrays", "console", "lib", "spec"].map(PackageName::new)
+        );
+    }
+
+    /// Written by spago for the same workspace as `workspace`
+    const SPAGO_LOCK: &str = r#"workspace:
+  packages:
+    app:
+      path: ./
+      core:
+        dependencies:
+          - console: ">=6.1.0 <7.0.0"
+          - lib
+        build_plan:
+          - arrays
+          - console
+          - prelude
+      test:
+        dependencies:
+          - spec
+        build_plan:
+          - prelude
+          - spec
+    lib:
+      path: lib
+      core:
+        dependencies:
+          - arrays: ">=7.3.0 <8.0.0"
+        build_plan:
+          - arrays
+          - prelude
+      test:
+        dependencies: []
+        build_plan: []
+  package_set:
+    address:
+      registry: 62.1.0
+    compiler: ">=0.15.15 <0.16.0"
+    content:
+      arrays: 7.3.0
+      console: 6.1.0
+      prelude: 6.0.1
+  extra_packages: {}
+packages:
+  arrays:
+    type: registry
+    version: 7.3.0
+    integrity: sha256-7hJqbVm9Zk4pRF+wXbOcHeoAuzOSAe9ysfD7YkC2YCw=
+    dependencies:
+      - prelude
+  console:
+    type: registry
+    version: 6.1.0
+    integrity: sha256-CxmAzjgyuGDmt9FZW51VhV6rBPwR6o0YeKUzA9rSzcM=
+    dependencies:
+      - prelude
+  prelude:
+    type: registry
+    version: 6.0.1
+    integrity: sha256-bbhoa+8X5ikRC9MrCxZ6ScBlwvu7ul0aFM6j9cn3Fdc=
+    dependencies: []
+  spec:
+    type: git
+    url: https://github.com/purescript-spec/purescript-spec.git
+    rev: 3b3d9ea3ebbe3d6f3c2f9c2d3f3e8e1d4f27a5c1
+    dependencies:
+      - prelude
+"#;
+
+    #[test]
+    fn test_reads_spago_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let (config, package_set) = workspace(dir.path());
+        fs::write(dir.path().join(LOCKFILE), SPAGO_LOCK).unwrap();
+
+        let lock = Lockfile::load(dir.path()).unwrap().unwrap();
+        assert_eq!(
+            lock.workspace.packages[&PackageName::new("app")]
+                .core
+                .dependencies,
+            vec![PackageName::new("console"), PackageName::new("lib")]
+        );
+        assert_eq!(
+            lock.packages[&PackageName::new("spec")].hash(),
+            Some(&"3b3d9ea3ebbe3d6f3c2f9c2d3f3e8e1d4f27a5c1".to_string())
+        );
+
+        // Nothing in spago.yaml changed since spago wrote it
+        let resolved = Lockfile::resolve(&config, &package_set).unwrap();
+        assert_eq!(lock.changes(&resolved), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (config, package_set) = workspace(dir.path());
+        let lock = Lockfile::resolve(&config, &package_set).unwrap();
+
+        assert_eq!(Lockfile::load(dir.path()).unwrap(), None);
+        assert!(lock.save(dir.path()).unwrap());
+        assert!(!lock.save(dir.path()).unwrap());
+        assert_eq!(Lockfile::load(dir.path()).unwrap(), Some(lock));
+
+        let contents = fs::read_to_string(dir.path().join(LOCKFILE)).unwrap();
+        assert!(contents.contains("type: registry"));
+        assert!(contents.contains("registry: 62.1.0"));
+    }
+
+    #[test]
+    fn test_read_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(COMMIT_FILE), "abc123\n").unwrap();
+        let package = LockedPackage::Git {
+            url: "https://example.com/a.git".to_string(),
+            version: Some("main".to_string()),
+            rev: None,
+            dependencies: vec![],
+        };
+
+        assert_eq!(
+            package.clone().read_installed(dir.path()).hash(),
+            Some(&"abc123".to_string())
+        );
+        let registry = LockedPackage::Registry {
+            version: "1.0.0".to_string(),
+            integrity: None,
+            dependencies: vec![],
+        };
+        let mut registry = registry.read_installed(dir.path());
+        assert_eq!(registry.hash(), None);
+
+        let missing = dir.path().join("missing");
+        registry
+            .record_hash(
+                "sha256-abc".to_string(),
+                &[dir.path().to_path_buf(), missing.clone()],
+            )
+            .unwrap();
+        assert_eq!(registry.hash(), Some(&"sha256-abc".to_string()));
+        assert!(!missing.exists());
+        assert_eq!(
+            registry.without_hash().read_installed(dir.path()).hash(),
+            Some(&"sha256-abc".to_string())
+        );
+    }
+
+    #[test]
+    fn test_with_hashes_keeps_previous_for_unchanged_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let (config, package_set) = workspace(dir.path());
+        let resolved = Lockfile::resolve(&config, &package_set).unwrap();
+
+        let hashed = |package: &LockedPackage, hash: &str| {
+            let mut package = package.clone();
+            match &mut package {
+                LockedPackage::Registry { integrity, .. } => *integrity = Some(hash.to_string()),
+                LockedPackage::Git { rev, .. } => *rev = Some(hash.to_string()),
+                LockedPackage::Local { .. } => {}
+            }
+            package
+        };
+        let prelude = PackageName::new("prelude");
+        let spec = PackageName::new("spec");
+
+        let mut previous = resolved.clone();
+        previous.packages.insert(
+            prelude.clone(),
+            hashed(&resolved.packages[&prelude], "sha256-old"),
+        );
+        let installed = BTreeMap::from([(spec.clone(), hashed(&resolved.packages[&spec], "abc"))]);
+
+        let lock = resolved.clone().with_hashes(&installed, Some(&previous));
+        assert_eq!(lock.packages[&spec].hash(), Some(&"abc".to_string()));
+        assert_eq!(
+            lock.packages[&prelude].hash(),
+            Some(&"sha256-old".to_string())
+        );
+        assert!(lock.changes(&resolved).is_empty());
+
+        let mut moved = lock.clone();
+        moved
+            .packages
+            .insert(spec.clone(), hashed(&resolved.packages[&spec], "def"));
+        assert_eq!(
+            lock.mismatched_hashes(&moved.packages),
+            vec![
+                "arrays has no hash in spago.lock".to_string(),
+                "console has no hash in spago.lock".to_string(),
+                "spec is def, spago.lock has abc".to_string(),
+            ]
+        );
+        moved
+            .packages
+            .insert(spec.clone(), resolved.packages[&spec].clone());
+        assert_eq!(
+            lock.mismatched_hashes(&moved.packages)[2],
+            "spec couldn't be verified"
+        );
+    }
+
+    #[test]
+    fn test_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (config, mut package_set) = workspace(dir.path());
+        let lock = Lockfile::resolve(&config, &package_set).unwrap();
+
+        package_set.extend([registry("arrays", "7.4.0", &["prelude"])]);
+        if let Some(Package::Local(lib)) = package_set.get_mut(&PackageName::new("lib")) {
+            lib.dependencies.push(PackageName::new("maybe"));
+        }
+        package_set.extend([registry("maybe", "6.0.0", &["prelude"])]);
+        let mut config = config;
+        config.workspace.package_set = Some(PackageSetConfig {
+            url: None,
+            registry: Some("63.0.0".to_string()),
+        });
+
+        let resolved = Lockfile::resolve(&config, &package_set).unwrap();
+        assert_eq!(
+            lock.changes(&resolved),
+            vec![
+                "workspace package lib changed",
+                "package set changed",
+                "arrays 7.3.0 -> 7.4.0",
+                "maybe added",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path(Path::new("./lib"), Path::new(".")), "lib");
+        assert_eq!(
+            relative_path(Path::new("../../lib"), Path::new("../..")),
+            "lib"
+        );
+        assert_eq!(
+            relative_path(&PathBuf::from("../vendor/a"), Path::new(".")),
+            "../vendor/a"
+        );
+    }
+}
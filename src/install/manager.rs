@@ -1,7 +1,9 @@
 use anyhow::{Context, Result};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use colored::Colorize;
 use flate2::bufread::GzDecoder;
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -9,7 +11,8 @@ use std::sync::Arc;
 use tokio::task;
 
 use super::cache::{copy_dir_all, GlobalPackageCache};
-use super::git::{fetch_package, PackageInfo};
+use super::git::{checked_out_commit, fetch_package, PackageInfo};
+use super::lock::{LockedPackage, INTEGRITY_FILE};
 use crate::config::SpagoConfig;
 use crate::install::git::git_version_matches;
 use crate::registry::{
@@ -21,6 +24,9 @@ use crate::registry::{
 pub struct InstallResult {
     pub installed: Vec<InstalledPackage>,
     pub errors: Vec<String>,
+    /// What every required package resolved to, whether it was installed
+    /// now or already present
+    pub resolved: BTreeMap<PackageName, LockedPackage>,
 }
 
 #[derive(Debug, Clone)]
@@ -84,6 +90,7 @@ impl InstallManager {
         package_set: &PackageSet,
         config: &SpagoConfig,
         include_test_deps: bool,
+        extra_dependencies: &[PackageName],
     ) -> Result<InstallResult> {
         // Ensure .spago directory exists
         fs::create_dir_all(&self.spago_dir).context("Failed to create .spago directory")?;
@@ -118,6 +125,8 @@ impl InstallManager {
             }
         }
 
+        direct_package_dependencies.extend(extra_dependencies.iter().cloned());
+
         // Collect all packages to install (including dependencies)
         for package_name in direct_package_dependencies {
             self.collect_dependencies_recursive(
@@ -138,7 +147,8 @@ impl InstallManager {
         print!("\rStarting install...");
         std::io::stdout().flush().unwrap(); // Ensure output is shown immediately
 
-        for package_name in all_packages {
+        for package_name in &all_packages {
+            let package_name = package_name.clone();
             let package_set = package_set.clone();
             let spago_dir = spago_dir.clone();
             let global_cache = global_cache.clone();
@@ -189,7 +199,92 @@ impl InstallManager {
         print!("\r\x1B[K"); // Clear current line
         std::io::stdout().flush().unwrap(); // Ensure output is shown immediately
 
-        Ok(InstallResult { installed, errors })
+        let workspace_root = self.spago_dir.parent().unwrap_or(Path::new("."));
+        let resolved = all_packages
+            .into_iter()
+            .filter_map(|name| {
+                let package = LockedPackage::from_package(package_set.get(&name)?, workspace_root)
+                    .read_installed(&self.spago_dir.join(&name.0));
+                Some((name, package))
+            })
+            .collect();
+
+        Ok(InstallResult {
+            installed,
+            errors,
+            resolved,
+        })
+    }
+
+    /// Find the hashes of installed packages that don't have one, such as
+    /// those fetched before hashes were recorded. A git package that is
+    /// still a checkout has its HEAD recorded; anything else is fetched
+    /// again, so the hash is always that of what's installed. Packages that
+    /// can't be fetched are left without one.
+    pub async fn fill_missing_hashes(&self, resolved: &mut BTreeMap<PackageName, LockedPackage>) {
+        let lookups: Vec<_> = resolved
+            .iter()
+            .filter(|(_, package)| package.hash().is_none())
+            .filter_map(|(name, package)| {
+                let global_cache = self.global_cache.clone();
+                let spago_dir = self.spago_dir.clone();
+                let lookup = match package {
+                    LockedPackage::Registry {
+                        version,
+                        dependencies,
+                        ..
+                    } => {
+                        let package = RegistryPackage {
+                            name: name.clone(),
+                            version: version.clone(),
+                            dependencies: dependencies.clone(),
+                        };
+                        task::spawn(async move {
+                            refetch_registry_package(&package, &global_cache, &spago_dir).await
+                        })
+                    }
+                    LockedPackage::Git {
+                        url,
+                        version,
+                        dependencies,
+                        ..
+                    } => {
+                        let package = PackageSetPackage {
+                            name: name.clone(),
+                            dependencies: dependencies.clone(),
+                            repo: url.clone(),
+                            version: version.clone().unwrap_or_default(),
+                        };
+                        task::spawn_blocking(move || {
+                            installed_commit(&package, &global_cache, &spago_dir)
+                        })
+                    }
+                    LockedPackage::Local { .. } => return None,
+                };
+                Some((name.clone(), lookup))
+            })
+            .collect();
+
+        for (name, lookup) in lookups {
+            let package_dir = self.spago_dir.join(&name.0);
+            let recorded = lookup
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|hash| hash)
+                .and_then(|hash| match resolved.get_mut(&name) {
+                    Some(package) => match hash {
+                        Some(hash) => package.record_hash(hash, &[package_dir]),
+                        None => {
+                            *package = package.clone().read_installed(&package_dir);
+                            Ok(())
+                        }
+                    },
+                    None => Ok(()),
+                });
+            if let Err(e) = recorded {
+                eprintln!("⚠️  Couldn't find the hash of {}: {:#}", name.0, e);
+            }
+        }
     }
 
     /// Collect all dependencies recursively
@@ -284,6 +379,23 @@ async fn install_registry_package(
         })));
     }
 
+    download_registry_package(package, global_cache, spago_dir).await?;
+
+    Ok(Some(InstalledPackage::Registry(RegistryPackageInfo {
+        name: package.name.clone(),
+        version: package.version.clone(),
+    })))
+}
+
+/// Download a registry package's tarball, unpack it into `spago_dir` along
+/// with the tarball's hash, and cache it
+async fn download_registry_package(
+    package: &RegistryPackage,
+    global_cache: &GlobalPackageCache,
+    spago_dir: &Path,
+) -> Result<()> {
+    let package_dir = spago_dir.join(&package.name.0);
+
     // Download and extract the registry package
     let registry_tar_url = format!(
         "https://packages.registry.purescript.org/{}/{}.tar.gz",
@@ -305,6 +417,10 @@ async fn install_registry_package(
 
     // Create the package directory
     let tar_data = response.bytes().await?;
+    let integrity = format!(
+        "sha256-{}",
+        BASE64_STANDARD.encode(Sha256::digest(&tar_data))
+    );
     let cursor = std::io::Cursor::new(tar_data);
     let gz_data = GzDecoder::new(cursor);
     let mut tar = tar::Archive::new(gz_data);
@@ -367,13 +483,56 @@ async fn install_registry_package(
         }
     }
 
+    // Recorded for the lockfile, and kept with the package in the cache
+    fs::write(package_dir.join(INTEGRITY_FILE), &integrity).context(format!(
+        "Failed to write integrity file for {}",
+        package.name.0
+    ))?;
+
     // Cache the package for future use
     global_cache.cache_package(&package.name, &package.version, &package_dir)?;
 
-    Ok(Some(InstalledPackage::Registry(RegistryPackageInfo {
-        name: package.name.clone(),
-        version: package.version.clone(),
-    })))
+    Ok(())
+}
+
+/// Download a registry package again, replacing the installed and cached
+/// copies, so its integrity is that of the tarball that was unpacked
+async fn refetch_registry_package(
+    package: &RegistryPackage,
+    global_cache: &GlobalPackageCache,
+    spago_dir: &Path,
+) -> Result<Option<String>> {
+    remove_installed(&package.name, spago_dir)?;
+    download_registry_package(package, global_cache, spago_dir).await?;
+    Ok(None)
+}
+
+/// The commit a git package's checkout is at, or `None` once the package
+/// has been fetched again to record it. Pruned packages are no longer
+/// checkouts, so this is the usual case.
+fn installed_commit(
+    package: &PackageSetPackage,
+    global_cache: &GlobalPackageCache,
+    spago_dir: &Path,
+) -> Result<Option<String>> {
+    if let Some(commit) = checked_out_commit(&spago_dir.join(&package.name.0)) {
+        return Ok(Some(commit));
+    }
+    if package.version.is_empty() {
+        anyhow::bail!("{} has no ref to fetch", package.name.0);
+    }
+    remove_installed(&package.name, spago_dir)?;
+    fetch_git_package(package, global_cache, spago_dir)?;
+    Ok(None)
+}
+
+fn remove_installed(name: &PackageName, spago_dir: &Path) -> Result<()> {
+    let package_dir = spago_dir.join(&name.0);
+    if package_dir.exists() {
+        fs::remove_dir_all(&package_dir)
+            .context(format!("Failed to remove package directory for {}", name.0))?;
+    }
+    Ok(())
 }
 
 fn package_version_matches(package: &RegistryPackage, package_dir: &Path) -> Result<bool> {
@@ -422,6 +581,19 @@ fn install_git_package(
         })));
     }
 
+    Ok(Some(InstalledPackage::Git(fetch_git_package(
+        package,
+        global_cache,
+        spago_dir,
+    )?)))
+}
+
+/// Fetch a git package into `spago_dir` and cache it
+fn fetch_git_package(
+    package: &PackageSetPackage,
+    global_cache: &GlobalPackageCache,
+    spago_dir: &Path,
+) -> Result<PackageInfo> {
     // Fetch from Git and cache
     let package_info = fetch_package(package, spago_dir)?;
 
@@ -432,12 +604,13 @@ fn install_git_package(
         &package_info.local_path,
     )?;
 
-    Ok(Some(InstalledPackage::Git(package_info)))
+    Ok(package_info)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::install::lock::COMMIT_FILE;
     use std::fs;
     use tempfile::TempDir;
 
@@ -546,4 +719,82 @@ mod tests {
             "Stale package directory should have been removed before the clone attempt"
         );
     }
+
+    /// Commit a src file to a new repository at `dir`, returning the commit
+    fn create_git_repo(dir: &Path) -> String {
+        let repo = git2::Repository::init(dir).unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/Main.purs"), "module Main where").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["src"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.tag_lightweight("v1.0.0", &repo.find_object(commit, None).unwrap(), false)
+            .unwrap();
+        commit.to_string()
+    }
+
+    #[test]
+    fn test_installed_commit_reads_the_checkout() {
+        let temp = TempDir::new().unwrap();
+        let spago_dir = temp.path().join(".spago");
+        let commit = create_git_repo(&spago_dir.join("my-package"));
+        let global_cache = GlobalPackageCache::new_with_dir(temp.path().join("cache")).unwrap();
+
+        // The remote doesn't exist, so this can only come from HEAD
+        let package = make_git_package("my-package", "main");
+        let found = installed_commit(&package, &global_cache, &spago_dir).unwrap();
+
+        assert_eq!(found, Some(commit));
+    }
+
+    #[test]
+    fn test_installed_commit_refetches_a_pruned_package() {
+        let temp = TempDir::new().unwrap();
+        let spago_dir = temp.path().join(".spago");
+        let remote = temp.path().join("remote");
+        let commit = create_git_repo(&remote);
+        let global_cache = GlobalPackageCache::new_with_dir(temp.path().join("cache")).unwrap();
+
+        // Installed before commits were recorded, so there's no commit.txt
+        create_fake_installed_package(&spago_dir, "my-package", "v1.0.0");
+        let mut package = make_git_package("my-package", "v1.0.0");
+        package.repo = remote.to_string_lossy().to_string();
+
+        let found = installed_commit(&package, &global_cache, &spago_dir).unwrap();
+
+        assert_eq!(found, None);
+        let package_dir = spago_dir.join("my-package");
+        assert_eq!(
+            fs::read_to_string(package_dir.join(COMMIT_FILE)).unwrap(),
+            commit
+        );
+        let cached = global_cache
+            .get_cached_path(&package.name, "v1.0.0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(cached.join(COMMIT_FILE)).unwrap(),
+            commit
+        );
+    }
+
+    #[test]
+    fn test_installed_commit_fails_without_a_checkout_or_remote() {
+        let temp = TempDir::new().unwrap();
+        let spago_dir = temp.path().join(".spago");
+        let global_cache = GlobalPackageCache::new_with_dir(temp.path().join("cache")).unwrap();
+        create_fake_installed_package(&spago_dir, "my-package", "v1.0.0");
+
+        let mut package = make_git_package("my-package", "v1.0.0");
+        package.repo = temp.path().join("missing").to_string_lossy().to_string();
+
+        assert!(installed_commit(&package, &global_cache, &spago_dir).is_err());
+    }
 }
@@ -1,6 +1,7 @@
 pub mod cache;
 pub mod cleanup;
 mod git;
+pub mod lock;
 mod manager;
 
 pub use cleanup::cleanup_unused_packages;
@@ -9,6 +10,9 @@ pub mod command;
 pub mod uninstall;
 
 use anyhow::Result;
+use colored::Colorize;
+
+use lock::Lockfile;
 
 /// Install all dependencies from spago.yaml
 pub async fn install_all_dependencies(
@@ -18,6 +22,92 @@ pub async fn install_all_dependencies(
 ) -> Result<InstallResult> {
     let manager = InstallManager::new(&config.spago_dir())?;
     manager
-        .install_packages(package_set, config, include_test_deps)
+        .install_packages(package_set, config, include_test_deps, &[])
+        .await
+}
+
+/// Install the dependencies from spago.yaml along with some extra packages,
+/// such as benchmark dependencies
+pub async fn install_with_extra_dependencies(
+    config: &crate::config::SpagoConfig,
+    package_set: &crate::registry::PackageSet,
+    extra_dependencies: &[crate::registry::PackageName],
+) -> Result<InstallResult> {
+    let manager = InstallManager::new(&config.spago_dir())?;
+    manager
+        .install_packages(package_set, config, false, extra_dependencies)
         .await
 }
+
+/// Install the dependencies of every package in the workspace, tests
+/// included, and record what they resolved to in spago.lock.
+///
+/// When frozen, nothing is written: the lock must exist and match the
+/// spago.yaml files, and what gets installed must match the lock.
+pub async fn install_locked(
+    config: &crate::config::SpagoConfig,
+    package_set: &crate::registry::PackageSet,
+    frozen: bool,
+) -> Result<InstallResult> {
+    let root_config = if config.is_workspace_root() {
+        config.clone()
+    } else {
+        crate::config::load_config(config.workspace_root.join("spago.yaml"), false)?
+    };
+    let resolved = Lockfile::resolve(&root_config, package_set)?;
+    let previous = match Lockfile::load(&config.workspace_root) {
+        Ok(previous) => previous,
+        Err(e) if frozen => return Err(e),
+        Err(e) => {
+            eprintln!("⚠️  {:#}, it will be rewritten", e);
+            None
+        }
+    };
+
+    let frozen_lock = match (frozen, &previous) {
+        (false, _) => None,
+        (true, None) => anyhow::bail!("No spago.lock found, run ragu install to create it"),
+        (true, Some(previous)) => {
+            let changes = previous.changes(&resolved);
+            if !changes.is_empty() {
+                anyhow::bail!(
+                    "spago.lock is out of date with spago.yaml:\n  {}\nRun ragu install without --frozen to update it",
+                    changes.join("\n  ")
+                );
+            }
+            Some(previous)
+        }
+    };
+
+    let manager = InstallManager::new(&config.spago_dir())?;
+    let mut result = manager
+        .install_packages(
+            package_set,
+            config,
+            false,
+            &resolved.workspace_dependencies(),
+        )
+        .await?;
+    manager.fill_missing_hashes(&mut result.resolved).await;
+    let lockfile = resolved.with_hashes(&result.resolved, previous.as_ref());
+
+    match frozen_lock {
+        Some(locked) => {
+            let mismatched = locked.mismatched_hashes(&result.resolved);
+            if !mismatched.is_empty() {
+                anyhow::bail!(
+                    "Installed packages don't match spago.lock:\n  {}",
+                    mismatched.join("\n  ")
+                );
+            }
+        }
+        None => {
+            if lockfile.save(&config.workspace_root)? {
+                print!("\r\x1B[K"); // Clear the install progress
+                println!("{} Updated {}", "✓".green(), lock::LOCKFILE);
+            }
+        }
+    }
+
+    Ok(result)
+}
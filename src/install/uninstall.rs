@@ -2,8 +2,9 @@ use anyhow::{Context, Result};
 use colored::Colorize;
 use std::path::PathBuf;
 
+use crate::clean::prune_stale_output;
 use crate::config::{load_config_cwd, remove_packages_from_config};
-use crate::install::cleanup_unused_packages;
+use crate::install::{cleanup_unused_packages, install_locked};
 use crate::registry::{PackageName, PackageSet};
 
 /// Execute the uninstall command
@@ -57,9 +58,17 @@ pub async fn execute(
             .retain(|dep| dep != &package_name);
     }
 
+    // Keep spago.lock in step, with workspace packages reloaded from the
+    // updated spago.yaml
+    let updated_package_set = updated_config.package_set().await?;
+    install_locked(&updated_config, &updated_package_set, false).await?;
+
     // Clean up unused packages from .spago directory
     let removed_packages = cleanup_unused_packages(&updated_config, package_set)?;
 
+    // Drop the compiled modules of packages that are no longer needed
+    let removed_modules = prune_stale_output(&updated_config, package_set, false, verbose)?;
+
     // Report results
     if !removed_packages.is_empty() {
         if verbose {
@@ -78,5 +87,12 @@ pub async fn execute(
         println!("{} No packages were removed", "✓".green().bold());
     }
 
+    if !removed_modules.is_empty() && !verbose {
+        println!(
+            "  Removed {} stale modules from output",
+            removed_modules.len().to_string().yellow()
+        );
+    }
+
     Ok(())
 }
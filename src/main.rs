@@ -1,7 +1,13 @@
+mod bench;
 mod build;
+mod bundle;
 mod cache;
+mod clean;
 mod cli;
+mod compiler;
 mod config;
+mod docs;
+mod ide;
 mod imports;
 mod init;
 mod install;
@@ -10,6 +16,7 @@ mod package_info;
 mod package_sets;
 mod print_output;
 mod registry;
+mod repl;
 mod run;
 mod sources;
 mod src_as_sources;
@@ -45,7 +45,10 @@ pub fn discover_all_modules(sources: &BuildSources) -> Result<Vec<ModuleInfo>> {
 }
 
 /// Discover modules from a glob pattern
-fn discover_modules_from_glob(glob_pattern: &str, package_name: &str) -> Result<Vec<ModuleInfo>> {
+pub fn discover_modules_from_glob(
+    glob_pattern: &str,
+    package_name: &str,
+) -> Result<Vec<ModuleInfo>> {
     let mut modules = Vec::new();
 
     // Use glob to find all matching files
@@ -80,11 +80,13 @@ mod tests {
                     package_name: "prelude".to_string(),
                     glob_pattern: "./.spago/prelude/src/**/*.purs".to_string(),
                     local_path: PathBuf::from("./.spago/prelude"),
+                    is_test: false,
                 },
                 DependencyGlob {
                     package_name: "console".to_string(),
                     glob_pattern: "./.spago/console/src/**/*.purs".to_string(),
                     local_path: PathBuf::from("./.spago/console"),
+                    is_test: false,
                 },
             ],
         }
@@ -180,6 +182,10 @@ mod tests {
                 name: crate::registry::PackageName::new("test-package"),
                 dependencies: vec![],
                 test: None,
+                bundle: None,
+                build: None,
+                run: None,
+                bench: None,
             },
             workspace: crate::config::WorkspaceConfig::default(),
             workspace_root: PathBuf::from("."),
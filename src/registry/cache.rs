@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -129,6 +130,12 @@ fn get_tags_cache_path() -> Result<PathBuf> {
     Ok(cache_dir.join("tags.json"))
 }
 
+/// Get path to the cache of compiler versions targeted by registry package sets
+fn get_registry_compilers_cache_path() -> Result<PathBuf> {
+    let cache_dir = get_metadata_cache_dir()?;
+    Ok(cache_dir.join("registry-compilers.json"))
+}
+
 /// Get path to the registry versions cache file
 fn get_registry_versions_cache_path() -> Result<PathBuf> {
     let cache_dir = get_metadata_cache_dir()?;
@@ -227,6 +234,42 @@ pub fn save_cached_registry_versions(versions: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// Load the compiler version a registry package set targets, if cached.
+/// Published package sets never change so there is no TTL.
+pub fn load_cached_registry_compiler_version(registry_version: &str) -> Result<Option<String>> {
+    let cache_path = get_registry_compilers_cache_path()?;
+
+    if !cache_path.exists() {
+        return Ok(None);
+    }
+
+    let cached_data =
+        fs::read_to_string(&cache_path).context("Failed to read registry compilers cache file")?;
+
+    let cached: HashMap<String, String> = serde_json::from_str(&cached_data)
+        .context("Failed to deserialize cached registry compilers")?;
+
+    Ok(cached.get(registry_version).cloned())
+}
+
+/// Save the compiler version a registry package set targets
+pub fn save_cached_registry_compiler_version(registry_version: &str, compiler: &str) -> Result<()> {
+    let cache_path = get_registry_compilers_cache_path()?;
+
+    let mut cached: HashMap<String, String> = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    cached.insert(registry_version.to_string(), compiler.to_string());
+
+    let json = serde_json::to_string_pretty(&cached)
+        .context("Failed to serialize registry compilers cache")?;
+
+    fs::write(&cache_path, json).context("Failed to write registry compilers cache file")?;
+
+    Ok(())
+}
+
 /// Get the registry cache directory
 pub fn get_registry_cache_dir() -> Result<PathBuf> {
     let cache_dir = dirs::cache_dir()
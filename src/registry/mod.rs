@@ -17,7 +17,7 @@ pub use package_sets::{
     list_available_tags_with_options,
 };
 pub use packages::PackageQuery;
-pub use purescript_registry::get_package_set_by_registry_version;
+pub use purescript_registry::{get_package_set_by_registry_version, get_registry_compiler_version};
 pub use types::{
     LocalPackage, Package, PackageName, PackageSet, PackageSetPackage, RegistryPackage,
 };
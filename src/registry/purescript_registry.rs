@@ -8,7 +8,9 @@ use walkdir::WalkDir;
 use crate::{
     config::load_config_cwd,
     registry::{
-        add_workspace_packages, clear_registry_package_set_cache, load_registry_index_from_cache,
+        add_workspace_packages,
+        cache::{load_cached_registry_compiler_version, save_cached_registry_compiler_version},
+        clear_registry_package_set_cache, load_registry_index_from_cache,
         load_registry_package_set_from_cache,
         package_sets::add_extra_packages,
         save_registry_index_to_cache, save_registry_package_set_to_cache,
@@ -69,7 +71,7 @@ async fn fetch_registry_package_set(registry_version: &str) -> Result<PackageSet
 
     let mut package_set = HashMap::new();
 
-    for (name, version) in registry_set.0 {
+    for (name, version) in registry_set.packages {
         let registry_package = index.get_package(&name, &version);
         if let Some(registry_package) = registry_package {
             package_set.insert(name, Package::Registry(registry_package.clone()));
@@ -100,6 +102,7 @@ async fn fetch_registry_package_set_from_github(
 
     #[derive(Deserialize)]
     struct RawPackageSet {
+        compiler: String,
         packages: HashMap<String, String>,
     }
 
@@ -108,13 +111,29 @@ async fn fetch_registry_package_set_from_github(
         .await
         .context("Failed to parse package set JSON")?;
 
-    let package_set = raw_package_set
+    save_cached_registry_compiler_version(registry_version, &raw_package_set.compiler)?;
+
+    let packages = raw_package_set
         .packages
         .into_iter()
         .map(|(name, version)| (PackageName::new(&name), version))
         .collect();
 
-    Ok(RegistryPackageSet(package_set))
+    Ok(RegistryPackageSet {
+        compiler: raw_package_set.compiler,
+        packages,
+    })
+}
+
+/// Get the compiler version a registry package set was built for,
+/// fetching the package set if it hasn't been seen before
+pub async fn get_registry_compiler_version(registry_version: &str) -> Result<String> {
+    if let Some(compiler) = load_cached_registry_compiler_version(registry_version)? {
+        return Ok(compiler);
+    }
+
+    let registry_set = fetch_registry_package_set_from_github(registry_version).await?;
+    Ok(registry_set.compiler)
 }
 
 async fn fetch_registry_index_from_github_or_cache() -> Result<RegistryIndex> {
@@ -189,7 +208,10 @@ async fn fetch_registry_index_from_github_or_cache() -> Result<RegistryIndex> {
         }
     }
 }
-struct RegistryPackageSet(HashMap<PackageName, String>);
+struct RegistryPackageSet {
+    compiler: String,
+    packages: HashMap<PackageName, String>,
+}
 
 impl RegistryIndex {
     fn get_package(&self, name: &PackageName, version: &str) -> Option<&RegistryPackage> {
@@ -34,17 +34,14 @@ pub fn add_workspace_packages(package_set: &mut PackageSet, workspace_root: &Pat
         {
             if let Ok(config) = crate::config::load_config(entry.path(), true) {
                 let path = entry.path().parent().unwrap().to_path_buf();
+                let test_dependencies = config.test_dependencies().into_iter().cloned().collect();
                 // Add the package to our set
                 package_set.insert(
                     config.package.name.clone(),
                     Package::Local(LocalPackage {
                         name: config.package.name,
                         dependencies: config.package.dependencies,
-                        test_dependencies: config
-                            .package
-                            .test
-                            .map(|t| t.dependencies)
-                            .unwrap_or_default(),
+                        test_dependencies,
                         path,
                     }),
                 );
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use tokio::process::Command;
+
+use crate::{
+    build::resolve_build_sources,
+    compiler::{toolchain::resolve_toolchain, version::check_compiler_version},
+    install::install_all_dependencies,
+};
+
+/// Repl options from the command line
+#[derive(Debug, Default)]
+pub struct ReplArgs {
+    /// Include test sources and dependencies
+    pub test: bool,
+    /// Extra arguments for `purs repl`
+    pub repl_args: Vec<String>,
+}
+
+/// Start `purs repl` with the project's sources.
+///
+/// The repl runs from the package directory, so it picks up the package's
+/// own `.purs-repl`, and is pointed at the workspace's shared output.
+pub async fn execute(args: ReplArgs, verbose: bool) -> Result<()> {
+    let config = crate::config::load_config_cwd()?;
+
+    check_compiler_version(&config, verbose).await?;
+    let package_set = config.package_set().await?;
+    install_all_dependencies(&config, &package_set, args.test).await?;
+    let sources = resolve_build_sources(&config, package_set, args.test, verbose).await?;
+
+    let toolchain = resolve_toolchain(&config)?;
+    if verbose {
+        println!(
+            "{} Starting {} repl with output {}",
+            "→".cyan(),
+            toolchain.purs.display(),
+            config.output_dir().display()
+        );
+    }
+
+    let status = run_repl(
+        repl_command(
+            &toolchain.purs,
+            &sources,
+            &config.output_dir(),
+            &args.repl_args,
+        ),
+        verbose,
+    )
+    .await?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+fn repl_command(
+    purs: &Path,
+    sources: &[String],
+    output_dir: &Path,
+    repl_args: &[String],
+) -> Command {
+    let mut command = Command::new(purs);
+    command
+        .arg("repl")
+        .args(sources)
+        .arg("--output")
+        .arg(output_dir)
+        .args(repl_args);
+    command
+}
+
+/// Wait for the repl to exit. Ctrl-C belongs to the repl (it cancels the
+/// current input), so ragu keeps running until the repl is done.
+async fn run_repl(mut command: Command, verbose: bool) -> Result<std::process::ExitStatus> {
+    if verbose {
+        println!("{} {:?}", "→".cyan(), command.as_std());
+    }
+    let mut child = command
+        .spawn()
+        .context("Failed to start purs repl. Is purs installed?")?;
+
+    loop {
+        tokio::select! {
+            status = child.wait() => return status.context("Failed to wait for purs repl"),
+            _ = tokio::signal::ctrl_c() => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repl_command_uses_shared_output() {
+        let command = repl_command(
+            Path::new("/bin/purs"),
+            &["src/**/*.purs".to_string()],
+            Path::new("../../output"),
+            &["--node-opts".to_string(), "--inspect".to_string()],
+        );
+        let command = command.as_std();
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "repl",
+                "src/**/*.purs",
+                "--output",
+                "../../output",
+                "--node-opts",
+                "--inspect"
+            ]
+        );
+        // Started where ragu was, next to the package's .purs-repl
+        assert_eq!(command.get_current_dir(), None);
+    }
+}
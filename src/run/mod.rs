@@ -1,17 +1,86 @@
-use tokio::process::Command;
+pub mod runner;
 
 use anyhow::Result;
 
-use crate::{build::compiler::execute_compiler, install::install_all_dependencies};
+use crate::{
+    build::compiler::{execute_compiler, CompilerOptions},
+    compiler::version::check_compiler_version,
+    config::{RunConfig, Runtime, SpagoConfig},
+    install::install_all_dependencies,
+};
 
-pub async fn execute(
-    module: Option<String>,
-    skip_compilation: bool,
-    verbose: bool,
-    node_args: Vec<String>,
-) -> Result<()> {
+use runner::Program;
+
+const DEFAULT_MODULE: &str = "Main";
+const DEFAULT_FUNCTION: &str = "main";
+
+/// Run options from the command line, each overriding `package.run`
+#[derive(Debug, Default)]
+pub struct RunArgs {
+    pub module: Option<String>,
+    pub function: Option<String>,
+    pub runtime: Option<Runtime>,
+    pub node_args: Vec<String>,
+    pub program_args: Vec<String>,
+}
+
+impl RunArgs {
+    /// Combine with the config, falling back to defaults.
+    ///
+    /// Program arguments on the command line replace `execArgs`, runtime
+    /// flags are added after `nodeArgs`.
+    pub fn resolve(self, config: Option<&RunConfig>) -> Program {
+        let config = config.cloned().unwrap_or_default();
+
+        let mut node_args = config.node_args;
+        node_args.extend(self.node_args);
+
+        Program {
+            module: self
+                .module
+                .or(config.main)
+                .unwrap_or_else(|| DEFAULT_MODULE.to_string()),
+            function: self
+                .function
+                .or(config.function)
+                .unwrap_or_else(|| DEFAULT_FUNCTION.to_string()),
+            runtime: self.runtime.or(config.runtime).unwrap_or_default(),
+            program_args: if self.program_args.is_empty() {
+                config.exec_args
+            } else {
+                self.program_args
+            },
+            runtime_args: node_args,
+        }
+    }
+}
+
+/// What a command with its own entry point runs, such as tests or
+/// benchmarks. The runtime falls back from the command line to the
+/// command's own `runtime`, then `package.run.runtime`.
+pub fn entry_program(
+    config: &SpagoConfig,
+    module: &str,
+    runtime: Option<&Runtime>,
+    args: RunArgs,
+) -> Program {
+    RunArgs {
+        module: Some(module.to_string()),
+        runtime: args
+            .runtime
+            .or_else(|| runtime.cloned())
+            .or_else(|| config.package.run.as_ref()?.runtime.clone()),
+        ..args
+    }
+    .resolve(None)
+}
+
+pub async fn execute(args: RunArgs, skip_compilation: bool, verbose: bool) -> Result<()> {
     let config = crate::config::load_config_cwd()?;
+    let program = args.resolve(config.package.run.as_ref());
+
     if !skip_compilation {
+        check_compiler_version(&config, verbose).await?;
         let package_set = config.package_set().await?;
         install_all_dependencies(&config, &package_set, false).await?;
         let sources =
@@ -28,38 +97,92 @@ pub async fn execute(
 
         execute_compiler(
             &all_sources,
-            &config.output_dir(),
-            &config.workspace_root,
-            vec![],
-            &config.workspace.psa_options,
-            false,
-            verbose,
+            &CompilerOptions::from_config(&config, verbose)?,
         )
         .await?;
     }
 
-    let output_dir = config.output_dir();
-    let test_file = output_dir.join(format!("{}/index.js", module.unwrap_or("Main".to_string())));
+    let code = runner::run(&config.run_output_dir(), &program).await?;
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
 
-    // Create a JavaScript snippet that loads the module and calls main
-    let js_code = format!(
-        "import {{main}} from '{}';main();",
-        test_file.to_string_lossy()
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut run_process = Command::new("node");
-    run_process.arg("-e").arg(js_code);
+    #[test]
+    fn test_resolve_uses_defaults() {
+        let program = RunArgs::default().resolve(None);
 
-    // Add any additional node arguments passed from the CLI
-    for arg in node_args {
-        run_process.arg(arg);
+        assert_eq!(program.module, "Main");
+        assert_eq!(program.function, "main");
+        assert!(program.program_args.is_empty());
+        assert_eq!(program.runtime, Runtime::Node);
+        assert!(program.runtime_args.is_empty());
     }
 
-    let mut child = run_process
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
-        .spawn()?;
+    #[test]
+    fn test_resolve_cli_overrides_config() {
+        let config: RunConfig = serde_yaml::from_str(
+            "main: App.Main\nfunction: start\nexecArgs: [--port, \"8080\"]\nnodeArgs: [--enable-source-maps]\nruntime: bun\n",
+        )
+        .unwrap();
 
-    child.wait().await?;
-    Ok(())
+        let program = RunArgs::default().resolve(Some(&config));
+        assert_eq!(program.module, "App.Main");
+        assert_eq!(program.function, "start");
+        assert_eq!(program.runtime, Runtime::Bun);
+        assert_eq!(program.program_args, vec!["--port", "8080"]);
+
+        let program = RunArgs {
+            module: Some("Other".to_string()),
+            function: Some("serve".to_string()),
+            runtime: Some(Runtime::Deno),
+            node_args: vec!["--inspect".to_string()],
+            program_args: vec!["--port".to_string(), "3000".to_string()],
+        }
+        .resolve(Some(&config));
+        assert_eq!(program.module, "Other");
+        assert_eq!(program.function, "serve");
+        assert_eq!(program.program_args, vec!["--port", "3000"]);
+        assert_eq!(program.runtime, Runtime::Deno);
+        assert_eq!(
+            program.runtime_args,
+            vec!["--enable-source-maps", "--inspect"]
+        );
+    }
+
+    #[test]
+    fn test_entry_program_runtime_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spago.yaml");
+        std::fs::write(
+            &path,
+            "workspace: {}\npackage:\n  name: app\n  run:\n    main: App.Main\n    runtime: bun\n",
+        )
+        .unwrap();
+        let config = crate::config::load_config(&path, false).unwrap();
+
+        let program = entry_program(&config, "Test.Main", None, RunArgs::default());
+        assert_eq!(program.module, "Test.Main");
+        assert_eq!(program.runtime, Runtime::Bun);
+
+        let program = entry_program(
+            &config,
+            "Test.Main",
+            Some(&Runtime::Deno),
+            RunArgs::default(),
+        );
+        assert_eq!(program.runtime, Runtime::Deno);
+
+        let args = RunArgs {
+            runtime: Some(Runtime::Node),
+            ..Default::default()
+        };
+        let program = entry_program(&config, "Test.Main", Some(&Runtime::Deno), args);
+        assert_eq!(program.runtime, Runtime::Node);
+    }
 }
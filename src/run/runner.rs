@@ -0,0 +1,399 @@
+//! Runs a compiled module's entry function with a JavaScript runtime, shared
+//! by `run` and `test`.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::config::Runtime;
+
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").unwrap());
+
+/// Entry scripts live here, inside the output directory, so their imports
+/// resolve against the compiled modules next to them
+const ENTRY_DIR: &str = ".ragu-run";
+
+/// Replaced by the entry script in a runtime command template
+const ENTRY_PLACEHOLDER: &str = "{entry}";
+/// Replaced by the program arguments in a runtime command template
+const ARGS_PLACEHOLDER: &str = "{args}";
+
+impl Runtime {
+    /// The executable and arguments that run an ESM entry script
+    fn command(&self, entry: &Path, program: &Program) -> Result<(String, Vec<String>)> {
+        let entry = entry.to_string_lossy().to_string();
+        let flags = program.runtime_args.iter().cloned();
+        let program_args = program.program_args.iter().cloned();
+
+        let (executable, args): (&str, Vec<String>) = match self {
+            Runtime::Node => ("node", flags.chain([entry]).chain(program_args).collect()),
+            // Without `run`, bun treats a script named like a package.json
+            // script as that script
+            Runtime::Bun => (
+                "bun",
+                ["run".to_string()]
+                    .into_iter()
+                    .chain(flags)
+                    .chain([entry])
+                    .chain(program_args)
+                    .collect(),
+            ),
+            // Programs expect node's unrestricted access, so grant every
+            // permission unless the configured flags choose their own
+            Runtime::Deno => {
+                let grants_permissions = program
+                    .runtime_args
+                    .iter()
+                    .any(|flag| flag == "-A" || flag.starts_with("--allow-"));
+                let allow_all = (!grants_permissions).then(|| "--allow-all".to_string());
+                (
+                    "deno",
+                    ["run".to_string()]
+                        .into_iter()
+                        .chain(allow_all)
+                        .chain(flags)
+                        .chain([entry])
+                        .chain(program_args)
+                        .collect(),
+                )
+            }
+            Runtime::Command(template) => {
+                let mut tokens = template.split_whitespace();
+                let executable = tokens.next().context("The runtime command is empty")?;
+                let tokens: Vec<&str> = tokens.collect();
+
+                let mut args: Vec<String> = flags.collect();
+                for token in &tokens {
+                    match *token {
+                        ENTRY_PLACEHOLDER => args.push(entry.clone()),
+                        ARGS_PLACEHOLDER => args.extend(program.program_args.iter().cloned()),
+                        token => args.push(token.to_string()),
+                    }
+                }
+                if !tokens.contains(&ENTRY_PLACEHOLDER) {
+                    args.push(entry);
+                    if !tokens.contains(&ARGS_PLACEHOLDER) {
+                        args.extend(program_args);
+                    }
+                }
+                (executable, args)
+            }
+        };
+        Ok((executable.to_string(), args))
+    }
+}
+
+/// A compiled program to run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub module: String,
+    /// The exported function to call, an `Effect Unit`
+    pub function: String,
+    pub runtime: Runtime,
+    /// Arguments the program sees in `process.argv`
+    pub program_args: Vec<String>,
+    /// Flags for the runtime itself, such as `--enable-source-maps`
+    pub runtime_args: Vec<String>,
+}
+
+/// Run the program and return its exit code.
+pub async fn run(output_dir: &Path, program: &Program) -> Result<i32> {
+    run_in(output_dir, program, None).await
+}
+
+/// Run the program from another directory and return its exit code.
+pub async fn run_in(
+    output_dir: &Path,
+    program: &Program,
+    working_dir: Option<&Path>,
+) -> Result<i32> {
+    let status = command(output_dir, program, working_dir)?
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .with_context(|| format!("Failed to start {}", program.runtime))?;
+    Ok(exit_code(status))
+}
+
+/// A finished program's exit code and everything it printed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Captured {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Captured output without the colours and cursor movement of a terminal
+pub fn strip_ansi(output: &str) -> std::borrow::Cow<'_, str> {
+    ANSI_ESCAPE.replace_all(output, "")
+}
+
+/// Run the program with its output captured. With `echo` the output is also
+/// shown as it happens, otherwise it's held back so several programs can
+/// run at once without interleaving.
+pub async fn run_captured(
+    output_dir: &Path,
+    program: &Program,
+    working_dir: Option<&Path>,
+    echo: bool,
+) -> Result<Captured> {
+    let mut child = command(output_dir, program, working_dir)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", program.runtime))?;
+
+    let stdout = child.stdout.take().context("Failed to capture stdout")?;
+    let stderr = child.stderr.take().context("Failed to capture stderr")?;
+    let stdout = tokio::spawn(collect_output(
+        stdout,
+        echo.then(|| Box::new(std::io::stdout()) as Box<dyn Write + Send>),
+    ));
+    let stderr = tokio::spawn(collect_output(
+        stderr,
+        echo.then(|| Box::new(std::io::stderr()) as Box<dyn Write + Send>),
+    ));
+
+    let status = child.wait().await?;
+    Ok(Captured {
+        code: exit_code(status),
+        stdout: stdout.await??,
+        stderr: stderr.await??,
+    })
+}
+
+async fn collect_output(
+    stream: impl AsyncRead + Unpin,
+    mut echo: Option<Box<dyn Write + Send>>,
+) -> std::io::Result<String> {
+    let mut reader = BufReader::new(stream);
+    let mut captured = Vec::new();
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).await? > 0 {
+        if let Some(echo) = echo.as_mut() {
+            let _ = echo.write_all(&line);
+        }
+        captured.append(&mut line);
+    }
+    Ok(String::from_utf8_lossy(&captured).into_owned())
+}
+
+fn command(output_dir: &Path, program: &Program, working_dir: Option<&Path>) -> Result<Command> {
+    let module_index = output_dir.join(&program.module).join("index.js");
+    if !module_index.exists() {
+        anyhow::bail!(
+            "Module {} has not been compiled ({} not found)",
+            program.module,
+            module_index.display()
+        );
+    }
+
+    // Absolute, so the entry still resolves from another working directory
+    let entry = write_entry_file(output_dir, program)?
+        .canonicalize()
+        .context("Failed to resolve run entry file")?;
+    let (executable, args) = program.runtime.command(&entry, program)?;
+
+    let mut command = Command::new(executable);
+    command.args(args);
+    if let Some(dir) = working_dir {
+        command.current_dir(dir);
+    }
+    Ok(command)
+}
+
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    // Killed by a signal: report it the way a shell would
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+    status.code().unwrap_or(1)
+}
+
+fn write_entry_file(output_dir: &Path, program: &Program) -> Result<PathBuf> {
+    let entry_dir = output_dir.join(ENTRY_DIR);
+    fs::create_dir_all(&entry_dir).context("Failed to create run entry directory")?;
+
+    let entry = entry_dir.join(format!("{}.{}.mjs", program.module, program.function));
+    fs::write(&entry, entry_source(&program.module, &program.function))
+        .context("Failed to write run entry file")?;
+    Ok(entry)
+}
+
+fn entry_source(module: &str, function: &str) -> String {
+    // JSON string escaping is also valid JavaScript
+    let import_path = serde_json::to_string(&format!("../{}/index.js", module)).unwrap_or_default();
+    let function = serde_json::to_string(function).unwrap_or_default();
+    let missing = serde_json::to_string(&format!("{} does not export a function named ", module))
+        .unwrap_or_default();
+
+    // Throwing fails the same way in every runtime, `process` may not exist
+    format!(
+        "import * as Module from {import_path};\n\
+         const entry = Module[{function}];\n\
+         if (typeof entry !== \"function\") {{\n  \
+           throw new Error({missing} + {function});\n\
+         }}\n\
+         entry();\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_source() {
+        assert_eq!(
+            entry_source("Test.Main", "main"),
+            "import * as Module from \"../Test.Main/index.js\";\n\
+             const entry = Module[\"main\"];\n\
+             if (typeof entry !== \"function\") {\n  \
+               throw new Error(\"Test.Main does not export a function named \" + \"main\");\n\
+             }\n\
+             entry();\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_requires_compiled_module() {
+        let output = tempfile::tempdir().unwrap();
+        let program = Program {
+            module: "Main".to_string(),
+            function: "main".to_string(),
+            runtime: Runtime::Node,
+            program_args: vec![],
+            runtime_args: vec![],
+        };
+
+        let error = run(output.path(), &program).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Module Main has not been compiled"));
+        assert!(!output.path().join(ENTRY_DIR).exists());
+    }
+
+    #[tokio::test]
+    async fn test_run_passes_arguments_and_exit_code() {
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir_all(output.path().join("Main")).unwrap();
+        // purs writes this too, so index.js files load as ES modules
+        fs::write(output.path().join("package.json"), r#"{"type":"module"}"#).unwrap();
+        fs::write(
+            output.path().join("Main/index.js"),
+            "export const start = () => process.exit(process.argv.slice(2).length + 40);\n",
+        )
+        .unwrap();
+        let program = Program {
+            module: "Main".to_string(),
+            function: "start".to_string(),
+            runtime: Runtime::Node,
+            program_args: vec!["--one".to_string(), "two".to_string()],
+            runtime_args: vec!["--no-warnings".to_string()],
+        };
+
+        assert_eq!(run(output.path(), &program).await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_run_captured() {
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir_all(output.path().join("Test.Main")).unwrap();
+        fs::write(output.path().join("package.json"), r#"{"type":"module"}"#).unwrap();
+        fs::write(
+            output.path().join("Test.Main/index.js"),
+            "export const main = () => {\n  console.log(\"ok 1 - works\");\n  console.error(\"oops\");\n  process.exitCode = 3;\n};\n",
+        )
+        .unwrap();
+        let program = Program {
+            module: "Test.Main".to_string(),
+            function: "main".to_string(),
+            runtime: Runtime::Node,
+            program_args: vec![],
+            runtime_args: vec![],
+        };
+
+        let captured = run_captured(output.path(), &program, None, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            captured,
+            Captured {
+                code: 3,
+                stdout: "ok 1 - works\n".to_string(),
+                stderr: "oops\n".to_string(),
+            }
+        );
+    }
+
+    fn program(runtime: &str) -> Program {
+        Program {
+            module: "Main".to_string(),
+            function: "main".to_string(),
+            runtime: runtime.parse().unwrap(),
+            program_args: vec!["--port".to_string(), "80".to_string()],
+            runtime_args: vec!["--smol".to_string()],
+        }
+    }
+
+    fn command_line(runtime: &str) -> String {
+        let program = program(runtime);
+        let (executable, args) = program
+            .runtime
+            .command(Path::new("entry.mjs"), &program)
+            .unwrap();
+        format!("{} {}", executable, args.join(" "))
+    }
+
+    #[test]
+    fn test_runtime_commands() {
+        assert_eq!(command_line("node"), "node --smol entry.mjs --port 80");
+        assert_eq!(command_line("bun"), "bun run --smol entry.mjs --port 80");
+        assert_eq!(
+            command_line("deno"),
+            "deno run --allow-all --smol entry.mjs --port 80"
+        );
+    }
+
+    #[test]
+    fn test_deno_keeps_configured_permissions() {
+        let mut program = program("deno");
+        program.runtime_args = vec!["--allow-net".to_string()];
+        let (_, args) = program
+            .runtime
+            .command(Path::new("entry.mjs"), &program)
+            .unwrap();
+        assert_eq!(
+            args,
+            vec!["run", "--allow-net", "entry.mjs", "--port", "80"]
+        );
+    }
+
+    #[test]
+    fn test_runtime_command_templates() {
+        assert_eq!(
+            command_line("tsx --no-cache {entry} -- {args}"),
+            "tsx --smol --no-cache entry.mjs -- --port 80"
+        );
+        assert_eq!(
+            command_line("/opt/node/bin/node --trace-warnings"),
+            "/opt/node/bin/node --smol --trace-warnings entry.mjs --port 80"
+        );
+        assert!(program("  ")
+            .runtime
+            .command(Path::new("entry.mjs"), &program("  "))
+            .is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
+use serde::Serialize;
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -7,6 +8,7 @@ use std::path::{Path, PathBuf};
 use crate::config::SpagoConfig;
 use crate::install::InstallManager;
 use crate::registry::{Package, PackageName, PackageQuery, PackageSet};
+use crate::test::test_globs;
 
 /// Build command result containing source globs for each dependency
 #[derive(Debug)]
@@ -25,12 +27,47 @@ pub struct DependencyGlob {
     /// Glob pattern for this dependency's source files
     pub glob_pattern: String,
     /// Local path to the dependency
-    #[allow(dead_code)]
     pub local_path: PathBuf,
+    /// Whether this is a local package's test sources rather than its src
+    pub is_test: bool,
+}
+
+/// Options for `ragu sources`
+#[derive(Debug, Default)]
+pub struct SourcesArgs {
+    /// Print a record per package instead of bare globs
+    pub json: bool,
+    /// Resolve globs and paths against the current directory
+    pub absolute: bool,
+}
+
+/// `ragu sources --json` output
+#[derive(Debug, Serialize)]
+pub struct SourcesJson {
+    /// The current package's sources, unless skipRootSrc applies
+    pub main: Option<String>,
+    /// The current package's test globs, from `package.test.main` and
+    /// every suite
+    pub test: Vec<String>,
+    pub packages: Vec<PackageSources>,
+}
+
+/// One dependency glob and the package it belongs to
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct PackageSources {
+    pub name: String,
+    pub glob: String,
+    pub path: String,
+    /// The package's test sources rather than its src
+    pub test: bool,
+    /// registry, git or local
+    #[serde(rename = "type")]
+    pub package_type: &'static str,
+    pub version: Option<String>,
 }
 
 /// Execute the sources command - outputs just the source globs for piping
-pub async fn execute_sources(verbose: bool) -> Result<()> {
+pub async fn execute_sources(args: SourcesArgs, verbose: bool) -> Result<()> {
     if verbose {
         println!("{} Generating source globs", "→".cyan());
     }
@@ -38,23 +75,113 @@ pub async fn execute_sources(verbose: bool) -> Result<()> {
     // Load spago.yaml configuration
     let config =
         crate::config::load_config_cwd().context("Failed to load spago.yaml configuration")?;
-
-    // Generate source globs for dependencies
-    let sources = generate_sources(&config, None, false, false, verbose).await?;
+    let package_set = config.package_set().await?;
+
+    // Generate source globs for dependencies. Tooling reading the JSON
+    // wants the test dependencies too, flagged as such.
+    let sources = generate_sources(
+        &config,
+        Some(package_set.clone()),
+        false,
+        args.json,
+        verbose,
+    )
+    .await?;
+
+    if args.json {
+        let json = sources_json(&config, &package_set, &sources, args.absolute)?;
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
 
     // Output main sources
     if let Some(main) = &sources.main_sources {
-        println!("{}", main);
+        println!("{}", output_glob(main, args.absolute)?);
     }
 
     // Output dependency sources
     for glob in &sources.dependency_globs {
-        println!("{}", glob.glob_pattern);
+        println!("{}", output_glob(&glob.glob_pattern, args.absolute)?);
     }
 
     Ok(())
 }
 
+/// Label each dependency glob with its package, sorted by package name
+pub fn sources_json(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+    sources: &BuildSources,
+    absolute: bool,
+) -> Result<SourcesJson> {
+    let glob = |glob: &str| output_glob(glob, absolute);
+
+    let mut packages = Vec::new();
+    for dependency in &sources.dependency_globs {
+        let package = package_set.get(&PackageName::new(&dependency.package_name));
+        let path = if absolute {
+            absolute_path(&dependency.local_path)?
+        } else {
+            dependency.local_path.clone()
+        };
+        packages.push(PackageSources {
+            name: dependency.package_name.clone(),
+            glob: glob(&dependency.glob_pattern)?,
+            path: path.to_string_lossy().to_string(),
+            test: dependency.is_test,
+            package_type: match package {
+                Some(Package::Local(_)) => "local",
+                Some(Package::Remote(_)) => "git",
+                Some(Package::Registry(_)) | None => "registry",
+            },
+            version: package.and_then(|p| p.version()).cloned(),
+        });
+    }
+    packages.sort_by(|a, b| (&a.name, a.test).cmp(&(&b.name, b.test)));
+
+    Ok(SourcesJson {
+        main: sources.main_sources.as_deref().map(glob).transpose()?,
+        test: match &config.package.test {
+            Some(test_config) => test_globs(test_config)
+                .iter()
+                .map(|test| glob(test))
+                .collect::<Result<_>>()?,
+            None => vec![],
+        },
+        packages,
+    })
+}
+
+fn output_glob(glob: &str, absolute: bool) -> Result<String> {
+    if absolute {
+        absolute_glob(glob)
+    } else {
+        Ok(glob.to_string())
+    }
+}
+
+/// Make the directory part of a glob absolute, leaving the pattern alone
+fn absolute_glob(glob: &str) -> Result<String> {
+    let (base, pattern) = match glob.find("/**") {
+        Some(index) => glob.split_at(index),
+        None => (glob, ""),
+    };
+    Ok(format!(
+        "{}{}",
+        absolute_path(Path::new(base))?.display(),
+        pattern
+    ))
+}
+
+/// Canonical when the path exists, otherwise joined onto the current directory
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+    match path.canonicalize() {
+        Ok(path) => Ok(path),
+        Err(_) => std::path::absolute(path)
+            .with_context(|| format!("Failed to make {} absolute", path.display())),
+    }
+}
+
 /// Generate source globs for all dependencies
 pub async fn generate_sources(
     config: &SpagoConfig,
@@ -187,6 +314,7 @@ pub fn generate_dependency_glob(
                 package_name: package_name.0.clone(),
                 glob_pattern,
                 local_path: dir,
+                is_test: false,
             }));
         } else if verbose {
             return Err(anyhow::anyhow!(
@@ -216,6 +344,7 @@ pub fn generate_local_test_dependency_glob(
             glob_pattern,
             local_path: package.path.clone(),
             package_name: package_name.0.clone(),
+            is_test: true,
         }));
     }
 
@@ -1,10 +1,12 @@
 #[cfg(test)]
 mod tests {
     use crate::config::{PackageConfig, SpagoConfig, WorkspaceConfig};
-    use crate::registry::{LocalPackage, Package, PackageName, PackageSet, PackageSetPackage};
+    use crate::registry::{
+        LocalPackage, Package, PackageName, PackageSet, PackageSetPackage, RegistryPackage,
+    };
     use crate::sources::{
-        find_package_directory, generate_dependency_glob, generate_sources, BuildSources,
-        DependencyGlob,
+        absolute_glob, find_package_directory, generate_dependency_glob, generate_sources,
+        sources_json, BuildSources, DependencyGlob, PackageSources,
     };
     use std::collections::HashMap;
     use std::fs;
@@ -38,6 +40,10 @@ mod tests {
             name: PackageName::new("test-package"),
             dependencies: vec![PackageName::new("prelude"), PackageName::new("console")],
             test: None,
+            bundle: None,
+            build: None,
+            run: None,
+            bench: None,
         };
 
         let workspace_config = WorkspaceConfig::default();
@@ -245,6 +251,7 @@ mod tests {
             package_name: "test-package".to_string(),
             glob_pattern: "/path/to/test-package/src/**/*.purs".to_string(),
             local_path: PathBuf::from("/path/to/test-package"),
+            is_test: false,
         };
 
         let sources = BuildSources {
@@ -263,6 +270,7 @@ mod tests {
             package_name: "test-package".to_string(),
             glob_pattern: "/path/to/test-package/src/**/*.purs".to_string(),
             local_path: PathBuf::from("/path/to/test-package"),
+            is_test: false,
         };
 
         let cloned = original.clone();
@@ -379,4 +387,111 @@ mod tests {
         assert!(!glob_names.contains(&config.package.name.0));
         assert_eq!(sources.main_sources, Some("./src/**/*.purs".to_string()));
     }
+
+    #[test]
+    fn test_sources_json_labels_each_glob() {
+        let (temp_dir, spago_dir) = create_test_spago_dir();
+        let mut config = create_test_config(&spago_dir);
+        config.package.test = Some(
+            serde_yaml::from_str(
+                "main: Test.Main\nsuites:\n  - name: integration\n    main: Test.Integration\n    sources: [\"./integration/**/*.purs\"]\n",
+            )
+            .unwrap(),
+        );
+
+        let lib_path = temp_dir.path().join("lib");
+        let mut package_set = create_test_package_set();
+        package_set.insert(
+            PackageName::new("lib"),
+            Package::Local(LocalPackage {
+                name: PackageName::new("lib"),
+                path: lib_path.clone(),
+                test_dependencies: vec![],
+                dependencies: vec![],
+            }),
+        );
+        package_set.insert(
+            PackageName::new("arrays"),
+            Package::Registry(RegistryPackage {
+                name: PackageName::new("arrays"),
+                version: "7.3.0".to_string(),
+                dependencies: vec![],
+            }),
+        );
+
+        let glob = |name: &str, dir: &Path, subdirectory: &str, is_test| DependencyGlob {
+            package_name: name.to_string(),
+            glob_pattern: format!("{}/{}/**/*.purs", dir.display(), subdirectory),
+            local_path: dir.to_path_buf(),
+            is_test,
+        };
+        let sources = BuildSources {
+            dependency_globs: vec![
+                glob("lib", &lib_path, "test", true),
+                glob("prelude", &spago_dir.join("prelude"), "src", false),
+                glob("lib", &lib_path, "src", false),
+                glob("arrays", &spago_dir.join("arrays"), "src", false),
+            ],
+            main_sources: Some("./src/**/*.purs".to_string()),
+        };
+
+        let json = sources_json(&config, &package_set, &sources, false).unwrap();
+
+        assert_eq!(json.main, Some("./src/**/*.purs".to_string()));
+        assert_eq!(
+            json.test,
+            vec!["./test/**/*.purs", "./integration/**/*.purs"]
+        );
+        let packages: Vec<(&str, bool, &str, Option<&str>)> = json
+            .packages
+            .iter()
+            .map(|p| {
+                (
+                    p.name.as_str(),
+                    p.test,
+                    p.package_type,
+                    p.version.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            packages,
+            vec![
+                ("arrays", false, "registry", Some("7.3.0")),
+                ("lib", false, "local", None),
+                ("lib", true, "local", None),
+                ("prelude", false, "git", Some("v6.0.1")),
+            ]
+        );
+        assert_eq!(
+            json.packages[2],
+            PackageSources {
+                name: "lib".to_string(),
+                glob: format!("{}/test/**/*.purs", lib_path.display()),
+                path: lib_path.to_string_lossy().to_string(),
+                test: true,
+                package_type: "local",
+                version: None,
+            }
+        );
+
+        let value = serde_json::to_value(&json).unwrap();
+        assert_eq!(value["packages"][0]["type"], "registry");
+    }
+
+    #[test]
+    fn test_absolute_glob() {
+        let (temp_dir, spago_dir) = create_test_spago_dir();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+
+        assert_eq!(
+            absolute_glob(&format!("{}/../src/**/*.purs", spago_dir.display())).unwrap(),
+            format!("{}/src/**/*.purs", root.display())
+        );
+        // Missing directories are still made absolute
+        let relative = absolute_glob("./not-here/**/*.purs").unwrap();
+        assert!(Path::new(&relative).is_absolute());
+        assert!(relative.ends_with("not-here/**/*.purs"));
+    }
 }
@@ -8,7 +8,8 @@ use rayon::prelude::*;
 mod import_parsing;
 
 use crate::{
-    build::compiler::execute_compiler,
+    build::compiler::{execute_compiler, CompilerOptions},
+    compiler::version::check_compiler_version,
     sources::{generate_sources, BuildSources},
     test::TEST_SOURCES,
 };
@@ -20,6 +21,7 @@ pub async fn execute(
     build: bool,
     compiler_args: Vec<String>,
     include_rts_stats: bool,
+    json_errors: bool,
     verbose: bool,
 ) -> Result<()> {
     if verbose {
@@ -34,20 +36,16 @@ pub async fn execute(
     println!("{}", "Starting compilation".dimmed());
 
     if build {
+        check_compiler_version(&config, verbose).await?;
         let sources = modules
             .iter()
             .map(|m| m.file_path.to_string_lossy().to_string())
             .collect::<Vec<_>>();
-        execute_compiler(
-            &sources,
-            &config.output_dir(),
-            &config.workspace_root,
-            compiler_args,
-            &config.workspace.psa_options,
-            include_rts_stats,
-            verbose,
-        )
-        .await?;
+        let mut options = CompilerOptions::from_config(&config, verbose)?;
+        options.compiler_args = compiler_args;
+        options.include_rts_stats = include_rts_stats;
+        options.json_errors = json_errors;
+        execute_compiler(&sources, &options).await?;
     } else {
         println!(
             "{}",
@@ -1,11 +1,52 @@
-use tokio::process::Command;
+mod report;
+mod watch;
+mod workspace;
+
+use std::path::Path;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
 use colored::Colorize;
 
-use crate::{build::compiler::execute_compiler, install::install_all_dependencies};
+use crate::{
+    build::{
+        compiler::{execute_compiler, CompilerOptions},
+        pedantic,
+    },
+    compiler::version::check_compiler_version,
+    config::{Runtime, SpagoConfig, TestConfig},
+    install::install_all_dependencies,
+    registry::PackageSet,
+    run::{
+        entry_program,
+        runner::{self, Program},
+        RunArgs,
+    },
+};
+
+pub use report::ReportTarget;
+use report::SuiteReport;
+
+/// Test options from the command line. The test module comes from
+/// `package.test.main`, or the selected suite's `main`.
+#[derive(Debug, Default)]
+pub struct TestArgs {
+    /// A suite from `package.test.suites`
+    pub suite: Option<String>,
+    pub function: Option<String>,
+    pub runtime: Option<Runtime>,
+    pub node_args: Vec<String>,
+    pub program_args: Vec<String>,
+    /// Run every workspace package's tests instead of the current package's
+    pub workspace: bool,
+    /// How many workspace packages to test at once
+    pub jobs: usize,
+    pub reports: Vec<ReportTarget>,
+    /// Re-run the tests whenever their inputs change
+    pub watch: bool,
+}
 
-pub async fn execute(skip_compilation: bool, verbose: bool) -> Result<()> {
+pub async fn execute(args: TestArgs, skip_compilation: bool, verbose: bool) -> Result<()> {
     let config = crate::config::load_config_cwd()?;
 
     let message = if skip_compilation {
@@ -16,57 +57,50 @@ pub async fn execute(skip_compilation: bool, verbose: bool) -> Result<()> {
     println!("{}", message);
     println!();
 
-    if !skip_compilation {
-        let package_set = config.package_set().await?;
-        install_all_dependencies(&config, &package_set, true).await?;
-        let sources =
-            crate::sources::generate_sources(&config, None, false, false, verbose).await?;
-        let mut all_sources = sources
-            .dependency_globs
-            .iter()
-            .map(|g| g.glob_pattern.clone())
-            .collect::<Vec<String>>();
+    if args.watch {
+        return watch::execute(config, args, verbose).await;
+    }
 
-        if let Some(main) = &sources.main_sources {
-            all_sources.push(main.clone());
-        }
-        all_sources.push(TEST_SOURCES.to_string());
-
-        execute_compiler(
-            &all_sources,
-            &config.output_dir(),
-            &config.workspace_root,
-            vec![],
-            &config.workspace.psa_options,
-            false,
-            verbose,
-        )
-        .await?;
+    if args.workspace {
+        return workspace::execute(&config, args, skip_compilation, verbose).await;
     }
 
-    let output_dir = config.output_dir();
     let test_config = config
         .package
         .test
+        .as_ref()
         .context("No main test package. Add a package.test.")?;
+    let suite = select_suite(test_config, args.suite.as_deref())?;
+
+    if !skip_compilation {
+        let config = config.for_test_suite(args.suite.as_deref());
+        let package_set = config.package_set().await?;
+        compile(&config, package_set.clone(), false, &suite.sources, verbose).await?;
+        if config.pedantic_packages() {
+            pedantic::check(&config, package_set, true, args.suite.as_deref(), verbose).await?;
+        }
+    }
+
+    let program = test_program(&config, test_config, &suite, &args);
 
-    let main_test_package = test_config.main.clone();
-    let test_file = output_dir.join(format!("{}/index.js", main_test_package));
-    let mut test_process = Command::new("node")
-        .arg("-e")
-        .arg(format!(
-            "const module = require('{}'); module.main();",
-            test_file.to_string_lossy()
-        ))
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
-        .spawn()?;
-
-    let test_result = test_process.wait().await?;
-    if !test_result.success() {
+    let (code, report) = run_suite(
+        &config.run_output_dir(),
+        &config.package.name.0,
+        &program,
+        None,
+        !args.reports.is_empty(),
+        true,
+    )
+    .await;
+    if let Some(report) = report {
+        report::write_reports(&args.reports, &[report])?;
+    }
+
+    let code = code?;
+    if code != 0 {
         println!();
         eprintln!("❌ Tests failed");
-        std::process::exit(1);
+        std::process::exit(code);
     } else {
         println!();
         println!("{}", "✓ Tests passed".green());
@@ -75,4 +109,248 @@ pub async fn execute(skip_compilation: bool, verbose: bool) -> Result<()> {
     Ok(())
 }
 
+/// Compile sources and the given test globs. For the whole workspace the
+/// globs are those of every suite being run, and the package set's local
+/// packages carry those suites' dependencies.
+async fn compile(
+    config: &SpagoConfig,
+    package_set: PackageSet,
+    workspace: bool,
+    test_sources: &[String],
+    verbose: bool,
+) -> Result<()> {
+    if workspace && !config.is_workspace_root() {
+        anyhow::bail!("Run ragu test --workspace from the workspace root");
+    }
+
+    check_compiler_version(config, verbose).await?;
+    install_all_dependencies(config, &package_set, true).await?;
+    // From the root, test dependencies bring in every local package's
+    let include_test_deps = workspace || !config.is_workspace_root();
+    let sources = crate::sources::generate_sources(
+        config,
+        Some(package_set),
+        false,
+        include_test_deps,
+        verbose,
+    )
+    .await?;
+    // Test sources are the suites' own, not every local package's test dir
+    let mut all_sources = sources
+        .dependency_globs
+        .iter()
+        .filter(|g| !g.is_test)
+        .map(|g| g.glob_pattern.clone())
+        .collect::<Vec<String>>();
+
+    if let Some(main) = &sources.main_sources {
+        all_sources.push(main.clone());
+    }
+    all_sources.extend(test_sources.iter().cloned());
+
+    execute_compiler(
+        &all_sources,
+        &CompilerOptions::from_config(config, verbose)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Run a package's tests and return the exit code. With `capture` the run
+/// is also recorded for reports, and with `echo` its output is shown as it
+/// happens.
+async fn run_suite(
+    output_dir: &Path,
+    name: &str,
+    program: &Program,
+    working_dir: Option<&Path>,
+    capture: bool,
+    echo: bool,
+) -> (Result<i32>, Option<SuiteReport>) {
+    if !capture {
+        return (runner::run_in(output_dir, program, working_dir).await, None);
+    }
+
+    let started = Instant::now();
+    let result = runner::run_captured(output_dir, program, working_dir, echo).await;
+    let report = SuiteReport::new(
+        name,
+        &program.module,
+        started.elapsed().as_secs_f64(),
+        &result,
+    );
+    (result.map(|captured| captured.code), Some(report))
+}
+
+/// A test entry point and the sources compiled for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestSuite {
+    pub main: String,
+    pub sources: Vec<String>,
+}
+
+/// The named suite from `package.test.suites`, or `package.test.main`
+/// when no name is given
+pub fn select_suite(test_config: &TestConfig, name: Option<&str>) -> Result<TestSuite> {
+    let available = || {
+        test_config
+            .suites
+            .iter()
+            .map(|suite| suite.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    };
+
+    match name {
+        Some(name) => {
+            let suite = test_config
+                .suites
+                .iter()
+                .find(|suite| suite.name == name)
+                .with_context(|| match test_config.suites.len() {
+                    0 => format!("No test suite named {}. package.test has no suites", name),
+                    _ => format!(
+                        "No test suite named {}. Available suites: {}",
+                        name,
+                        available()
+                    ),
+                })?;
+            let sources = if suite.sources.is_empty() {
+                vec![TEST_SOURCES.to_string()]
+            } else {
+                suite.sources.clone()
+            };
+            Ok(TestSuite {
+                main: suite.main.clone(),
+                sources,
+            })
+        }
+        None => match &test_config.main {
+            Some(main) => Ok(TestSuite {
+                main: main.clone(),
+                sources: vec![TEST_SOURCES.to_string()],
+            }),
+            None => anyhow::bail!(
+                "package.test has no main. Choose a suite with --suite: {}",
+                available()
+            ),
+        },
+    }
+}
+
+/// Every test glob of a package: the default one for `package.test.main`
+/// and each suite's sources, without duplicates
+pub fn test_globs(test_config: &TestConfig) -> Vec<String> {
+    let mut globs: Vec<String> = Vec::new();
+    if test_config.main.is_some() {
+        globs.push(TEST_SOURCES.to_string());
+    }
+    for suite in &test_config.suites {
+        let sources = if suite.sources.is_empty() {
+            vec![TEST_SOURCES.to_string()]
+        } else {
+            suite.sources.clone()
+        };
+        for source in sources {
+            if !globs.contains(&source) {
+                globs.push(source);
+            }
+        }
+    }
+    globs
+}
+
+/// What to run for a package's tests. The runtime falls back from the
+/// command line to `package.test.runtime` then `package.run.runtime`.
+fn test_program(
+    config: &SpagoConfig,
+    test_config: &TestConfig,
+    suite: &TestSuite,
+    args: &TestArgs,
+) -> Program {
+    let args = RunArgs {
+        module: None,
+        function: args.function.clone(),
+        runtime: args.runtime.clone(),
+        node_args: args.node_args.clone(),
+        program_args: args.program_args.clone(),
+    };
+    entry_program(config, &suite.main, test_config.runtime.as_ref(), args)
+}
+
 pub const TEST_SOURCES: &str = "./test/**/*.purs";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config(yaml: &str) -> TestConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_select_suite() {
+        let config = test_config(
+            "main: Test.Main\nsuites:\n  - name: unit\n    main: Test.Unit\n  - name: integration\n    main: Test.Integration\n    sources: [\"test/integration/**/*.purs\", \"test/Common/**/*.purs\"]\n",
+        );
+
+        assert_eq!(
+            select_suite(&config, None).unwrap(),
+            TestSuite {
+                main: "Test.Main".to_string(),
+                sources: vec![TEST_SOURCES.to_string()],
+            }
+        );
+        assert_eq!(
+            select_suite(&config, Some("unit")).unwrap().sources,
+            vec![TEST_SOURCES]
+        );
+        assert_eq!(
+            select_suite(&config, Some("integration")).unwrap(),
+            TestSuite {
+                main: "Test.Integration".to_string(),
+                sources: vec![
+                    "test/integration/**/*.purs".to_string(),
+                    "test/Common/**/*.purs".to_string()
+                ],
+            }
+        );
+
+        let error = select_suite(&config, Some("browser")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No test suite named browser. Available suites: unit, integration"
+        );
+    }
+
+    #[test]
+    fn test_select_suite_without_main() {
+        let config = test_config("suites:\n  - name: unit\n    main: Test.Unit\n");
+
+        let error = select_suite(&config, None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "package.test has no main. Choose a suite with --suite: unit"
+        );
+    }
+
+    #[test]
+    fn test_test_globs() {
+        let config = test_config(
+            "main: Test.Main\nsuites:\n  - name: unit\n    main: Test.Unit\n  - name: integration\n    main: Test.Integration\n    sources: [\"test/integration/**/*.purs\", \"test/Common/**/*.purs\"]\n",
+        );
+        assert_eq!(
+            test_globs(&config),
+            vec![
+                TEST_SOURCES,
+                "test/integration/**/*.purs",
+                "test/Common/**/*.purs"
+            ]
+        );
+
+        let config = test_config(
+            "suites:\n  - name: e2e\n    main: Test.E2E\n    sources: [\"e2e/**/*.purs\"]\n",
+        );
+        assert_eq!(test_globs(&config), vec!["e2e/**/*.purs"]);
+    }
+}
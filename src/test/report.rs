@@ -0,0 +1,615 @@
+//! JUnit XML and JSON reports of test runs. Test cases are recovered from
+//! TAP or spec reporter output when the test program prints either.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::run::runner::{strip_ansi, Captured};
+
+static TAP_RESULT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(not )?ok\b(?:\s+\d+)?(?:\s*-)?\s*([^#]*?)\s*(?:#\s*(\w+).*)?$").unwrap()
+});
+static TAP_PLAN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+\.\.\d+").unwrap());
+
+/// Markers the spec reporter puts in front of passing, failing and pending tests
+const SPEC_PASSED: [char; 2] = ['✓', '✔'];
+const SPEC_FAILED: [char; 2] = ['✗', '✘'];
+const SPEC_PENDING: char = '~';
+/// Joins nested `describe` names into a test case's class name
+const CLASSNAME_SEPARATOR: &str = " » ";
+
+/// The formats `--report` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Json,
+}
+
+/// A report to write, given on the command line as `junit=path.xml` or
+/// `json=path.json`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportTarget {
+    type Err = String;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        let (format, path) = target
+            .split_once('=')
+            .ok_or_else(|| format!("expected junit=PATH or json=PATH, got '{}'", target))?;
+        let format = match format {
+            "junit" => ReportFormat::Junit,
+            "json" => ReportFormat::Json,
+            other => return Err(format!("unknown report format '{}'", other)),
+        };
+        if path.is_empty() {
+            return Err("the report path is empty".to_string());
+        }
+        Ok(Self {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// How a single test case finished
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum CaseStatus {
+    Passed,
+    Failed {
+        message: String,
+    },
+    Skipped,
+    /// The suite couldn't run at all
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    pub classname: String,
+    #[serde(flatten)]
+    pub status: CaseStatus,
+}
+
+impl TestCase {
+    fn new(name: &str, classname: &str, status: CaseStatus) -> Self {
+        Self {
+            name: name.to_string(),
+            classname: classname.to_string(),
+            status,
+        }
+    }
+}
+
+/// One package's test run
+#[derive(Debug, Clone, Serialize)]
+pub struct SuiteReport {
+    pub name: String,
+    pub module: String,
+    pub duration_seconds: f64,
+    /// None when the tests couldn't be started
+    pub exit_code: Option<i32>,
+    pub cases: Vec<TestCase>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl SuiteReport {
+    /// Record a finished run. The exit code is always reflected in the cases:
+    /// without parsable output the whole suite is one case, and a failing
+    /// exit with only passing cases adds a failed case for the exit.
+    pub fn new(name: &str, module: &str, duration_seconds: f64, result: &Result<Captured>) -> Self {
+        let captured = match result {
+            Ok(captured) => captured,
+            Err(e) => {
+                return Self {
+                    name: name.to_string(),
+                    module: module.to_string(),
+                    duration_seconds,
+                    exit_code: None,
+                    cases: vec![TestCase::new(
+                        module,
+                        name,
+                        CaseStatus::Error {
+                            message: format!("{:#}", e),
+                        },
+                    )],
+                    stdout: String::new(),
+                    stderr: String::new(),
+                };
+            }
+        };
+
+        let mut cases = parse_cases(&captured.stdout, module);
+        let any_failed = cases.iter().any(|case| is_failure(&case.status));
+        if captured.code != 0 && !any_failed {
+            let exited = format!("exited with code {}", captured.code);
+            let name = if cases.is_empty() {
+                module.to_string()
+            } else {
+                format!("{} {}", module, exited)
+            };
+            cases.push(TestCase::new(
+                &name,
+                module,
+                CaseStatus::Failed { message: exited },
+            ));
+        } else if cases.is_empty() {
+            cases.push(TestCase::new(module, module, CaseStatus::Passed));
+        }
+
+        Self {
+            name: name.to_string(),
+            module: module.to_string(),
+            duration_seconds,
+            exit_code: Some(captured.code),
+            cases,
+            stdout: captured.stdout.clone(),
+            stderr: captured.stderr.clone(),
+        }
+    }
+
+    fn count(&self, status: fn(&CaseStatus) -> bool) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| status(&case.status))
+            .count()
+    }
+}
+
+fn is_failure(status: &CaseStatus) -> bool {
+    matches!(status, CaseStatus::Failed { .. })
+}
+
+fn is_error(status: &CaseStatus) -> bool {
+    matches!(status, CaseStatus::Error { .. })
+}
+
+fn is_skipped(status: &CaseStatus) -> bool {
+    matches!(status, CaseStatus::Skipped)
+}
+
+/// Write every requested report
+pub fn write_reports(targets: &[ReportTarget], suites: &[SuiteReport]) -> Result<()> {
+    for target in targets {
+        let contents = match target.format {
+            ReportFormat::Junit => render_junit(suites),
+            ReportFormat::Json => render_json(suites)?,
+        };
+        write_report(&target.path, &contents)?;
+    }
+    Ok(())
+}
+
+fn write_report(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).context("Failed to create report directory")?;
+        }
+    }
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write test report to {}", path.display()))
+}
+
+fn render_json(suites: &[SuiteReport]) -> Result<String> {
+    #[derive(Serialize)]
+    struct Report<'a> {
+        tests: usize,
+        failures: usize,
+        errors: usize,
+        skipped: usize,
+        duration_seconds: f64,
+        suites: &'a [SuiteReport],
+    }
+
+    let report = Report {
+        tests: suites.iter().map(|suite| suite.cases.len()).sum(),
+        failures: suites.iter().map(|suite| suite.count(is_failure)).sum(),
+        errors: suites.iter().map(|suite| suite.count(is_error)).sum(),
+        skipped: suites.iter().map(|suite| suite.count(is_skipped)).sum(),
+        duration_seconds: suites.iter().map(|suite| suite.duration_seconds).sum(),
+        suites,
+    };
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+fn render_junit(suites: &[SuiteReport]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        suites.iter().map(|suite| suite.cases.len()).sum::<usize>(),
+        suites
+            .iter()
+            .map(|suite| suite.count(is_failure))
+            .sum::<usize>(),
+        suites
+            .iter()
+            .map(|suite| suite.count(is_error))
+            .sum::<usize>(),
+        suites
+            .iter()
+            .map(|suite| suite.count(is_skipped))
+            .sum::<usize>(),
+        suites
+            .iter()
+            .map(|suite| suite.duration_seconds)
+            .sum::<f64>(),
+    );
+
+    for suite in suites {
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            escape_xml(&suite.name),
+            suite.cases.len(),
+            suite.count(is_failure),
+            suite.count(is_error),
+            suite.count(is_skipped),
+            suite.duration_seconds,
+        );
+        for case in &suite.cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape_xml(&case.name),
+                escape_xml(&case.classname)
+            );
+            let _ = match &case.status {
+                CaseStatus::Passed => writeln!(xml, "/>"),
+                CaseStatus::Skipped => writeln!(xml, ">\n      <skipped/>\n    </testcase>"),
+                CaseStatus::Failed { message } => writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    escape_xml(first_line(message)),
+                    escape_xml(message)
+                ),
+                CaseStatus::Error { message } => writeln!(
+                    xml,
+                    ">\n      <error message=\"{}\">{}</error>\n    </testcase>",
+                    escape_xml(first_line(message)),
+                    escape_xml(message)
+                ),
+            };
+        }
+        if !suite.stdout.is_empty() {
+            let _ = writeln!(
+                xml,
+                "    <system-out>{}</system-out>",
+                escape_xml(&suite.stdout)
+            );
+        }
+        if !suite.stderr.is_empty() {
+            let _ = writeln!(
+                xml,
+                "    <system-err>{}</system-err>",
+                escape_xml(&suite.stderr)
+            );
+        }
+        let _ = writeln!(xml, "  </testsuite>");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// Escape text for XML, dropping control characters XML can't contain
+fn escape_xml(text: &str) -> String {
+    let text = strip_ansi(text);
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Recover individual test cases from TAP or spec reporter output. Cases
+/// without a `describe` to group them are classed under `classname`, the
+/// suite's test module.
+pub fn parse_cases(output: &str, classname: &str) -> Vec<TestCase> {
+    let output = strip_ansi(output);
+    let lines: Vec<&str> = output.lines().collect();
+
+    let is_tap = lines
+        .iter()
+        .any(|line| line.starts_with("TAP version") || TAP_PLAN.is_match(line));
+    if is_tap {
+        parse_tap(&lines, classname)
+    } else {
+        parse_spec(&lines, classname)
+    }
+}
+
+/// `ok 1 - name`, `not ok 2 - name` and `# SKIP` / `# TODO` directives.
+/// Only top level results count, nested subtests are summarised by them.
+fn parse_tap(lines: &[&str], classname: &str) -> Vec<TestCase> {
+    let mut cases = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        let Some(result) = TAP_RESULT.captures(line) else {
+            continue;
+        };
+
+        let name = result.get(2).map_or("", |m| m.as_str());
+        let directive = result.get(3).map(|m| m.as_str().to_ascii_uppercase());
+        let status = if matches!(directive.as_deref(), Some("SKIP" | "TODO")) {
+            CaseStatus::Skipped
+        } else if result.get(1).is_some() {
+            // Diagnostics follow as an indented YAML block
+            let mut diagnostics = Vec::new();
+            while index < lines.len() && lines[index].starts_with(char::is_whitespace) {
+                let diagnostic = lines[index].trim();
+                if diagnostic != "---" && diagnostic != "..." {
+                    diagnostics.push(diagnostic);
+                }
+                index += 1;
+            }
+            let message = diagnostics
+                .iter()
+                .find_map(|line| line.strip_prefix("message:"))
+                .map(|message| message.trim().trim_matches(|c| c == '\'' || c == '"'))
+                .map(str::to_string)
+                .unwrap_or_else(|| diagnostics.join("\n"));
+            CaseStatus::Failed { message }
+        } else {
+            CaseStatus::Passed
+        };
+        cases.push(TestCase::new(name, classname, status));
+    }
+    cases
+}
+
+/// purescript-spec's spec reporter: `describe` names as headings, tests
+/// marked ✓ passed, ✗ failed (followed by the indented failure) or ~ pending
+fn parse_spec(lines: &[&str], module: &str) -> Vec<TestCase> {
+    let mut cases = Vec::new();
+    let mut headings: Vec<(usize, &str)> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        headings.retain(|(heading_indent, _)| *heading_indent < indent);
+        let classname = if headings.is_empty() {
+            module.to_string()
+        } else {
+            headings
+                .iter()
+                .map(|(_, heading)| *heading)
+                .collect::<Vec<&str>>()
+                .join(CLASSNAME_SEPARATOR)
+        };
+
+        let mut chars = text.chars();
+        let marker = chars.next().unwrap_or_default();
+        // The spec reporter follows ✓ with a text presentation selector
+        let name = chars.as_str().trim_start_matches('\u{fe0e}').trim();
+
+        if SPEC_PASSED.contains(&marker) {
+            cases.push(TestCase::new(name, &classname, CaseStatus::Passed));
+        } else if SPEC_FAILED.contains(&marker) {
+            let mut message = Vec::new();
+            while index < lines.len() {
+                let next = lines[index];
+                let next_indent = next.len() - next.trim_start().len();
+                if !next.trim().is_empty() && next_indent <= indent {
+                    break;
+                }
+                if !next.trim().is_empty() {
+                    message.push(next.trim());
+                }
+                index += 1;
+            }
+            cases.push(TestCase::new(
+                name.trim_end_matches(':'),
+                &classname,
+                CaseStatus::Failed {
+                    message: message.join("\n"),
+                },
+            ));
+        } else if marker == SPEC_PENDING {
+            cases.push(TestCase::new(name, &classname, CaseStatus::Skipped));
+        } else if indent == 0 && text == "Summary" {
+            // Totals and the failures repeated in full
+            break;
+        } else {
+            headings.push((indent, text));
+        }
+    }
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captured(code: i32, stdout: &str) -> Result<Captured> {
+        Ok(Captured {
+            code,
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        })
+    }
+
+    #[test]
+    fn test_report_target_from_str() {
+        assert_eq!(
+            "junit=reports/tests.xml".parse::<ReportTarget>().unwrap(),
+            ReportTarget {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("reports/tests.xml"),
+            }
+        );
+        assert_eq!(
+            "json=out.json".parse::<ReportTarget>().unwrap().format,
+            ReportFormat::Json
+        );
+        assert!("xml=out.xml".parse::<ReportTarget>().is_err());
+        assert!("junit".parse::<ReportTarget>().is_err());
+        assert!("junit=".parse::<ReportTarget>().is_err());
+    }
+
+    #[test]
+    fn test_parse_tap() {
+        let output = "\
+TAP version 13
+# Subtest: nested
+    ok 1 - inner
+ok 1 - adds numbers
+not ok 2 - parses dates
+  ---
+  message: 'expected 2020 to equal 2021'
+  ...
+ok 3 - talks to the network # SKIP offline
+1..3
+";
+        assert_eq!(
+            parse_cases(output, "Test.Main"),
+            vec![
+                TestCase::new("adds numbers", "Test.Main", CaseStatus::Passed),
+                TestCase::new(
+                    "parses dates",
+                    "Test.Main",
+                    CaseStatus::Failed {
+                        message: "expected 2020 to equal 2021".to_string()
+                    }
+                ),
+                TestCase::new("talks to the network", "Test.Main", CaseStatus::Skipped),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_spec_reporter() {
+        let output = "\
+✓\u{fe0e} runs without a describe
+\x1b[32mData.Array\x1b[0m
+  cons
+    ✓\u{fe0e} prepends
+    ✗ keeps order:
+      [2,1] ≠ [1,2]
+  ~ snoc
+Data.String
+  ✓\u{fe0e} trims
+
+Summary
+2/4 tests passed
+1) Data.Array cons keeps order
+";
+        assert_eq!(
+            parse_cases(output, "Test.Main"),
+            vec![
+                TestCase::new("runs without a describe", "Test.Main", CaseStatus::Passed),
+                TestCase::new("prepends", "Data.Array » cons", CaseStatus::Passed),
+                TestCase::new(
+                    "keeps order",
+                    "Data.Array » cons",
+                    CaseStatus::Failed {
+                        message: "[2,1] ≠ [1,2]".to_string()
+                    }
+                ),
+                TestCase::new("snoc", "Data.Array", CaseStatus::Skipped),
+                TestCase::new("trims", "Data.String", CaseStatus::Passed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suite_report_reflects_exit_code() {
+        let suite = SuiteReport::new("my-lib", "Test.Main", 1.0, &captured(0, "all good\n"));
+        assert_eq!(
+            suite.cases,
+            vec![TestCase::new("Test.Main", "Test.Main", CaseStatus::Passed)]
+        );
+
+        let suite = SuiteReport::new("my-lib", "Test.Main", 1.0, &captured(3, "ok 1 - a\n1..1\n"));
+        assert_eq!(suite.count(is_failure), 1);
+        assert_eq!(suite.cases[1].name, "Test.Main exited with code 3");
+
+        let suite = SuiteReport::new(
+            "my-lib",
+            "Test.Main",
+            0.0,
+            &Err(anyhow::anyhow!("Module Test.Main has not been compiled")),
+        );
+        assert_eq!(suite.exit_code, None);
+        assert_eq!(suite.count(is_error), 1);
+    }
+
+    #[test]
+    fn test_render_junit() {
+        let suites = vec![
+            SuiteReport::new(
+                "a-lib",
+                "Test.A",
+                0.5,
+                &captured(1, "ok 1 - works\nnot ok 2 - <breaks> & fails\n1..2\n"),
+            ),
+            SuiteReport::new("b-lib", "Test.B", 0.25, &captured(0, "")),
+        ];
+        let xml = render_junit(&suites);
+
+        assert!(xml.contains(
+            "<testsuites tests=\"3\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"0.750\">"
+        ));
+        assert!(xml.contains("<testsuite name=\"a-lib\" tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testcase name=\"&lt;breaks&gt; &amp; fails\" classname=\"Test.A\">"));
+        assert!(xml.contains("<testcase name=\"Test.B\" classname=\"Test.B\"/>"));
+        assert!(xml.contains("<system-out>ok 1 - works\n"));
+    }
+
+    #[test]
+    fn test_render_json() {
+        let suites = vec![SuiteReport::new(
+            "a-lib",
+            "Test.A",
+            0.5,
+            &captured(0, "ok 1 - works\nok 2 - # skip later\n1..2\n"),
+        )];
+        let json: serde_json::Value = serde_json::from_str(&render_json(&suites).unwrap()).unwrap();
+
+        assert_eq!(json["tests"], 2);
+        assert_eq!(json["skipped"], 1);
+        assert_eq!(json["suites"][0]["exit_code"], 0);
+        assert_eq!(json["suites"][0]["cases"][0]["status"], "passed");
+        assert_eq!(json["suites"][0]["cases"][0]["name"], "works");
+    }
+
+    #[test]
+    fn test_escape_xml_drops_control_characters() {
+        assert_eq!(escape_xml("a\u{0}b\x1b[31mc\"'"), "abc&quot;&apos;");
+    }
+}
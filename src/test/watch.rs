@@ -0,0 +1,324 @@
+//! `ragu test --watch`: recompile and re-run the tests whenever the
+//! package's sources, tests or dependencies change.
+
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::build::compiler::{self, CompilerOptions};
+use crate::build::fingerprint;
+use crate::build::resolve_test_sources;
+use crate::build::watch::{config_files, ChangeKind, SourceWatcher};
+use crate::compiler::version::check_compiler_version;
+use crate::config::SpagoConfig;
+use crate::install::install_all_dependencies;
+use crate::registry::PackageSet;
+use crate::run::runner;
+
+use super::{select_suite, test_program, TestArgs, TestSuite};
+
+/// Clears the screen and scrollback, then moves the cursor home
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[3J\x1B[H";
+
+/// Everything resolved once and kept between test runs. Only a spago.yaml
+/// change resolves it again.
+struct Session {
+    config: SpagoConfig,
+    /// The package's own directory, where its tests run
+    package_dir: PathBuf,
+    package_set: PackageSet,
+    suite: TestSuite,
+    sources: Vec<String>,
+    /// Inputs of the last successful compile, to skip unchanged rebuilds
+    fingerprint: Option<String>,
+}
+
+impl Session {
+    async fn start(
+        config: SpagoConfig,
+        package_dir: PathBuf,
+        suite_name: Option<&str>,
+        verbose: bool,
+    ) -> Result<Self> {
+        let suite = Self::suite(&config, suite_name)?;
+        check_compiler_version(&config, verbose).await?;
+        let package_set = config.package_set().await?;
+        install_all_dependencies(&config, &package_set, true).await?;
+        Self::resolve(config, package_dir, package_set, suite, suite_name, verbose).await
+    }
+
+    fn suite(config: &SpagoConfig, suite_name: Option<&str>) -> Result<TestSuite> {
+        let test_config = config
+            .package
+            .test
+            .as_ref()
+            .context("No main test package. Add a package.test.")?;
+        select_suite(test_config, suite_name)
+    }
+
+    /// Resolve the suite's sources against an installed package set
+    async fn resolve(
+        config: SpagoConfig,
+        package_dir: PathBuf,
+        package_set: PackageSet,
+        suite: TestSuite,
+        suite_name: Option<&str>,
+        verbose: bool,
+    ) -> Result<Self> {
+        let sources = resolve_test_sources(
+            &config.for_test_suite(suite_name),
+            package_set.clone(),
+            &suite.sources,
+            verbose,
+        )
+        .await?;
+        Ok(Self {
+            config,
+            package_dir,
+            package_set,
+            suite,
+            sources,
+            fingerprint: None,
+        })
+    }
+
+    fn watcher(&self) -> Result<SourceWatcher> {
+        SourceWatcher::new(
+            &self.sources,
+            &config_files(&self.config, &self.package_set),
+            &self.config.output_dir(),
+        )
+    }
+
+    /// Compile if the inputs changed since the last successful compile.
+    /// Returns whether the output is ready to test.
+    async fn compile(&mut self, verbose: bool) -> Result<bool> {
+        let options = CompilerOptions::from_config(&self.config, verbose)?;
+        let fingerprint = fingerprint::compute(&self.sources, &options).await?;
+        if self.fingerprint.as_ref() == Some(&fingerprint) {
+            println!("{} Build up to date", "✓".green());
+            return Ok(true);
+        }
+
+        let outcome = compiler::run_compiler(&self.sources, &options).await?;
+        if !outcome.success {
+            compiler::report_failure(&outcome);
+            self.fingerprint = None;
+            return Ok(false);
+        }
+        println!("{} Build successful", "✓".green());
+        self.fingerprint = Some(fingerprint);
+        Ok(true)
+    }
+
+    async fn run_tests(&self, args: &TestArgs) -> Result<()> {
+        let test_config = self
+            .config
+            .package
+            .test
+            .as_ref()
+            .context("No main test package. Add a package.test.")?;
+        let program = test_program(&self.config, test_config, &self.suite, args);
+
+        println!();
+        let code = runner::run_in(
+            &self.config.run_output_dir(),
+            &program,
+            Some(&self.package_dir),
+        )
+        .await?;
+        println!();
+        if code == 0 {
+            println!("{}", "✓ Tests passed".green());
+        } else {
+            eprintln!("❌ Tests failed");
+        }
+        Ok(())
+    }
+}
+
+/// Compile and test, then again after every change until interrupted.
+/// Failures are reported and the watch carries on.
+pub async fn execute(config: SpagoConfig, args: TestArgs, verbose: bool) -> Result<()> {
+    let package_dir = std::env::current_dir().context("Failed to get current directory")?;
+    let mut session =
+        Session::start(config, package_dir.clone(), args.suite.as_deref(), verbose).await?;
+    let mut watcher = session.watcher()?;
+
+    loop {
+        cycle(&mut session, &args, verbose).await;
+        println!("{}", "Watching for changes...".dimmed());
+
+        let change = match watcher.next_change().await {
+            Some(change) => change,
+            None => return Ok(()),
+        };
+        clear_screen();
+
+        if change == ChangeKind::Config {
+            println!("{} spago.yaml changed, reinstalling", "→".cyan());
+            let reloaded = match crate::config::load_config_cwd() {
+                Ok(config) => {
+                    Session::start(config, package_dir.clone(), args.suite.as_deref(), verbose)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match reloaded {
+                Ok(reloaded) => {
+                    session = reloaded;
+                    watcher = session.watcher()?;
+                }
+                Err(e) => eprintln!("{} {:#}", "Error:".red().bold(), e),
+            }
+        }
+    }
+}
+
+async fn cycle(session: &mut Session, args: &TestArgs, verbose: bool) {
+    let result = match session.compile(verbose).await {
+        Ok(true) => session.run_tests(args).await,
+        Ok(false) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("{} {:#}", "Error:".red().bold(), e);
+    }
+}
+
+fn clear_screen() {
+    if std::io::stdout().is_terminal() {
+        print!("{}", CLEAR_SCREEN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_config;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// A purs that answers `--version` and logs every compile
+    const FAKE_PURS: &str = "#!/bin/sh\n\
+        if [ \"$1\" = --version ]; then echo 0.15.15; exit 0; fi\n\
+        echo \"$1\" >> \"$(dirname \"$0\")/compiles\"\n";
+
+    fn write_config(root: &Path, main: &str) {
+        let config = format!(
+            "package:\n  name: app\n  dependencies: []\n  test:\n    suites:\n      \
+             - name: unit\n        main: {}\n        sources:\n          - {}/test/**/*.purs\n\
+             workspace:\n  compiler:\n    path: purs\n",
+            main,
+            root.display()
+        );
+        fs::write(root.join("spago.yaml"), config).unwrap();
+    }
+
+    fn workspace() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        write_config(root, "Test.Unit");
+        fs::create_dir_all(root.join(".spago")).unwrap();
+        fs::create_dir_all(root.join("test")).unwrap();
+        fs::write(root.join("test/Unit.purs"), "module Test.Unit where").unwrap();
+
+        let purs = root.join("purs");
+        fs::write(&purs, FAKE_PURS).unwrap();
+        fs::set_permissions(&purs, fs::Permissions::from_mode(0o755)).unwrap();
+        temp
+    }
+
+    async fn resolve_session(root: &Path) -> Session {
+        let config = load_config(root.join("spago.yaml"), false).unwrap();
+        let suite = Session::suite(&config, Some("unit")).unwrap();
+        Session::resolve(
+            config,
+            root.to_path_buf(),
+            PackageSet::new(),
+            suite,
+            Some("unit"),
+            false,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn compiles(root: &Path) -> usize {
+        fs::read_to_string(root.join("compiles"))
+            .map(|log| log.lines().count())
+            .unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn test_session_keeps_suite_and_sources() {
+        let temp = workspace();
+        let session = resolve_session(temp.path()).await;
+
+        assert_eq!(session.suite.main, "Test.Unit");
+        assert!(session
+            .sources
+            .contains(&format!("{}/test/**/*.purs", temp.path().display())));
+        assert_eq!(session.fingerprint, None);
+    }
+
+    #[tokio::test]
+    async fn test_compile_skips_unchanged_inputs() {
+        let temp = workspace();
+        let mut session = resolve_session(temp.path()).await;
+
+        assert!(session.compile(false).await.unwrap());
+        assert_eq!(compiles(temp.path()), 1);
+        assert!(session.fingerprint.is_some());
+
+        // Nothing changed, so the same session doesn't run purs again
+        assert!(session.compile(false).await.unwrap());
+        assert_eq!(compiles(temp.path()), 1);
+
+        fs::write(
+            temp.path().join("test/Unit.purs"),
+            "module Test.Unit where\n\nx = 1",
+        )
+        .unwrap();
+        assert!(session.compile(false).await.unwrap());
+        assert_eq!(compiles(temp.path()), 2);
+    }
+
+    #[tokio::test]
+    async fn test_config_change_starts_a_new_session() {
+        let temp = workspace();
+        let mut session = resolve_session(temp.path()).await;
+        session.compile(false).await.unwrap();
+
+        write_config(temp.path(), "Test.Unit.Main");
+        let reloaded = resolve_session(temp.path()).await;
+
+        assert_eq!(reloaded.suite.main, "Test.Unit.Main");
+        assert_eq!(reloaded.fingerprint, None);
+        assert_eq!(reloaded.sources, session.sources);
+    }
+
+    #[tokio::test]
+    async fn test_config_reload_rejects_a_removed_suite() {
+        let temp = workspace();
+        fs::write(
+            temp.path().join("spago.yaml"),
+            "package:\n  name: app\n  dependencies: []\n  test:\n    main: Test.Main\n\
+             workspace:\n  compiler:\n    path: purs\n",
+        )
+        .unwrap();
+
+        // The suite is checked before any package set is fetched, so the
+        // watch can keep the old session and report the error
+        let config = load_config(temp.path().join("spago.yaml"), false).unwrap();
+        let error = Session::start(config, temp.path().to_path_buf(), Some("unit"), false)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("No test suite named unit"));
+    }
+}
@@ -0,0 +1,380 @@
+//! `ragu test --workspace`: run the test suite of every package in the
+//! workspace after a single compile.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+
+use crate::config::SpagoConfig;
+use crate::registry::{Package, PackageName, PackageQuery, PackageSet};
+use crate::run::runner::Program;
+
+use super::report::write_reports;
+use super::{compile, run_suite, select_suite, test_program, TestArgs};
+
+/// A package's tests, ready to run from its directory
+#[derive(Debug)]
+struct Suite {
+    package: PackageName,
+    dir: PathBuf,
+    program: Program,
+    /// The suite's source globs, relative to the workspace root
+    sources: Vec<String>,
+    /// The package's test dependencies for this suite
+    dependencies: Vec<PackageName>,
+}
+
+/// How a package's tests finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failed(i32),
+    /// The tests couldn't be started at all
+    Error(String),
+}
+
+pub async fn execute(
+    config: &SpagoConfig,
+    args: TestArgs,
+    skip_compilation: bool,
+    verbose: bool,
+) -> Result<()> {
+    let package_set = config.package_set().await?;
+    let suites = discover_suites(config, &package_set, &args, verbose);
+    if suites.is_empty() {
+        println!("No workspace packages have tests. Add a package.test to their spago.yaml.");
+        return Ok(());
+    }
+
+    if !skip_compilation {
+        let mut test_sources: Vec<String> = Vec::new();
+        for source in suites.iter().flat_map(|suite| &suite.sources) {
+            if !test_sources.contains(source) {
+                test_sources.push(source.clone());
+            }
+        }
+        compile(
+            &config.for_test_suite(args.suite.as_deref()),
+            with_suite_dependencies(package_set, &suites),
+            true,
+            &test_sources,
+            verbose,
+        )
+        .await?;
+    }
+
+    let jobs = args.jobs.max(1);
+    println!(
+        "{} Testing {} packages{}",
+        "→".cyan(),
+        suites.len(),
+        if jobs > 1 {
+            format!(", {} at a time", jobs)
+        } else {
+            String::new()
+        }
+    );
+
+    let output_dir = config.run_output_dir();
+    let reporting = !args.reports.is_empty();
+    let mut results = Vec::new();
+    let mut reports = Vec::new();
+    if jobs == 1 {
+        // One at a time, so output can stream straight through
+        for suite in &suites {
+            println!();
+            println!("{} {}", "→".cyan(), suite.package.0.bold());
+            let (code, report) = run_suite(
+                &output_dir,
+                &suite.package.0,
+                &suite.program,
+                Some(&suite.dir),
+                reporting,
+                true,
+            )
+            .await;
+            results.push((suite.package.clone(), outcome(code)));
+            reports.extend(report);
+        }
+    } else {
+        // Output is held back until each package finishes so it doesn't interleave
+        let finished: Vec<_> = stream::iter(&suites)
+            .map(|suite| async {
+                let (code, report) = run_suite(
+                    &output_dir,
+                    &suite.package.0,
+                    &suite.program,
+                    Some(&suite.dir),
+                    true,
+                    false,
+                )
+                .await;
+                println!();
+                println!("{} {}", "→".cyan(), suite.package.0.bold());
+                if let Some(report) = &report {
+                    print!("{}", report.stdout);
+                    eprint!("{}", report.stderr);
+                }
+                (suite.package.clone(), outcome(code), report)
+            })
+            .buffer_unordered(jobs)
+            .collect()
+            .await;
+
+        for (package, outcome, report) in finished {
+            results.push((package, outcome));
+            reports.extend(report);
+        }
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        reports.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    if reporting {
+        write_reports(&args.reports, &reports)?;
+    }
+
+    println!();
+    print!("{}", render_summary(&results));
+
+    if results
+        .iter()
+        .any(|(_, outcome)| *outcome != Outcome::Passed)
+    {
+        eprintln!("❌ Tests failed");
+        std::process::exit(1);
+    }
+    println!("{}", "✓ Tests passed".green());
+    Ok(())
+}
+
+/// The root package's tests, then every local package with a `package.test`.
+/// With `--suite`, packages without that suite are skipped.
+fn discover_suites(
+    config: &SpagoConfig,
+    package_set: &PackageSet,
+    args: &TestArgs,
+    verbose: bool,
+) -> Vec<Suite> {
+    let mut suites = Vec::new();
+    let mut add = |package: &PackageName, dir: &PathBuf, package_config: &SpagoConfig| {
+        let Some(test_config) = &package_config.package.test else {
+            if verbose {
+                println!("  {} has no tests", package.0);
+            }
+            return;
+        };
+        match select_suite(test_config, args.suite.as_deref()) {
+            Ok(suite) => suites.push(Suite {
+                package: package.clone(),
+                dir: dir.clone(),
+                program: test_program(package_config, test_config, &suite, args),
+                sources: suite
+                    .sources
+                    .iter()
+                    .map(|glob| package_glob(dir, glob))
+                    .collect(),
+                dependencies: package_config
+                    .for_test_suite(args.suite.as_deref())
+                    .test_dependencies()
+                    .into_iter()
+                    .cloned()
+                    .collect(),
+            }),
+            Err(e) if verbose => println!("  Skipping {}: {:#}", package.0, e),
+            Err(_) => {}
+        }
+    };
+
+    if config.package.test.is_some() {
+        add(&config.package.name, &config.workspace_root, config);
+    }
+
+    let query = PackageQuery::new(package_set);
+    let mut local_packages = query.local_packages();
+    local_packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for package in local_packages {
+        let package_config = match crate::config::load_config(package.path.join("spago.yaml"), true)
+        {
+            Ok(package_config) => package_config,
+            Err(e) => {
+                eprintln!("⚠️  Skipping {}: {}", package.name.0, e);
+                continue;
+            }
+        };
+        add(&package.name, &package.path, &package_config);
+    }
+
+    suites
+}
+
+/// A suite's glob, which is relative to its package, from the workspace root
+fn package_glob(dir: &Path, glob: &str) -> String {
+    dir.join(glob.trim_start_matches("./"))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The package set with each local package's test dependencies narrowed to
+/// those of the suite being run, and none for packages whose tests aren't
+fn with_suite_dependencies(mut package_set: PackageSet, suites: &[Suite]) -> PackageSet {
+    for package in package_set.values_mut() {
+        if let Package::Local(local) = package {
+            local.test_dependencies = suites
+                .iter()
+                .find(|suite| suite.package == local.name)
+                .map(|suite| suite.dependencies.clone())
+                .unwrap_or_default();
+        }
+    }
+    package_set
+}
+
+fn outcome(code: Result<i32>) -> Outcome {
+    match code {
+        Ok(0) => Outcome::Passed,
+        Ok(code) => Outcome::Failed(code),
+        Err(e) => Outcome::Error(format!("{:#}", e)),
+    }
+}
+
+fn render_summary(results: &[(PackageName, Outcome)]) -> String {
+    let mut summary = String::from("Test summary\n");
+    for (package, outcome) in results {
+        let _ = match outcome {
+            Outcome::Passed => writeln!(summary, "  {} {}", "✓".green(), package.0),
+            Outcome::Failed(code) => {
+                writeln!(summary, "  ❌ {} (exit code {})", package.0, code)
+            }
+            Outcome::Error(message) => writeln!(summary, "  ❌ {} ({})", package.0, message),
+        };
+    }
+
+    let passed = results
+        .iter()
+        .filter(|(_, outcome)| *outcome == Outcome::Passed)
+        .count();
+    let _ = writeln!(summary, "{} of {} packages passed", passed, results.len());
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::LocalPackage;
+    use std::fs;
+
+    #[test]
+    fn test_discover_suites() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("spago.yaml"), "workspace: {}\n").unwrap();
+
+        let mut package_set = PackageSet::new();
+        for (name, spago_yaml) in [
+            (
+                "b-lib",
+                "package:\n  name: b-lib\n  test:\n    main: Test.B\n    runtime: bun\n",
+            ),
+            (
+                "a-lib",
+                "package:\n  name: a-lib\n  test:\n    main: Test.A\n    dependencies: [spec]\n    suites:\n      - name: integration\n        main: Test.A.Integration\n        sources: [\"./integration/**/*.purs\"]\n        dependencies: [node-fs]\n",
+            ),
+            ("no-tests", "package:\n  name: no-tests\n"),
+        ] {
+            let dir = root.path().join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("spago.yaml"), spago_yaml).unwrap();
+            package_set.insert(
+                PackageName::new(name),
+                Package::Local(LocalPackage {
+                    name: PackageName::new(name),
+                    dependencies: vec![],
+                    test_dependencies: vec![PackageName::new("stale")],
+                    path: dir,
+                }),
+            );
+        }
+
+        let config = crate::config::load_config(root.path().join("spago.yaml"), false).unwrap();
+        let args = TestArgs {
+            function: Some("spec".to_string()),
+            ..Default::default()
+        };
+        let suites = discover_suites(&config, &package_set, &args, false);
+
+        let found: Vec<(&str, &str, String)> = suites
+            .iter()
+            .map(|suite| {
+                (
+                    suite.package.0.as_str(),
+                    suite.program.module.as_str(),
+                    suite.program.runtime.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a-lib", "Test.A", "node".to_string()),
+                ("b-lib", "Test.B", "bun".to_string()),
+            ]
+        );
+        assert!(suites.iter().all(|suite| suite.program.function == "spec"));
+        assert_eq!(suites[0].dir, root.path().join("a-lib"));
+        assert_eq!(
+            suites[0].sources,
+            vec![format!(
+                "{}/test/**/*.purs",
+                root.path().join("a-lib").display()
+            )]
+        );
+
+        // Each package compiles only the selected suite's sources and
+        // dependencies
+        let args = TestArgs {
+            suite: Some("integration".to_string()),
+            ..Default::default()
+        };
+        let suites = discover_suites(&config, &package_set, &args, false);
+        assert_eq!(suites.len(), 1);
+        assert_eq!(
+            suites[0].sources,
+            vec![format!(
+                "{}/integration/**/*.purs",
+                root.path().join("a-lib").display()
+            )]
+        );
+
+        let package_set = with_suite_dependencies(package_set, &suites);
+        let test_dependencies = |name: &str| match &package_set[&PackageName::new(name)] {
+            Package::Local(package) => package.test_dependencies.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            test_dependencies("a-lib"),
+            vec![PackageName::new("node-fs"), PackageName::new("spec")]
+        );
+        assert!(test_dependencies("b-lib").is_empty());
+    }
+
+    #[test]
+    fn test_render_summary() {
+        colored::control::set_override(false);
+        let summary = render_summary(&[
+            (PackageName::new("a-lib"), Outcome::Passed),
+            (PackageName::new("b-lib"), Outcome::Failed(2)),
+            (
+                PackageName::new("c-lib"),
+                Outcome::Error("Module Test.C has not been compiled".to_string()),
+            ),
+        ]);
+
+        assert!(summary.contains("✓ a-lib"));
+        assert!(summary.contains("❌ b-lib (exit code 2)"));
+        assert!(summary.contains("❌ c-lib (Module Test.C has not been compiled)"));
+        assert!(summary.ends_with("1 of 3 packages passed\n"));
+    }
+}
@@ -1,13 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use colored::Colorize;
 
 use crate::config::{add_packages_to_config, load_config_cwd, remove_packages_from_config};
-use crate::imports::extract_imports_from_sources;
+use crate::imports::{extract_imports_from_sources, ImportInfo};
 use crate::modules::discover_all_modules;
 use crate::registry::{LocalPackage, PackageName, PackageQuery};
+use crate::sources::BuildSources;
 
 pub async fn execute_local_packages() -> Result<()> {
     let config = load_config_cwd()?;
@@ -101,10 +102,7 @@ pub async fn fetch_workspace_dependency_stats() -> Result<DependencyStats> {
     )
     .await?;
 
-    let workspace_modules = discover_all_modules(&workspace_sources)?
-        .iter()
-        .map(|m| (m.name.clone(), PackageName::new(&m.package_name)))
-        .collect::<HashMap<String, PackageName>>();
+    let workspace_modules = module_packages(&workspace_sources)?;
 
     let mut to_install: HashMap<PackageName, HashSet<PackageName>> = HashMap::new();
     let mut to_uninstall: HashMap<PackageName, HashSet<PackageName>> = HashMap::new();
@@ -113,39 +111,25 @@ pub async fn fetch_workspace_dependency_stats() -> Result<DependencyStats> {
     for local_package in all_local_packages {
         let imports = extract_imports_from_sources(local_package.path.as_path())?;
         let deps: HashSet<&PackageName> = local_package.dependencies.iter().collect();
-        let mut installed = HashSet::new();
-        for import in imports {
-            if PRIMITIVE.contains(&import.module_name.as_str()) {
-                continue; // Is primitive, ignore
-            }
-            if let Some(import_package) = workspace_modules.get(&import.module_name) {
-                if import_package.0 == local_package.name.0 {
-                    continue; // Is current package, ignore
-                }
-                if deps.contains(import_package) {
-                    installed.insert(import_package);
-                    continue; // direct dependency, ignore
-                }
+        let analysis = analyze_imports(&local_package.name, &imports, &deps, &workspace_modules);
 
-                to_install
-                    .entry(local_package.name.clone())
-                    .or_insert_with(HashSet::new)
-                    .insert(import_package.clone());
-            } else {
-                not_found
-                    .entry(local_package.name.clone())
-                    .or_insert_with(HashSet::new)
-                    .insert(import.module_name);
-            }
+        if !analysis.undeclared.is_empty() {
+            to_install.insert(
+                local_package.name.clone(),
+                analysis.undeclared.into_keys().collect(),
+            );
         }
-
-        for dep in deps {
-            if !installed.contains(dep) {
-                to_uninstall
-                    .entry(local_package.name.clone())
-                    .or_insert_with(HashSet::new)
-                    .insert(dep.clone());
-            }
+        if !analysis.unused.is_empty() {
+            to_uninstall.insert(
+                local_package.name.clone(),
+                analysis.unused.into_iter().collect(),
+            );
+        }
+        if !analysis.not_found.is_empty() {
+            not_found.insert(
+                local_package.name.clone(),
+                analysis.not_found.into_iter().collect(),
+            );
         }
     }
 
@@ -156,6 +140,72 @@ pub async fn fetch_workspace_dependency_stats() -> Result<DependencyStats> {
     })
 }
 
+/// Map every module name in the sources to the package providing it
+pub fn module_packages(sources: &BuildSources) -> Result<HashMap<String, PackageName>> {
+    Ok(discover_all_modules(sources)?
+        .into_iter()
+        .map(|m| (m.name, PackageName::new(&m.package_name)))
+        .collect())
+}
+
+/// How a package's imports line up with the dependencies it declares
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportAnalysis {
+    /// Packages imported from without being declared, with the imports that need them
+    pub undeclared: BTreeMap<PackageName, Vec<ImportInfo>>,
+    /// Declared dependencies that nothing imports from
+    pub unused: BTreeSet<PackageName>,
+    /// Imported modules that no known package provides
+    pub not_found: BTreeSet<String>,
+}
+
+impl ImportAnalysis {
+    pub fn is_clean(&self) -> bool {
+        self.undeclared.is_empty() && self.unused.is_empty() && self.not_found.is_empty()
+    }
+}
+
+/// Check a package's imports against its declared dependencies.
+///
+/// Imports of primitive modules and of the package's own modules are ignored.
+pub fn analyze_imports(
+    package: &PackageName,
+    imports: &[ImportInfo],
+    dependencies: &HashSet<&PackageName>,
+    modules: &HashMap<String, PackageName>,
+) -> ImportAnalysis {
+    let mut analysis = ImportAnalysis::default();
+    let mut used = HashSet::new();
+
+    for import in imports {
+        if PRIMITIVE.contains(&import.module_name.as_str()) {
+            continue;
+        }
+        match modules.get(&import.module_name) {
+            Some(import_package) if import_package == package => {}
+            Some(import_package) if dependencies.contains(import_package) => {
+                used.insert(import_package);
+            }
+            Some(import_package) => analysis
+                .undeclared
+                .entry(import_package.clone())
+                .or_default()
+                .push(import.clone()),
+            None => {
+                analysis.not_found.insert(import.module_name.clone());
+            }
+        }
+    }
+
+    analysis.unused = dependencies
+        .iter()
+        .filter(|dep| !used.contains(*dep))
+        .map(|dep| (*dep).clone())
+        .collect();
+
+    analysis
+}
+
 pub fn display_dependency_stats(
     package: &PackageName,
     stats: &DependencyStats,
@@ -307,3 +357,53 @@ const PRIMITIVE: [&str; 5] = [
     "Prim.TypeError",
     "Prim.Boolean",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(module_name: &str) -> ImportInfo {
+        ImportInfo {
+            module_name: module_name.to_string(),
+            file_path: "src/Main.purs".to_string(),
+            line_number: 1,
+        }
+    }
+
+    #[test]
+    fn test_analyze_imports() {
+        let package = PackageName::new("my-app");
+        let modules: HashMap<String, PackageName> = [
+            ("Main.Util", "my-app"),
+            ("Prelude", "prelude"),
+            ("Data.Array", "arrays"),
+            ("Data.String", "strings"),
+        ]
+        .into_iter()
+        .map(|(module, package)| (module.to_string(), PackageName::new(package)))
+        .collect();
+        let prelude = PackageName::new("prelude");
+        let strings = PackageName::new("strings");
+        let dependencies: HashSet<&PackageName> = [&prelude, &strings].into_iter().collect();
+        let imports = vec![
+            import("Prelude"),
+            import("Main.Util"),
+            import("Prim.Row"),
+            import("Data.Array"),
+            import("Data.Missing"),
+        ];
+
+        let analysis = analyze_imports(&package, &imports, &dependencies, &modules);
+
+        assert_eq!(
+            analysis.undeclared.keys().collect::<Vec<_>>(),
+            vec![&PackageName::new("arrays")]
+        );
+        assert_eq!(analysis.unused, BTreeSet::from([strings.clone()]));
+        assert_eq!(
+            analysis.not_found,
+            BTreeSet::from(["Data.Missing".to_string()])
+        );
+        assert!(!analysis.is_clean());
+    }
+}